@@ -0,0 +1,142 @@
+extern crate libinko;
+extern crate getopts;
+
+use std::io::prelude::*;
+use std::io::{self, Write};
+use std::env;
+use std::fs::File;
+use std::process;
+
+use libinko::heap_snapshot::analysis::{self, Dominators};
+use libinko::heap_snapshot::reader::{self, Snapshot};
+
+/// The number of prototypes to show by default.
+const DEFAULT_LIMIT: usize = 20;
+
+fn print_usage(options: &getopts::Options) -> ! {
+    print_stderr(format!("{}",
+                         options.usage("Usage: iheap SNAPSHOT [OPTIONS]")));
+
+    process::exit(1);
+}
+
+fn print_stderr(message: String) {
+    let mut stderr = io::stderr();
+
+    stderr.write(message.as_bytes()).unwrap();
+    stderr.write(b"\n").unwrap();
+    stderr.flush().unwrap();
+}
+
+fn terminate(message: String) -> ! {
+    print_stderr(message);
+    process::exit(1);
+}
+
+fn read_snapshot(path: &str) -> Snapshot {
+    let mut input = String::new();
+
+    if let Err(error) = File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut input)) {
+        terminate(format!("Failed to read {}: {}", path, error));
+    }
+
+    match Snapshot::parse(&input) {
+        Ok(snapshot) => snapshot,
+        Err(message) => terminate(format!("Failed to parse {}: {}",
+                                          path,
+                                          message)),
+    }
+}
+
+fn print_summary(snapshot: &Snapshot, limit: usize) {
+    let dominators = Dominators::new(snapshot);
+    let summaries = dominators.summarize(snapshot);
+
+    println!("{:>12} {:>12} {:>8}  {}",
+             "Retained",
+             "Shallow",
+             "Objects",
+             "Prototype");
+
+    for summary in summaries.iter().take(limit) {
+        println!("{:>12} {:>12} {:>8}  {}",
+                 summary.retained_size,
+                 summary.shallow_size,
+                 summary.count,
+                 snapshot.prototype_name(summary.prototype));
+    }
+
+    if summaries.len() > limit {
+        println!("\n{} more prototypes not shown", summaries.len() - limit);
+    }
+}
+
+fn print_path(snapshot: &Snapshot, address: usize) {
+    let path = match analysis::shortest_path(snapshot, address) {
+        Some(path) => path,
+        None => {
+            terminate(format!("The object {:#x} is not reachable from any \
+                               root",
+                              address))
+        }
+    };
+
+    println!("process {}", path.pid);
+
+    for step in path.steps.iter() {
+        println!("  {} -> {}", step.label, snapshot.describe(step.address));
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = getopts::Options::new();
+
+    options.optflag("h", "help", "Shows this help message");
+
+    options.optopt("p",
+                   "path",
+                   "Shows the shortest path from the roots to an object",
+                   "ADDRESS");
+
+    options.optopt("l",
+                   "limit",
+                   "The number of prototypes to show, defaults to 20",
+                   "LIMIT");
+
+    let matches = match options.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(error) => {
+            print_stderr(format!("{}", error.to_string()));
+            print_usage(&options);
+        }
+    };
+
+    if matches.opt_present("h") || matches.free.is_empty() {
+        print_usage(&options);
+    }
+
+    let snapshot = read_snapshot(&matches.free[0]);
+
+    if let Some(address) = matches.opt_str("p") {
+        match reader::parse_address(&address, 0) {
+            Ok(address) => print_path(&snapshot, address),
+            Err(_) => terminate(format!("The address {:?} is invalid", address)),
+        }
+
+        return;
+    }
+
+    let limit = match matches.opt_str("l") {
+        Some(limit) => {
+            match limit.parse::<usize>() {
+                Ok(limit) => limit,
+                Err(_) => terminate(format!("The limit {:?} is invalid", limit)),
+            }
+        }
+        None => DEFAULT_LIMIT,
+    };
+
+    print_summary(&snapshot, limit);
+}
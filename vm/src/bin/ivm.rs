@@ -5,13 +5,18 @@ use std::io::prelude::*;
 use std::io::{self, Write};
 use std::env;
 use std::fs::File;
+use std::path::PathBuf;
 use std::process;
+use std::sync::atomic::Ordering;
 
 use libinko::bytecode_parser;
 use libinko::config::Config;
 use libinko::vm::machine::Machine;
 use libinko::vm::state::State;
 
+/// The exit status to use when the program was terminated due to a deadlock.
+const DEADLOCK_STATUS: i32 = 3;
+
 fn print_usage(options: &getopts::Options) -> ! {
     print_stderr(format!("{}", options.usage("Usage: ivm FILE [OPTIONS]")));
 
@@ -27,8 +32,20 @@ fn print_stderr(message: String) {
 }
 
 fn terminate(message: String) -> ! {
+    terminate_with_status(message, 1);
+}
+
+fn terminate_with_status(message: String, status: i32) -> ! {
     print_stderr(message);
-    process::exit(1);
+    process::exit(status);
+}
+
+/// Returns the block limit given for the option, if any.
+fn block_limit(matches: &getopts::Matches, option: &str) -> Option<usize> {
+    matches.opt_str(option).map(|limit| match limit.parse::<usize>() {
+        Ok(limit) => limit,
+        Err(_) => terminate(format!("The {} {:?} is invalid", option, limit)),
+    })
 }
 
 fn main() {
@@ -43,6 +60,84 @@ fn main() {
                      "A directory to search for bytecode files",
                      "DIR");
 
+    options.optmulti("",
+                     "pool",
+                     "Adds a process pool with the given number of threads",
+                     "NAME=THREADS");
+
+    options.optopt("",
+                   "node",
+                   "Enables distribution using the given node name",
+                   "NAME");
+
+    options.optopt("",
+                   "node-directory",
+                   "The directory containing the sockets of all nodes",
+                   "DIR");
+
+    options.optopt("",
+                   "mailbox-capacity",
+                   "The maximum number of messages a mailbox can hold",
+                   "SIZE");
+
+    options.optopt("",
+                   "mailbox-policy",
+                   "What to do when a mailbox is full: suspend, error, or drop",
+                   "POLICY");
+
+    options.optopt("",
+                   "young-block-limit",
+                   "The maximum number of blocks the young generation of a \
+                    process can use",
+                   "BLOCKS");
+
+    options.optopt("",
+                   "mature-block-limit",
+                   "The maximum number of blocks the mature generation of a \
+                    process can use",
+                   "BLOCKS");
+
+    options.optopt("",
+                   "mailbox-block-limit",
+                   "The maximum number of blocks the mailbox of a process can \
+                    use",
+                   "BLOCKS");
+
+    options.optopt("",
+                   "block-limit",
+                   "The maximum number of blocks all processes combined can \
+                    use",
+                   "BLOCKS");
+
+    options.optopt("",
+                   "free-block-limit",
+                   "The maximum number of free blocks to retain, 0 to never \
+                    release free blocks",
+                   "BLOCKS");
+
+    options.optopt("",
+                   "gc-log",
+                   "Logs every garbage collection to a file, or to STDERR \
+                    when set to \"stderr\"",
+                   "FILE");
+
+    options.optflag("",
+                   "gc-stress",
+                   "Collects garbage at every safepoint and verifies the heap \
+                    afterwards, for debugging the garbage collector");
+
+    options.optopt("",
+                   "heap-snapshot",
+                   "Writes a heap snapshot of all processes that are not \
+                    running to a file when the VM terminates",
+                   "FILE");
+
+    options.optopt("",
+                   "scheduler-seed",
+                   "Runs all processes on a single thread, in an order based \
+                    on the given seed",
+                   "SEED");
+
     let matches = match options.parse(&args[1..]) {
         Ok(matches) => matches,
         Err(error) => {
@@ -74,6 +169,77 @@ fn main() {
 
         config.populate_from_env();
 
+        if matches.opt_present("pool") {
+            for pool in matches.opt_strs("pool") {
+                if let Err(message) = config.add_pool_from_string(&pool) {
+                    terminate(message);
+                }
+            }
+        }
+
+        if let Some(size) = matches.opt_str("mailbox-capacity") {
+            match size.parse::<usize>() {
+                Ok(size) => config.mailbox_capacity = size,
+                Err(_) => {
+                    terminate(format!("The mailbox capacity {:?} is invalid",
+                                      size))
+                }
+            }
+        }
+
+        if let Some(limit) = block_limit(&matches, "young-block-limit") {
+            config.young_block_limit = limit;
+        }
+
+        if let Some(limit) = block_limit(&matches, "mature-block-limit") {
+            config.mature_block_limit = limit;
+        }
+
+        if let Some(limit) = block_limit(&matches, "mailbox-block-limit") {
+            config.mailbox_block_limit = limit;
+        }
+
+        if let Some(limit) = block_limit(&matches, "block-limit") {
+            config.block_limit = limit;
+        }
+
+        if let Some(limit) = block_limit(&matches, "free-block-limit") {
+            config.free_block_limit = limit;
+        }
+
+        if let Some(policy) = matches.opt_str("mailbox-policy") {
+            match policy.parse() {
+                Ok(policy) => config.mailbox_policy = policy,
+                Err(message) => terminate(message),
+            }
+        }
+
+        if let Some(seed) = matches.opt_str("scheduler-seed") {
+            if let Err(message) = config.set_scheduler_seed_from_string(&seed) {
+                terminate(message);
+            }
+        }
+
+        if let Some(target) = matches.opt_str("gc-log") {
+            config.set_gc_log(target);
+        }
+
+        if matches.opt_present("gc-stress") {
+            config.gc_stress = true;
+        }
+
+        if let Some(path) = matches.opt_str("heap-snapshot") {
+            config.set_heap_snapshot(path);
+        }
+
+        if let Some(name) = matches.opt_str("node") {
+            config.set_node_name(name);
+        }
+
+        if let Some(directory) = matches.opt_str("node-directory") {
+            config.node_directory = PathBuf::from(directory);
+        }
+
         match File::open(path) {
             Ok(file) => {
                 let mut bytes = file.bytes();
@@ -84,7 +250,14 @@ fn main() {
 
                         match vm.start(code) {
                             Ok(_) => process::exit(0),
-                            Err(message) => terminate(message),
+                            Err(message) => {
+                                if vm.state.deadlocked.load(Ordering::Acquire) {
+                                    terminate_with_status(message,
+                                                          DEADLOCK_STATUS);
+                                } else {
+                                    terminate(message);
+                                }
+                            }
                         }
                     }
                     Err(error) => {
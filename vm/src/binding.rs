@@ -114,6 +114,16 @@ impl Binding {
         unsafe { &mut *self.locals.get() }
     }
 
+    /// Releases any unused memory of the local variables of this binding and
+    /// its parents.
+    pub fn shrink(&self) {
+        self.locals_mut().shrink_to_fit();
+
+        if let Some(ref parent) = self.parent {
+            parent.shrink();
+        }
+    }
+
     /// Pushes all pointers in this binding into the supplied vector.
     pub fn push_pointers(&self, pointers: &mut Vec<ObjectPointerPointer>) {
         for pointer in self.pointers() {
@@ -277,6 +287,21 @@ mod tests {
         assert_eq!(binding.locals_mut().len(), 1);
     }
 
+    #[test]
+    fn test_shrink() {
+        let ptr = ObjectPointer::null();
+        let parent = Binding::new(ptr);
+        let binding = Binding::with_parent(ptr, parent.clone());
+
+        parent.locals_mut().reserve(10);
+        binding.locals_mut().reserve(10);
+        binding.set_local(0, ptr);
+        binding.shrink();
+
+        assert_eq!(binding.locals().capacity(), 1);
+        assert_eq!(parent.locals().capacity(), 0);
+    }
+
     #[test]
     fn test_push_pointers() {
         let self_obj1 = ObjectPointer::new(0x1 as RawObjectPointer);
@@ -0,0 +1,179 @@
+//! Channels for sending messages to a process.
+//!
+//! A channel is an inbox owned by a single process, separate from the mailbox
+//! of said process. A process can create any number of channels, and hand
+//! them to other processes so these can send messages to it (e.g. a reply to
+//! a request).
+//!
+//! Messages sent to a channel are copied into the mailbox heap of the owning
+//! process, just like regular messages. As such only the owner of a channel
+//! can receive messages from it, copying a message into its own heap when
+//! doing so.
+//!
+//! A channel can be bounded, in which case no messages can be sent to it
+//! while it's full.
+
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use object_pointer::{ObjectPointer, ObjectPointerPointer};
+use process_table::PID;
+
+pub type RcChannel = Arc<Channel>;
+
+/// The result of sending a message to a channel.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SendResult {
+    /// The message was added to the channel.
+    Sent,
+
+    /// The channel is full.
+    Full,
+
+    /// The channel has been closed.
+    Closed,
+}
+
+pub struct Channel {
+    /// The PID of the process that owns this channel.
+    pub owner: PID,
+
+    /// The maximum number of messages that can be queued, if any.
+    pub capacity: Option<usize>,
+
+    /// The messages in this channel, allocated in the mailbox heap of the
+    /// owner.
+    messages: Mutex<VecDeque<ObjectPointer>>,
+
+    /// Set to true once the channel has been closed.
+    closed: AtomicBool,
+}
+
+impl Channel {
+    pub fn new(owner: PID, capacity: Option<usize>) -> RcChannel {
+        Arc::new(Channel {
+            owner: owner,
+            capacity: capacity,
+            messages: Mutex::new(VecDeque::new()),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Adds a message to the channel.
+    ///
+    /// The message is produced by the given closure, which is only called if
+    /// the message can actually be added to the channel.
+    pub fn push_with<F>(&self, message: F) -> SendResult
+        where F: FnOnce() -> ObjectPointer
+    {
+        if self.is_closed() {
+            return SendResult::Closed;
+        }
+
+        let mut messages = self.messages.lock();
+
+        if let Some(capacity) = self.capacity {
+            if messages.len() >= capacity {
+                return SendResult::Full;
+            }
+        }
+
+        messages.push_back(message());
+
+        SendResult::Sent
+    }
+
+    /// Removes the oldest message from the channel.
+    pub fn pop(&self) -> Option<ObjectPointer> {
+        self.messages.lock().pop_front()
+    }
+
+    /// Closes the channel, preventing any new messages from being sent.
+    ///
+    /// Messages already in the channel can still be received.
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of messages in the channel.
+    pub fn len(&self) -> usize {
+        self.messages.lock().len()
+    }
+
+    /// Returns pointers to all the messages in the channel.
+    ///
+    /// The caller must ensure no messages are added while these pointers are
+    /// in use, e.g. by holding on to the mailbox lock of the owner.
+    pub fn pointers(&self) -> Vec<ObjectPointerPointer> {
+        self.messages
+            .lock()
+            .iter()
+            .map(|pointer| pointer.pointer())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_pointer::ObjectPointer;
+
+    #[test]
+    fn test_push_with_and_pop() {
+        let channel = Channel::new(1, None);
+
+        assert_eq!(channel.push_with(|| ObjectPointer::null()),
+                   SendResult::Sent);
+
+        assert_eq!(channel.len(), 1);
+        assert!(channel.pop().is_some());
+        assert!(channel.pop().is_none());
+    }
+
+    #[test]
+    fn test_push_with_full_channel() {
+        let channel = Channel::new(1, Some(1));
+
+        channel.push_with(|| ObjectPointer::null());
+
+        assert_eq!(channel.push_with(|| panic!("the channel is full")),
+                   SendResult::Full);
+    }
+
+    #[test]
+    fn test_push_with_closed_channel() {
+        let channel = Channel::new(1, None);
+
+        channel.close();
+
+        assert!(channel.is_closed());
+        assert_eq!(channel.push_with(|| ObjectPointer::null()),
+                   SendResult::Closed);
+    }
+
+    #[test]
+    fn test_pop_closed_channel() {
+        let channel = Channel::new(1, None);
+
+        channel.push_with(|| ObjectPointer::null());
+        channel.close();
+
+        assert!(channel.pop().is_some());
+    }
+
+    #[test]
+    fn test_pointers() {
+        let channel = Channel::new(1, None);
+
+        channel.push_with(|| ObjectPointer::null());
+        channel.push_with(|| ObjectPointer::null());
+
+        assert_eq!(channel.pointers().len(), 2);
+    }
+}
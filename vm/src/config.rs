@@ -11,6 +11,10 @@ use num_cpus;
 use std::env;
 use std::path::PathBuf;
 
+use distribution;
+use mailbox::OverflowPolicy;
+use pools::{PRIMARY_POOL_NAME, SECONDARY_POOL_NAME};
+
 /// Sets a configuration field based on an environment variable.
 macro_rules! set_from_env {
     ($config: expr, $field: ident, $key: expr, $value_type: ty) => ({
@@ -22,6 +26,15 @@ macro_rules! set_from_env {
     });
 }
 
+/// The configuration of an additional process pool.
+pub struct PoolConfig {
+    /// The name of the pool, used for looking up the pool at runtime.
+    pub name: String,
+
+    /// The number of threads to run for this pool.
+    pub threads: usize,
+}
+
 /// Structure containing the configuration settings for the virtual machine.
 pub struct Config {
     /// The directories to search in for extra bytecode files to run.
@@ -33,6 +46,14 @@ pub struct Config {
     /// The number of secondary process threads to run.
     pub secondary_threads: usize,
 
+    /// Additional process pools to create, besides the primary and secondary
+    /// pools.
+    pub pools: Vec<PoolConfig>,
+
+    /// The name of the pool to move processes to when they are about to run a
+    /// blocking instruction. Defaults to the secondary pool.
+    pub blocking_pool: String,
+
     /// The number of garbage collector threads to run. Defaults to the number
     /// of CPU cores.
     pub gc_threads: usize,
@@ -50,6 +71,70 @@ pub struct Config {
     /// The block allocation growth factor for the mailbox space of every
     /// process..
     pub mailbox_growth_factor: f64,
+
+    /// The maximum number of messages the mailbox of a process can hold. A
+    /// value of 0 means mailboxes are unbounded. Defaults to 0.
+    pub mailbox_capacity: usize,
+
+    /// What to do when a message is sent to a full mailbox. Defaults to
+    /// suspending the sender.
+    pub mailbox_policy: OverflowPolicy,
+
+    /// The maximum number of blocks the young generation of a process can
+    /// use. A value of 0 means no limit is applied. Defaults to 0.
+    pub young_block_limit: usize,
+
+    /// The maximum number of blocks the mature generation of a process can
+    /// use. A value of 0 means no limit is applied. Defaults to 0.
+    pub mature_block_limit: usize,
+
+    /// The maximum number of blocks the mailbox of a process can use. A value
+    /// of 0 means no limit is applied. Defaults to 0.
+    pub mailbox_block_limit: usize,
+
+    /// The maximum number of blocks all processes combined can use. Processes
+    /// allocating blocks while this limit is exceeded are collected, and
+    /// terminated if the limit is still exceeded afterwards. A value of 0
+    /// means no limit is applied. Defaults to 0.
+    pub block_limit: usize,
+
+    /// The maximum number of free blocks to retain for re-use. Free blocks
+    /// beyond this limit are released to the operating system after a garbage
+    /// collection. A value of 0 means free blocks are never released. Defaults
+    /// to 256 (8 MB).
+    pub free_block_limit: usize,
+
+    /// The name of this VM when exchanging messages with other VM instances.
+    /// Distribution is disabled if no name is set.
+    pub node_name: Option<String>,
+
+    /// The directory containing the sockets of all nodes on the host.
+    pub node_directory: PathBuf,
+
+    /// The number of milliseconds to wait between checking for deadlocks.
+    /// Deadlock detection is disabled when set to 0. Defaults to 1000.
+    pub deadlock_check_interval: u64,
+
+    /// The seed to use for running all processes on a single thread in a
+    /// reproducible order. Processes are run concurrently using multiple
+    /// threads if no seed is set.
+    pub scheduler_seed: Option<u64>,
+
+    /// The file to write the details of every garbage collection to, or
+    /// "stderr" to write to STDERR. Collections are not logged if no target is
+    /// set.
+    pub gc_log: Option<String>,
+
+    /// When enabled a garbage collection is performed at every safepoint,
+    /// evacuating every object that can be moved. The heap is verified after
+    /// every collection, terminating the VM if it is corrupted. This is very
+    /// slow and only meant for debugging the garbage collector.
+    pub gc_stress: bool,
+
+    /// The file to write a heap snapshot of all processes to when the VM
+    /// terminates. Processes still running at this point, such as processes
+    /// blocked on IO, are left out. No snapshot is written if no file is set.
+    pub heap_snapshot: Option<String>,
 }
 
 impl Config {
@@ -61,10 +146,26 @@ impl Config {
             primary_threads: cpu_count,
             gc_threads: cpu_count,
             secondary_threads: cpu_count,
+            pools: Vec::new(),
+            blocking_pool: SECONDARY_POOL_NAME.to_string(),
             reductions: 1000,
             young_growth_factor: 1.5,
             mature_growth_factor: 1.5,
             mailbox_growth_factor: 1.5,
+            mailbox_capacity: 0,
+            mailbox_policy: OverflowPolicy::Suspend,
+            young_block_limit: 0,
+            mature_block_limit: 0,
+            mailbox_block_limit: 0,
+            block_limit: 0,
+            free_block_limit: 256,
+            node_name: None,
+            node_directory: env::temp_dir().join("inko"),
+            deadlock_check_interval: 1000,
+            scheduler_seed: None,
+            gc_log: None,
+            gc_stress: false,
+            heap_snapshot: None,
         }
     }
 
@@ -74,6 +175,16 @@ impl Config {
         set_from_env!(self, secondary_threads, "SECONDARY_THREADS", usize);
         set_from_env!(self, gc_threads, "GC_THREADS", usize);
 
+        set_from_env!(self, blocking_pool, "BLOCKING_POOL", String);
+
+        if let Ok(pools) = env::var("INKO_POOLS") {
+            for pool in pools.split(',') {
+                // Invalid pool definitions are ignored, just like other
+                // invalid environment values.
+                let _ = self.add_pool_from_string(pool);
+            }
+        }
+
         set_from_env!(self, reductions, "REDUCTIONS", usize);
 
         set_from_env!(self, young_growth_factor, "GC_YOUNG_GROWTH_FACTOR", f64);
@@ -83,6 +194,42 @@ impl Config {
                       mailbox_growth_factor,
                       "GC_MAILBOX_GROWTH_FACTOR",
                       f64);
+
+        set_from_env!(self, mailbox_capacity, "MAILBOX_CAPACITY", usize);
+        set_from_env!(self, mailbox_policy, "MAILBOX_POLICY", OverflowPolicy);
+
+        set_from_env!(self, young_block_limit, "YOUNG_BLOCK_LIMIT", usize);
+        set_from_env!(self, mature_block_limit, "MATURE_BLOCK_LIMIT", usize);
+        set_from_env!(self, mailbox_block_limit, "MAILBOX_BLOCK_LIMIT", usize);
+        set_from_env!(self, block_limit, "BLOCK_LIMIT", usize);
+        set_from_env!(self, free_block_limit, "FREE_BLOCK_LIMIT", usize);
+
+        if let Ok(name) = env::var("INKO_NODE") {
+            self.set_node_name(name);
+        }
+
+        if let Ok(directory) = env::var("INKO_NODE_DIRECTORY") {
+            self.node_directory = PathBuf::from(directory);
+        }
+
+        set_from_env!(self,
+                      deadlock_check_interval,
+                      "DEADLOCK_CHECK_INTERVAL",
+                      u64);
+
+        if let Ok(seed) = env::var("INKO_SCHEDULER_SEED") {
+            let _ = self.set_scheduler_seed_from_string(&seed);
+        }
+
+        if let Ok(target) = env::var("INKO_GC_LOG") {
+            self.set_gc_log(target);
+        }
+
+        set_from_env!(self, gc_stress, "GC_STRESS", bool);
+
+        if let Ok(path) = env::var("INKO_HEAP_SNAPSHOT") {
+            self.set_heap_snapshot(path);
+        }
     }
 
     pub fn add_directory(&mut self, path: String) {
@@ -113,11 +260,107 @@ impl Config {
         }
     }
 
+    /// Adds a process pool with the given name and number of threads.
+    ///
+    /// If a pool with the same name already exists its number of threads is
+    /// updated instead.
+    pub fn add_pool(&mut self, name: String, threads: usize) {
+        if name == PRIMARY_POOL_NAME {
+            return self.set_primary_threads(threads);
+        }
+
+        if name == SECONDARY_POOL_NAME {
+            return self.set_secondary_threads(threads);
+        }
+
+        let threads = if threads == 0 { 1 } else { threads };
+
+        for pool in self.pools.iter_mut() {
+            if pool.name == name {
+                pool.threads = threads;
+
+                return;
+            }
+        }
+
+        self.pools.push(PoolConfig {
+            name: name,
+            threads: threads,
+        });
+    }
+
+    /// Adds a process pool using a definition in the form "NAME=THREADS".
+    pub fn add_pool_from_string(&mut self,
+                                definition: &str)
+                                -> Result<(), String> {
+        let mut parts = definition.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let threads = parts.next().unwrap_or("").trim();
+
+        if name.is_empty() {
+            return Err(format!("The pool definition {:?} is missing a name",
+                               definition));
+        }
+
+        let threads = threads.parse::<usize>()
+            .map_err(|_| {
+                format!("The pool definition {:?} has an invalid number of \
+                         threads",
+                        definition)
+            })?;
+
+        self.add_pool(name.to_string(), threads);
+
+        Ok(())
+    }
+
     pub fn set_reductions(&mut self, reductions: usize) {
         if reductions > 0 {
             self.reductions = reductions;
         }
     }
+
+    /// Sets the scheduler seed using a string, enabling deterministic
+    /// scheduling.
+    pub fn set_scheduler_seed_from_string(&mut self,
+                                          seed: &str)
+                                          -> Result<(), String> {
+        let seed = seed.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("The scheduler seed {:?} is invalid", seed))?;
+
+        self.scheduler_seed = Some(seed);
+
+        Ok(())
+    }
+
+    /// Sets the target of the garbage collection log, enabling logging.
+    ///
+    /// Empty targets are ignored.
+    pub fn set_gc_log(&mut self, target: String) {
+        if !target.is_empty() {
+            self.gc_log = Some(target);
+        }
+    }
+
+    /// Sets the file to write a heap snapshot to when the VM terminates.
+    ///
+    /// Empty paths are ignored.
+    pub fn set_heap_snapshot(&mut self, path: String) {
+        if !path.is_empty() {
+            self.heap_snapshot = Some(path);
+        }
+    }
+
+    /// Sets the name of this node, enabling distribution.
+    ///
+    /// Empty names and names that can't be used for a node's socket are
+    /// ignored.
+    pub fn set_node_name(&mut self, name: String) {
+        if distribution::is_valid_name(&name) {
+            self.node_name = Some(name);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -133,6 +376,20 @@ mod tests {
         assert!(config.primary_threads >= 1);
         assert!(config.gc_threads >= 1);
         assert_eq!(config.reductions, 1000);
+        assert_eq!(config.blocking_pool, "secondary".to_string());
+        assert!(config.node_name.is_none());
+        assert_eq!(config.deadlock_check_interval, 1000);
+        assert!(config.scheduler_seed.is_none());
+        assert!(config.gc_log.is_none());
+        assert_eq!(config.gc_stress, false);
+        assert!(config.heap_snapshot.is_none());
+        assert_eq!(config.mailbox_capacity, 0);
+        assert_eq!(config.mailbox_policy, OverflowPolicy::Suspend);
+        assert_eq!(config.young_block_limit, 0);
+        assert_eq!(config.mature_block_limit, 0);
+        assert_eq!(config.mailbox_block_limit, 0);
+        assert_eq!(config.block_limit, 0);
+        assert_eq!(config.free_block_limit, 256);
     }
 
     #[test]
@@ -188,6 +445,109 @@ mod tests {
         assert_eq!(config.reductions, 5);
     }
 
+    #[test]
+    fn test_set_node_name() {
+        let mut config = Config::new();
+
+        config.set_node_name(String::new());
+
+        assert!(config.node_name.is_none());
+
+        config.set_node_name("../a".to_string());
+
+        assert!(config.node_name.is_none());
+
+        config.set_node_name("a".to_string());
+
+        assert_eq!(config.node_name, Some("a".to_string()));
+    }
+
+    #[test]
+    fn test_set_gc_log() {
+        let mut config = Config::new();
+
+        config.set_gc_log(String::new());
+
+        assert!(config.gc_log.is_none());
+
+        config.set_gc_log("stderr".to_string());
+
+        assert_eq!(config.gc_log, Some("stderr".to_string()));
+    }
+
+    #[test]
+    fn test_set_heap_snapshot() {
+        let mut config = Config::new();
+
+        config.set_heap_snapshot(String::new());
+
+        assert!(config.heap_snapshot.is_none());
+
+        config.set_heap_snapshot("heap.txt".to_string());
+
+        assert_eq!(config.heap_snapshot, Some("heap.txt".to_string()));
+    }
+
+    #[test]
+    fn test_set_scheduler_seed_from_string() {
+        let mut config = Config::new();
+
+        assert!(config.set_scheduler_seed_from_string("foo").is_err());
+        assert!(config.scheduler_seed.is_none());
+
+        assert!(config.set_scheduler_seed_from_string("42").is_ok());
+        assert_eq!(config.scheduler_seed, Some(42));
+    }
+
+    #[test]
+    fn test_add_pool() {
+        let mut config = Config::new();
+
+        config.add_pool("io".to_string(), 4);
+        config.add_pool("cpu".to_string(), 0);
+
+        assert_eq!(config.pools.len(), 2);
+        assert_eq!(config.pools[0].threads, 4);
+        assert_eq!(config.pools[1].threads, 1);
+    }
+
+    #[test]
+    fn test_add_pool_existing() {
+        let mut config = Config::new();
+
+        config.add_pool("io".to_string(), 4);
+        config.add_pool("io".to_string(), 8);
+
+        assert_eq!(config.pools.len(), 1);
+        assert_eq!(config.pools[0].threads, 8);
+    }
+
+    #[test]
+    fn test_add_pool_builtin() {
+        let mut config = Config::new();
+
+        config.add_pool("primary".to_string(), 3);
+        config.add_pool("secondary".to_string(), 2);
+
+        assert_eq!(config.pools.len(), 0);
+        assert_eq!(config.primary_threads, 3);
+        assert_eq!(config.secondary_threads, 2);
+    }
+
+    #[test]
+    fn test_add_pool_from_string() {
+        let mut config = Config::new();
+
+        assert!(config.add_pool_from_string("io=4").is_ok());
+        assert!(config.add_pool_from_string("io").is_err());
+        assert!(config.add_pool_from_string("=4").is_err());
+        assert!(config.add_pool_from_string("io=foo").is_err());
+
+        assert_eq!(config.pools.len(), 1);
+        assert_eq!(config.pools[0].name, "io".to_string());
+        assert_eq!(config.pools[0].threads, 4);
+    }
+
     #[test]
     fn test_set_secondary_threads() {
         let mut config = Config::new();
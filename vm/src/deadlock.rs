@@ -0,0 +1,279 @@
+//! Detecting processes that can never run again.
+//!
+//! A deadlock occurs when every process is waiting for an event (e.g. a
+//! message) that can only be produced by another process. Since none of these
+//! processes can run, none of these events will ever happen.
+//!
+//! Waiting processes are rescheduled to check if their event happened, so the
+//! schedulers can't tell the difference between a process that is stuck and a
+//! process that is about to receive a message. Instead the process table is
+//! inspected periodically.
+//!
+//! Processes keep running while being inspected, so a single snapshot is not
+//! enough. Instead the snapshot taken during every check is compared with the
+//! snapshot of the previous check, taken one check interval earlier. A
+//! deadlock is only reported if in both snapshots every process was waiting
+//! for the same event (without said event having happened) and had not
+//! stopped waiting in between. This means that at some point in time between
+//! the two snapshots all processes were waiting at the same time, and that
+//! they kept waiting for at least the entire check interval.
+
+use std::mem;
+use std::sync::RwLock;
+
+use process::{RcProcess, WaitReason};
+use process_table::{ProcessTable, PID};
+
+/// A process that is waiting for an event that has not yet happened.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BlockedProcess {
+    /// The PID of the process.
+    pub pid: PID,
+
+    /// The sequence number of the wait, used for comparing snapshots.
+    pub sequence: usize,
+
+    /// A description of the event the process is waiting for.
+    pub reason: &'static str,
+
+    /// The name of the method the process is waiting in.
+    pub method: String,
+
+    /// The file of the method the process is waiting in.
+    pub file: String,
+
+    /// The line number the process is waiting on.
+    pub line: u32,
+
+    /// The number of messages in the mailbox of the process.
+    pub mailbox_size: usize,
+}
+
+/// Returns all processes if every process is blocked.
+///
+/// A None is returned if there are no processes, or if at least one process
+/// is not blocked.
+pub fn blocked_processes(table: &ProcessTable<RcProcess>)
+                         -> Option<Vec<BlockedProcess>> {
+    let mut blocked = Vec::new();
+
+    for pid in table.pids() {
+        let process = if let Some(process) = table.get(&pid) {
+            process
+        } else {
+            return None;
+        };
+
+        let info = if let Some(info) = process.wait_info() {
+            info
+        } else {
+            return None;
+        };
+
+        if info.is_ready(&process) {
+            return None;
+        }
+
+        blocked.push(BlockedProcess {
+            pid: pid,
+            sequence: info.sequence,
+            reason: describe_reason(&info.reason),
+            method: info.method,
+            file: info.file,
+            line: info.line,
+            mailbox_size: process.mailbox_size(),
+        });
+    }
+
+    if blocked.is_empty() { None } else { Some(blocked) }
+}
+
+/// Periodically checks if all processes are deadlocked.
+pub struct Detector {
+    /// The blocked processes found during the previous check, if any.
+    previous: Option<Vec<BlockedProcess>>,
+}
+
+impl Detector {
+    pub fn new() -> Self {
+        Detector { previous: None }
+    }
+
+    /// Checks if all processes in the table are deadlocked.
+    ///
+    /// This method should be called once every check interval. The blocked
+    /// processes are returned if a deadlock is detected.
+    pub fn check(&mut self,
+                 table: &RwLock<ProcessTable<RcProcess>>)
+                 -> Option<Vec<BlockedProcess>> {
+        // Holding on to the lock prevents processes from being spawned or
+        // removed while taking the snapshot.
+        let current = blocked_processes(&read_lock!(table));
+        let previous = mem::replace(&mut self.previous, current);
+
+        if previous.is_some() && previous == self.previous {
+            self.previous.take()
+        } else {
+            None
+        }
+    }
+}
+
+/// Produces a human readable report of the blocked processes.
+pub fn report(blocked: &[BlockedProcess]) -> String {
+    let mut message = "A deadlock was detected, all processes are waiting for \
+                       an event that will never happen:\n"
+        .to_string();
+
+    for process in blocked {
+        message.push_str(&format!("\nProcess {} is waiting for {} in {} at \
+                                   {} line {} (mailbox size: {})",
+                                  process.pid,
+                                  process.reason,
+                                  process.method,
+                                  process.file,
+                                  process.line,
+                                  process.mailbox_size));
+    }
+
+    message
+}
+
+fn describe_reason(reason: &WaitReason) -> &'static str {
+    match *reason {
+        WaitReason::Message => "a message",
+        WaitReason::ChannelMessage(_) => "a channel message",
+        WaitReason::ChannelSpace(_) => "space in a channel",
+        WaitReason::MailboxSpace(_) => "space in a mailbox",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compiled_code::CompiledCode;
+    use immix::global_allocator::GlobalAllocator;
+    use object_pointer::ObjectPointer;
+    use process::Process;
+
+    fn table_with_processes(amount: usize) -> RwLock<ProcessTable<RcProcess>> {
+        let mut table = ProcessTable::new();
+        let global_alloc = GlobalAllocator::new();
+
+        for _ in 0..amount {
+            let code = CompiledCode::with_rc("a".to_string(),
+                                             "a.inko".to_string(),
+                                             1,
+                                             Vec::new());
+
+            let pid = table.reserve().unwrap();
+            let process = Process::from_code(pid,
+                                             0,
+                                             code,
+                                             ObjectPointer::null(),
+                                             global_alloc.clone());
+
+            table.map(pid, process);
+        }
+
+        RwLock::new(table)
+    }
+
+    fn process(table: &RwLock<ProcessTable<RcProcess>>, pid: PID) -> RcProcess {
+        read_lock!(table).get(&pid).unwrap()
+    }
+
+    fn check_twice(table: &RwLock<ProcessTable<RcProcess>>)
+                   -> Option<Vec<BlockedProcess>> {
+        let mut detector = Detector::new();
+
+        detector.check(table);
+        detector.check(table)
+    }
+
+    #[test]
+    fn test_detect_without_processes() {
+        let table = table_with_processes(0);
+
+        assert!(check_twice(&table).is_none());
+    }
+
+    #[test]
+    fn test_detect_with_running_process() {
+        let table = table_with_processes(2);
+
+        process(&table, 0).start_waiting(WaitReason::Message);
+
+        assert!(check_twice(&table).is_none());
+    }
+
+    #[test]
+    fn test_detect_with_pending_message() {
+        let table = table_with_processes(2);
+        let receiver = process(&table, 0);
+        let sender = process(&table, 1);
+        let message = sender.allocate_empty();
+
+        receiver.start_waiting(WaitReason::Message);
+        sender.start_waiting(WaitReason::Message);
+        receiver.send_message(&sender, message);
+
+        assert!(check_twice(&table).is_none());
+    }
+
+    #[test]
+    fn test_detect_deadlock() {
+        let table = table_with_processes(2);
+
+        process(&table, 0).start_waiting(WaitReason::Message);
+        process(&table, 1).start_waiting(WaitReason::Message);
+
+        let blocked = check_twice(&table).unwrap();
+
+        assert_eq!(blocked.len(), 2);
+        assert_eq!(blocked[0].pid, 0);
+        assert_eq!(blocked[0].reason, "a message");
+        assert_eq!(blocked[0].method, "a".to_string());
+        assert_eq!(blocked[0].line, 1);
+        assert_eq!(blocked[1].pid, 1);
+    }
+
+    #[test]
+    fn test_detect_requires_two_checks() {
+        let table = table_with_processes(1);
+        let mut detector = Detector::new();
+
+        process(&table, 0).start_waiting(WaitReason::Message);
+
+        assert!(detector.check(&table).is_none());
+        assert!(detector.check(&table).is_some());
+    }
+
+    #[test]
+    fn test_detect_with_wait_between_checks() {
+        let table = table_with_processes(1);
+        let process = process(&table, 0);
+        let mut detector = Detector::new();
+
+        process.start_waiting(WaitReason::Message);
+        detector.check(&table);
+
+        process.stop_waiting();
+        process.start_waiting(WaitReason::Message);
+
+        assert!(detector.check(&table).is_none());
+        assert!(detector.check(&table).is_some());
+    }
+
+    #[test]
+    fn test_report() {
+        let table = table_with_processes(1);
+
+        process(&table, 0).start_waiting(WaitReason::Message);
+
+        let report = report(&check_twice(&table).unwrap());
+
+        assert!(report.contains("Process 0 is waiting for a message in a at \
+                                 a.inko line 1 (mailbox size: 0)"));
+    }
+}
@@ -0,0 +1,148 @@
+//! Scheduling jobs in a reproducible order.
+//!
+//! When running in deterministic mode all process pools and garbage collection
+//! requests are processed by a single thread. Instead of processing jobs in the
+//! order they are scheduled, the next job is picked using a pseudo-random
+//! number generator. Running a program using the same seed results in the same
+//! order of jobs, and thus the same interleaving of processes.
+//!
+//! A different seed produces a different (but equally reproducible) order,
+//! allowing one to test different interleavings of the same program.
+
+/// A pseudo-random number generator based on SplitMix64.
+///
+/// This generator is not suitable for cryptographic purposes, but it's fast
+/// and produces the same sequence of numbers for the same seed on every
+/// platform.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random { state: seed }
+    }
+
+    /// Returns the next pseudo-random number.
+    pub fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut value = self.state;
+
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+        value ^ (value >> 31)
+    }
+}
+
+/// A collection of jobs, returned in a seeded pseudo-random order.
+pub struct Scheduler<T> {
+    random: Random,
+    jobs: Vec<T>,
+}
+
+impl<T> Scheduler<T> {
+    pub fn new(seed: u64) -> Self {
+        Scheduler {
+            random: Random::new(seed),
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Adds a number of jobs to the scheduler.
+    pub fn add<I: IntoIterator<Item = T>>(&mut self, jobs: I) {
+        self.jobs.extend(jobs);
+    }
+
+    /// Removes and returns the next job to perform.
+    pub fn next(&mut self) -> Option<T> {
+        if self.jobs.is_empty() {
+            return None;
+        }
+
+        let index = (self.random.next() % self.jobs.len() as u64) as usize;
+
+        Some(self.jobs.swap_remove(index))
+    }
+
+    /// Returns the number of jobs waiting to be performed.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(seed: u64) -> Vec<usize> {
+        let mut scheduler = Scheduler::new(seed);
+        let mut jobs = Vec::new();
+
+        scheduler.add(0..10);
+
+        while let Some(job) = scheduler.next() {
+            jobs.push(job);
+        }
+
+        jobs
+    }
+
+    #[test]
+    fn test_random_same_seed() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+
+        for _ in 0..10 {
+            assert_eq!(a.next(), b.next());
+        }
+    }
+
+    #[test]
+    fn test_random_different_seed() {
+        assert!(Random::new(1).next() != Random::new(2).next());
+    }
+
+    #[test]
+    fn test_random_zero_seed() {
+        let mut random = Random::new(0);
+
+        assert!(random.next() != random.next());
+    }
+
+    #[test]
+    fn test_scheduler_add() {
+        let mut scheduler = Scheduler::new(1);
+
+        scheduler.add(vec![1, 2]);
+
+        assert_eq!(scheduler.len(), 2);
+    }
+
+    #[test]
+    fn test_scheduler_next_empty() {
+        let mut scheduler: Scheduler<()> = Scheduler::new(1);
+
+        assert!(scheduler.next().is_none());
+    }
+
+    #[test]
+    fn test_scheduler_next_performs_all_jobs() {
+        let mut jobs = order(7);
+
+        jobs.sort();
+
+        assert_eq!(jobs, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_scheduler_same_seed_same_order() {
+        assert_eq!(order(7), order(7));
+    }
+
+    #[test]
+    fn test_scheduler_different_seed_different_order() {
+        assert!(order(1) != order(2));
+    }
+}
@@ -0,0 +1,558 @@
+//! Message passing between VM instances.
+//!
+//! Separate VM instances running on the same host can send messages to each
+//! other's processes. Every VM taking part in this is a "node" with a unique
+//! name. A node listens on a Unix domain socket located at
+//! `DIRECTORY/NAME.sock`, where DIRECTORY is a directory shared by all nodes
+//! on the host. A process running on another node is addressed using the name
+//! of the node and the PID of the process. Node names can't be empty, contain
+//! slashes, or refer to the directory itself (e.g. "..").
+//!
+//! Messages are serialized using the serializer module and sent as frames. A
+//! frame looks as follows (all integers are in big-endian order):
+//!
+//!     SIZE (u64, the size of the PID and message)
+//!     PID (u64)
+//!     MESSAGE (SIZE - 8 bytes)
+//!
+//! After reading a frame the receiving node replies with a single byte: 0 if
+//! the message was delivered, or 1 if the mailbox of the receiving process is
+//! full. In the latter case the message is not delivered, and the sending node
+//! applies its mailbox policy.
+//!
+//! Messages are delivered in the order in which they were sent by a node.
+//! Messages sent to processes that don't exist are dropped, just like messages
+//! sent to local processes.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{self, Arc, Condvar};
+use std::thread::{self, JoinHandle};
+
+use process_table::PID;
+use vm::state::RcState;
+
+/// The maximum size of a single frame, in bytes.
+pub const MAX_FRAME_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The maximum number of incoming connections handled at the same time.
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// The size of the PID stored in a frame, in bytes.
+const PID_SIZE: u64 = 8;
+
+const DELIVERED: u8 = 0;
+const MAILBOX_FULL: u8 = 1;
+
+/// The reply of a node to a message sent to one of its processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The message was delivered, or dropped because the receiving process
+    /// doesn't exist.
+    Delivered,
+
+    /// The message was not delivered because the mailbox of the receiving
+    /// process is full.
+    MailboxFull,
+}
+
+/// A connection to another node.
+///
+/// Every connection has its own lock, allowing processes to send messages to
+/// different nodes in parallel.
+type Connection = Arc<Mutex<UnixStream>>;
+
+pub struct Node {
+    /// The name of this node.
+    pub name: String,
+
+    /// The directory containing the sockets of all nodes.
+    pub directory: PathBuf,
+
+    /// Connections to other nodes, created on demand.
+    connections: Mutex<HashMap<String, Connection>>,
+}
+
+/// Returns true if the given name can be used as the name of a node.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains('/') &&
+    !name.contains('\0')
+}
+
+fn invalid_name() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "the node name is invalid")
+}
+
+impl Node {
+    /// Returns a new node, or an error if the name is invalid.
+    pub fn new(name: String, directory: PathBuf) -> io::Result<Self> {
+        if !is_valid_name(&name) {
+            return Err(invalid_name());
+        }
+
+        Ok(Node {
+            name: name,
+            directory: directory,
+            connections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the path of the socket for the given node name.
+    pub fn socket_path_for(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{}.sock", name))
+    }
+
+    /// Returns the path of the socket of this node.
+    pub fn socket_path(&self) -> PathBuf {
+        self.socket_path_for(&self.name)
+    }
+
+    /// Binds the socket of this node.
+    ///
+    /// A socket left behind by a node that is no longer running is removed.
+    /// An error is returned if another node with the same name is running.
+    pub fn bind(&self) -> io::Result<UnixListener> {
+        let path = self.socket_path();
+
+        fs::create_dir_all(&self.directory)?;
+
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse,
+                                          "another node uses the same name"));
+            }
+
+            fs::remove_file(&path)?;
+        }
+
+        UnixListener::bind(path)
+    }
+
+    /// Sends a serialized message to a process running on another node, and
+    /// waits for the node to reply.
+    ///
+    /// The lock on the list of connections is only held while looking up or
+    /// storing a connection, not while writing to it.
+    pub fn send(&self,
+                node: &str,
+                pid: PID,
+                message: &[u8])
+                -> io::Result<Delivery> {
+        if !is_valid_name(node) {
+            return Err(invalid_name());
+        }
+
+        if let Some(connection) = self.connection(node) {
+            {
+                let mut stream = connection.lock();
+
+                if write_frame(&mut *stream, pid, message).is_ok() {
+                    return read_delivery(&mut *stream);
+                }
+            }
+
+            // The other node was restarted since we last connected to it.
+            self.forget(node, &connection);
+        }
+
+        let connection = self.connect(node)?;
+        let mut stream = connection.lock();
+
+        write_frame(&mut *stream, pid, message)?;
+
+        read_delivery(&mut *stream)
+    }
+
+    /// Returns the existing connection to the given node, if any.
+    fn connection(&self, node: &str) -> Option<Connection> {
+        self.connections.lock().get(node).cloned()
+    }
+
+    /// Connects to the given node.
+    ///
+    /// If another thread connected to the node in the mean time its
+    /// connection is used instead, ensuring all messages sent to a node use
+    /// the same connection.
+    fn connect(&self, node: &str) -> io::Result<Connection> {
+        let stream = UnixStream::connect(self.socket_path_for(node))?;
+        let mut connections = self.connections.lock();
+
+        let connection = connections.entry(node.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(stream)))
+            .clone();
+
+        Ok(connection)
+    }
+
+    /// Removes a broken connection, unless it was already replaced.
+    fn forget(&self, node: &str, connection: &Connection) {
+        let mut connections = self.connections.lock();
+
+        let replaced = match connections.get(node) {
+            Some(current) => !Arc::ptr_eq(current, connection),
+            None => true,
+        };
+
+        if !replaced {
+            connections.remove(node);
+        }
+    }
+
+    /// Removes the socket of this node.
+    pub fn cleanup(&self) {
+        let _ = fs::remove_file(self.socket_path());
+    }
+}
+
+/// Accepts connections from other nodes in a separate thread.
+///
+/// Every connection is handled by a separate thread. At most `MAX_CONNECTIONS`
+/// connections are handled at a time, new connections are not accepted until
+/// an existing connection is closed.
+pub fn start_listener(state: RcState, listener: UnixListener) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let limit = Arc::new(ConnectionLimit::new(MAX_CONNECTIONS));
+
+        loop {
+            limit.acquire();
+
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(_) => {
+                    limit.release();
+                    continue;
+                }
+            };
+
+            let state = state.clone();
+            let limit = limit.clone();
+
+            thread::spawn(move || {
+                receive_messages(state, stream);
+                limit.release();
+            });
+        }
+    })
+}
+
+/// A counter limiting the number of connections handled at the same time.
+struct ConnectionLimit {
+    available: sync::Mutex<usize>,
+    signaler: Condvar,
+}
+
+impl ConnectionLimit {
+    fn new(maximum: usize) -> Self {
+        ConnectionLimit {
+            available: sync::Mutex::new(maximum),
+            signaler: Condvar::new(),
+        }
+    }
+
+    /// Blocks the current thread until a connection can be handled.
+    fn acquire(&self) {
+        let mut available = lock!(self.available);
+
+        while *available == 0 {
+            available = self.signaler.wait(available).unwrap();
+        }
+
+        *available -= 1;
+    }
+
+    /// Releases a connection acquired using `acquire`.
+    fn release(&self) {
+        *lock!(self.available) += 1;
+
+        self.signaler.notify_one();
+    }
+}
+
+/// Delivers a serialized message to a local process.
+///
+/// Messages for processes that don't exist and messages that can't be
+/// deserialized are dropped. Messages for processes with a full mailbox are
+/// not delivered, leaving it up to the sending node what to do with them.
+pub fn deliver(state: &RcState, pid: PID, message: &[u8]) -> Delivery {
+    if let Some(receiver) = read_lock!(state.process_table).get(&pid) {
+        let result = receiver.send_serialized_message(message,
+                                                      &state.shared_objects);
+
+        if let Ok(false) = result {
+            return Delivery::MailboxFull;
+        }
+    }
+
+    Delivery::Delivered
+}
+
+fn receive_messages(state: RcState, mut stream: UnixStream) {
+    // The connection is dropped upon reading an invalid frame, as there's no
+    // way of telling where the next frame would start.
+    while let Ok((pid, message)) = read_frame(&mut stream) {
+        let delivery = deliver(&state, pid, &message);
+
+        if write_delivery(&mut stream, delivery).is_err() {
+            break;
+        }
+    }
+}
+
+/// Writes a single frame to the output stream.
+pub fn write_frame<W: Write>(output: &mut W,
+                             pid: PID,
+                             message: &[u8])
+                             -> io::Result<()> {
+    let mut frame = Vec::with_capacity(message.len() + 16);
+
+    push_u64(&mut frame, message.len() as u64 + PID_SIZE);
+    push_u64(&mut frame, pid as u64);
+
+    frame.extend_from_slice(message);
+
+    output.write_all(&frame)?;
+    output.flush()
+}
+
+/// Reads a single frame from the input stream.
+pub fn read_frame<R: Read>(input: &mut R) -> io::Result<(PID, Vec<u8>)> {
+    let size = read_u64(input)?;
+
+    if size < PID_SIZE || size > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                  "the frame size is invalid"));
+    }
+
+    let pid = read_u64(input)? as PID;
+    let mut message = vec![0; (size - PID_SIZE) as usize];
+
+    input.read_exact(&mut message)?;
+
+    Ok((pid, message))
+}
+
+/// Writes the reply to a frame to the output stream.
+pub fn write_delivery<W: Write>(output: &mut W,
+                                delivery: Delivery)
+                                -> io::Result<()> {
+    let byte = match delivery {
+        Delivery::Delivered => DELIVERED,
+        Delivery::MailboxFull => MAILBOX_FULL,
+    };
+
+    output.write_all(&[byte])?;
+    output.flush()
+}
+
+/// Reads the reply to a frame from the input stream.
+pub fn read_delivery<R: Read>(input: &mut R) -> io::Result<Delivery> {
+    let mut buff: [u8; 1] = [0; 1];
+
+    input.read_exact(&mut buff)?;
+
+    match buff[0] {
+        DELIVERED => Ok(Delivery::Delivered),
+        MAILBOX_FULL => Ok(Delivery::MailboxFull),
+        _ => {
+            Err(io::Error::new(io::ErrorKind::InvalidData,
+                               "the reply is invalid"))
+        }
+    }
+}
+
+fn push_u64(output: &mut Vec<u8>, value: u64) {
+    let bytes: [u8; 8] = unsafe { mem::transmute(value.to_be()) };
+
+    output.extend_from_slice(&bytes);
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut buff: [u8; 8] = [0; 8];
+
+    input.read_exact(&mut buff)?;
+
+    let value: u64 = unsafe { mem::transmute(buff) };
+
+    Ok(u64::from_be(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::Cursor;
+
+    fn node(name: &str) -> Node {
+        let directory = env::temp_dir().join("inko-distribution-tests");
+
+        Node::new(name.to_string(), directory).unwrap()
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("a"));
+        assert!(is_valid_name("a.b"));
+
+        assert_eq!(is_valid_name(""), false);
+        assert_eq!(is_valid_name("."), false);
+        assert_eq!(is_valid_name(".."), false);
+        assert_eq!(is_valid_name("a/b"), false);
+        assert_eq!(is_valid_name("../a"), false);
+    }
+
+    #[test]
+    fn test_new_invalid_name() {
+        let directory = PathBuf::from("/tmp/inko");
+
+        assert!(Node::new("../a".to_string(), directory).is_err());
+    }
+
+    #[test]
+    fn test_send_invalid_name() {
+        let node = node("invalid");
+
+        assert!(node.send("../invalid", 1, &[1]).is_err());
+        assert!(node.send("", 1, &[1]).is_err());
+    }
+
+    #[test]
+    fn test_socket_path() {
+        let node = Node::new("a".to_string(), PathBuf::from("/tmp/inko"))
+            .unwrap();
+
+        assert_eq!(node.socket_path(), PathBuf::from("/tmp/inko/a.sock"));
+    }
+
+    #[test]
+    fn test_write_and_read_frame() {
+        let mut buffer = Vec::new();
+
+        write_frame(&mut buffer, 4, &[1, 2, 3]).unwrap();
+
+        let (pid, message) = read_frame(&mut Cursor::new(buffer)).unwrap();
+
+        assert_eq!(pid, 4);
+        assert_eq!(message, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_read_frame_invalid_size() {
+        let mut buffer = Vec::new();
+
+        push_u64(&mut buffer, 2);
+
+        assert!(read_frame(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[test]
+    fn test_read_frame_incomplete() {
+        let mut buffer = Vec::new();
+
+        write_frame(&mut buffer, 4, &[1, 2, 3]).unwrap();
+        buffer.pop();
+
+        assert!(read_frame(&mut Cursor::new(buffer)).is_err());
+    }
+
+    #[test]
+    fn test_write_and_read_delivery() {
+        let mut buffer = Vec::new();
+
+        write_delivery(&mut buffer, Delivery::MailboxFull).unwrap();
+
+        let delivery = read_delivery(&mut Cursor::new(buffer)).unwrap();
+
+        assert_eq!(delivery, Delivery::MailboxFull);
+    }
+
+    #[test]
+    fn test_read_delivery_invalid() {
+        assert!(read_delivery(&mut Cursor::new(vec![2])).is_err());
+    }
+
+    #[test]
+    fn test_send() {
+        let node = node("send");
+        let listener = node.bind().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let frame = read_frame(&mut stream).unwrap();
+
+            write_delivery(&mut stream, Delivery::MailboxFull).unwrap();
+
+            frame
+        });
+
+        let delivery = node.send("send", 2, &[5, 6]).unwrap();
+        let (pid, message) = handle.join().unwrap();
+
+        node.cleanup();
+
+        assert_eq!(delivery, Delivery::MailboxFull);
+        assert_eq!(pid, 2);
+        assert_eq!(message, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_send_reuses_connection() {
+        let node = node("reuse");
+        let listener = node.bind().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut pids = Vec::new();
+
+            for _ in 0..2 {
+                let (pid, _) = read_frame(&mut stream).unwrap();
+
+                write_delivery(&mut stream, Delivery::Delivered).unwrap();
+                pids.push(pid);
+            }
+
+            pids
+        });
+
+        node.send("reuse", 1, &[1]).unwrap();
+        node.send("reuse", 2, &[2]).unwrap();
+
+        let pids = handle.join().unwrap();
+
+        node.cleanup();
+
+        assert_eq!(pids, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let limit = ConnectionLimit::new(1);
+
+        limit.acquire();
+        limit.release();
+        limit.acquire();
+
+        assert_eq!(*lock!(limit.available), 0);
+    }
+
+    #[test]
+    fn test_send_unavailable_node() {
+        let node = node("unavailable");
+
+        assert!(node.send("unavailable", 1, &[1]).is_err());
+    }
+
+    #[test]
+    fn test_bind_twice() {
+        let node = node("bind");
+        let _listener = node.bind().unwrap();
+
+        assert!(node.bind().is_err());
+
+        node.cleanup();
+    }
+}
@@ -0,0 +1,18 @@
+#[repr(u16)]
+pub enum ErrorKind {
+    Closed,
+    NotOwner,
+    Unsendable,
+}
+
+pub fn closed() -> u16 {
+    ErrorKind::Closed as u16
+}
+
+pub fn not_owner() -> u16 {
+    ErrorKind::NotOwner as u16
+}
+
+pub fn unsendable() -> u16 {
+    ErrorKind::Unsendable as u16
+}
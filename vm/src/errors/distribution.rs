@@ -0,0 +1,18 @@
+#[repr(u16)]
+pub enum ErrorKind {
+    Disabled,
+    NodeUnavailable,
+    NotSerializable,
+}
+
+pub fn disabled() -> u16 {
+    ErrorKind::Disabled as u16
+}
+
+pub fn node_unavailable() -> u16 {
+    ErrorKind::NodeUnavailable as u16
+}
+
+pub fn not_serializable() -> u16 {
+    ErrorKind::NotSerializable as u16
+}
@@ -1,2 +1,5 @@
+pub mod channel;
+pub mod distribution;
 pub mod io;
+pub mod process;
 pub mod string;
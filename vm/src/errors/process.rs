@@ -0,0 +1,43 @@
+#[repr(u16)]
+pub enum ErrorKind {
+    AlreadyRegistered,
+    NotRegistered,
+    InvalidPool,
+    DoesNotExist,
+    GroupExists,
+    GroupDoesNotExist,
+    MailboxFull,
+    Unsendable,
+}
+
+pub fn already_registered() -> u16 {
+    ErrorKind::AlreadyRegistered as u16
+}
+
+pub fn not_registered() -> u16 {
+    ErrorKind::NotRegistered as u16
+}
+
+pub fn invalid_pool() -> u16 {
+    ErrorKind::InvalidPool as u16
+}
+
+pub fn does_not_exist() -> u16 {
+    ErrorKind::DoesNotExist as u16
+}
+
+pub fn group_exists() -> u16 {
+    ErrorKind::GroupExists as u16
+}
+
+pub fn group_does_not_exist() -> u16 {
+    ErrorKind::GroupDoesNotExist as u16
+}
+
+pub fn mailbox_full() -> u16 {
+    ErrorKind::MailboxFull as u16
+}
+
+pub fn unsendable() -> u16 {
+    ErrorKind::Unsendable as u16
+}
@@ -135,6 +135,19 @@ impl ExecutionContext {
         ExecutionContextIterator { current: Some(self) }
     }
 
+    /// Releases any unused memory of the registers and bindings of this
+    /// context and its parents.
+    pub fn shrink(&mut self) {
+        let mut current = Some(self);
+
+        while let Some(context) = current {
+            context.register.shrink();
+            context.binding.shrink();
+
+            current = context.parent.as_mut().map(|parent| &mut **parent);
+        }
+    }
+
     /// Returns pointers to all pointers stored in this context.
     pub fn pointers(&self) -> Vec<ObjectPointerPointer> {
         self.binding.pointers().chain(self.register.pointers()).collect()
@@ -340,4 +353,19 @@ mod tests {
 
         assert_eq!(pointers.len(), 3);
     }
+
+    #[test]
+    fn test_shrink() {
+        let mut context1 = new_context();
+        let mut context2 = new_context();
+        let pointer = ObjectPointer::new(0x1 as RawObjectPointer);
+
+        context1.register.set(0, pointer);
+        context2.register.set(0, pointer);
+        context2.set_parent(Box::new(context1));
+        context2.shrink();
+
+        assert_eq!(context2.register.values.capacity(), 1);
+        assert_eq!(context2.parent().unwrap().register.values.capacity(), 1);
+    }
 }
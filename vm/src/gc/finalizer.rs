@@ -0,0 +1,321 @@
+//! User-defined finalizers for objects holding external resources.
+//!
+//! An object can register a method to run once it becomes unreachable. After
+//! tracing, every registered object that was not marked is resurrected: the
+//! object (and everything it refers to) is marked so it survives the
+//! collection, and its registration is removed.
+//!
+//! Finalizers run asynchronously, each in a new process. The object is copied
+//! into the heap of this process using the same rules as sending a message,
+//! except for its own value (e.g. a file handle): this value is moved instead,
+//! as these values can not be copied. The original object is garbage once its
+//! finalizer is scheduled.
+//!
+//! Objects referring to other values that can't be copied (e.g. another file)
+//! are not copied at all. Instead their finalizers run in the process that
+//! owns the object, the next time this process starts executing a new
+//! execution context.
+//!
+//! Since the registration is removed before the finalizer runs, a finalizer
+//! runs at most once, even if it stores the object somewhere (resurrecting
+//! it). Finalizers of objects that are still reachable when their process
+//! terminates are not run, nor are finalizers that were to run in the process
+//! itself.
+
+use std::mem;
+
+use compiled_code::RcCompiledCode;
+use gc::collector;
+use immix::copy_object;
+use gc::trace_result::TraceResult;
+use object_pointer::ObjectPointer;
+use pools::PRIMARY_POOL;
+use process::RcProcess;
+use vm::machine::Machine;
+use vm::state::RcState;
+
+/// An object along with the method to run when it becomes unreachable.
+pub struct Finalizer {
+    /// The object to finalize.
+    pub object: ObjectPointer,
+
+    /// The method to run, using the object as "self".
+    pub code: RcCompiledCode,
+}
+
+impl Finalizer {
+    pub fn new(object: ObjectPointer, code: RcCompiledCode) -> Self {
+        Finalizer {
+            object: object,
+            code: code,
+        }
+    }
+}
+
+/// Returns true if the object is unreachable after tracing.
+///
+/// Mature objects are only considered if the mature generation was collected,
+/// as a young collection doesn't mark mature objects.
+fn is_unreachable(pointer: &ObjectPointer, mature: bool) -> bool {
+    if pointer.is_forwarded() || pointer.is_marked() {
+        return false;
+    }
+
+    pointer.is_young() || mature && pointer.is_mature()
+}
+
+/// Resurrects all unreachable objects with a finalizer, and queues their
+/// finalizers.
+///
+/// This function must be called after all other objects have been traced.
+pub fn resurrect(process: &RcProcess,
+                 move_objects: bool,
+                 mature: bool)
+                 -> TraceResult {
+    let mut local_data = process.local_data_mut();
+    let registered = mem::replace(&mut local_data.finalizers, Vec::new());
+
+    for finalizer in registered {
+        if is_unreachable(&finalizer.object, mature) {
+            local_data.finalize_queue.push(finalizer);
+        } else {
+            local_data.finalizers.push(finalizer);
+        }
+    }
+
+    // Finalizers that have yet to run in the process itself are traced as
+    // well, as nothing else refers to their objects.
+    let pointers = local_data.finalize_queue
+        .iter()
+        .chain(local_data.local_finalizers.iter())
+        .map(|finalizer| finalizer.object.pointer())
+        .collect();
+
+    let result = if move_objects {
+        collector::trace_pointers_with_moving(process, pointers, mature)
+    } else {
+        collector::trace_pointers_without_moving(pointers, mature)
+    };
+
+    // Registered objects that are still reachable may have been moved, in
+    // which case we have to update our pointers to them.
+    for finalizer in local_data.finalizers.iter() {
+        if finalizer.object.is_forwarded() {
+            finalizer.object.resolve_forwarding_pointer();
+        }
+    }
+
+    result
+}
+
+/// Schedules all queued finalizers of a process, each in a new process.
+///
+/// Finalizers of objects that can't be copied are instead scheduled to run in
+/// the given process. If no process can be allocated the remaining finalizers
+/// stay queued, and are scheduled again after the next collection.
+pub fn schedule(state: &RcState, process: &RcProcess) {
+    let mut local_data = process.local_data_mut();
+
+    if local_data.finalize_queue.is_empty() {
+        return;
+    }
+
+    let machine = Machine::new(state.clone());
+    let mut queue = mem::replace(&mut local_data.finalize_queue, Vec::new());
+
+    while let Some(finalizer) = queue.pop() {
+        let value = finalizer.object.get_mut().value.take();
+
+        if copy_object::ensure_copyable(finalizer.object).is_err() {
+            finalizer.object.get_mut().value = value;
+            local_data.local_finalizers.push(finalizer);
+
+            continue;
+        }
+
+        let new_proc = match machine.allocate_process(PRIMARY_POOL,
+                                                      finalizer.code.clone(),
+                                                      state.top_level) {
+            Ok(new_proc) => new_proc,
+            Err(_) => {
+                finalizer.object.get_mut().value = value;
+                queue.push(finalizer);
+                break;
+            }
+        };
+
+        // The object was checked above, so copying it can't fail.
+        let copy = new_proc.copy_object(finalizer.object).unwrap();
+
+        copy.get_mut().value = value;
+
+        if copy.is_finalizable() {
+            copy.mark_for_finalization();
+        }
+
+        new_proc.set_self_object(copy);
+        state.process_pools.schedule(new_proc);
+    }
+
+    local_data.finalize_queue = queue;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    use compiled_code::CompiledCode;
+    use config::Config;
+    use immix::global_allocator::GlobalAllocator;
+    use immix::permanent_allocator::PermanentAllocator;
+    use object::Object;
+    use object_value;
+    use process::Process;
+    use vm::state::State;
+
+    fn new_process() -> (Box<PermanentAllocator>, RcProcess) {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+
+        let mut perm_alloc =
+            Box::new(PermanentAllocator::new(global_alloc.clone()));
+
+        let self_obj = perm_alloc.allocate_empty();
+
+        (perm_alloc,
+         Process::from_code(1, 0, new_code(), self_obj, global_alloc))
+    }
+
+    fn new_code() -> RcCompiledCode {
+        CompiledCode::with_rc("a".to_string(),
+                              "a".to_string(),
+                              1,
+                              Vec::new())
+    }
+
+    #[test]
+    fn test_resurrect_unreachable_object() {
+        let (_perm_alloc, process) = new_process();
+        let pointer = process.allocate_empty();
+
+        process.register_finalizer(pointer, new_code());
+
+        let result = resurrect(&process, false, false);
+
+        assert_eq!(result.marked, 1);
+        assert!(pointer.is_marked());
+        assert!(process.local_data().finalizers.is_empty());
+        assert_eq!(process.local_data().finalize_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_resurrect_reachable_object() {
+        let (_perm_alloc, process) = new_process();
+        let pointer = process.allocate_empty();
+
+        pointer.mark();
+        process.register_finalizer(pointer, new_code());
+
+        let result = resurrect(&process, false, false);
+
+        assert_eq!(result.marked, 0);
+        assert_eq!(process.local_data().finalizers.len(), 1);
+        assert!(process.local_data().finalize_queue.is_empty());
+    }
+
+    #[test]
+    fn test_resurrect_mature_object_during_young_collection() {
+        let (_perm_alloc, process) = new_process();
+
+        let pointer = process.local_data_mut()
+            .allocator
+            .allocate_mature(Object::new(object_value::none()));
+
+        process.register_finalizer(pointer, new_code());
+
+        resurrect(&process, false, false);
+
+        assert_eq!(process.local_data().finalizers.len(), 1);
+
+        resurrect(&process, false, true);
+
+        assert!(process.local_data().finalizers.is_empty());
+    }
+
+    #[test]
+    fn test_resurrect_updates_forwarded_objects() {
+        let (_perm_alloc, process) = new_process();
+        let old = process.allocate_empty();
+        let new = process.allocate_empty();
+
+        old.get_mut().forward_to(new);
+        process.register_finalizer(old, new_code());
+
+        resurrect(&process, true, false);
+
+        assert!(process.local_data().finalizers[0].object == new);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let (_perm_alloc, process) = new_process();
+        let state = State::new(Config::new());
+        let file = File::open("/dev/null").unwrap();
+        let pointer =
+            process.allocate_without_prototype(object_value::file(file));
+
+        process.register_finalizer(pointer, new_code());
+
+        resurrect(&process, false, false);
+        schedule(&state, &process);
+
+        assert!(process.local_data().finalize_queue.is_empty());
+        assert!(pointer.get().value.is_none());
+
+        let pids = read_lock!(state.process_table).pids();
+        let new_proc = read_lock!(state.process_table).get(&pids[0]).unwrap();
+
+        assert_eq!(pids.len(), 1);
+        assert!(new_proc.self_object().get().value.is_file());
+    }
+
+    #[test]
+    fn test_schedule_with_uncopyable_attribute() {
+        let (_perm_alloc, process) = new_process();
+        let state = State::new(Config::new());
+        let file = File::open("/dev/null").unwrap();
+        let file_ptr =
+            process.allocate_without_prototype(object_value::file(file));
+
+        let pointer = process.allocate_empty();
+
+        pointer.get_mut().add_attribute("@file".to_string(), file_ptr);
+        process.register_finalizer(pointer, new_code());
+
+        resurrect(&process, false, false);
+        schedule(&state, &process);
+
+        assert!(process.local_data().finalize_queue.is_empty());
+        assert!(read_lock!(state.process_table).pids().is_empty());
+        let attribute = pointer.get().lookup_attribute(&"@file".to_string());
+
+        assert_eq!(process.local_data().local_finalizers.len(), 1);
+        assert!(attribute == Some(file_ptr));
+        assert!(file_ptr.get().value.is_file());
+    }
+
+    #[test]
+    fn test_resurrect_keeps_local_finalizers_alive() {
+        let (_perm_alloc, process) = new_process();
+        let pointer = process.allocate_empty();
+
+        process.local_data_mut()
+            .local_finalizers
+            .push(Finalizer::new(pointer, new_code()));
+
+        let result = resurrect(&process, false, false);
+
+        assert_eq!(result.marked, 1);
+        assert!(pointer.is_marked());
+    }
+}
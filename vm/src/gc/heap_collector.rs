@@ -3,15 +3,27 @@
 use rayon::prelude::*;
 
 use gc::collector;
+use gc::finalizer;
 use gc::profile::Profile;
 use gc::trace_result::TraceResult;
-use process::RcProcess;
+use gc::verifier;
+use gc::weak_reference;
+use process::{ExitReason, RcProcess};
 use vm::state::RcState;
 
 pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
     process.request_gc_suspension();
 
-    let collect_mature = process.should_collect_mature_generation();
+    let stress = vm_state.config.gc_stress;
+
+    // When stress testing we alternate between young and full collections, so
+    // both the remembered set and the mature generation are exercised.
+    //
+    // A process exceeding its heap limits is collected fully, as promoting
+    // young objects alone won't reduce the number of blocks used.
+    let collect_mature = process.should_collect_mature_generation() ||
+                         process.heap_limit_exceeded() ||
+                         stress && process.collections() % 2 == 1;
 
     let mut profile = if collect_mature {
         Profile::full()
@@ -22,7 +34,13 @@ pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
     profile.total.start();
     profile.prepare.start();
 
-    let move_objects = process.prepare_for_collection(collect_mature);
+    let move_objects = if stress {
+        process.prepare_for_full_evacuation(collect_mature);
+
+        true
+    } else {
+        process.prepare_for_collection(collect_mature)
+    };
 
     profile.prepare.stop();
     profile.trace.start();
@@ -33,6 +51,10 @@ pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
     profile.reclaim.start();
 
     process.reclaim_blocks(collect_mature);
+
+    // The off-heap bytes have to be recorded before they are reset.
+    profile.off_heap_bytes = process.off_heap_bytes(collect_mature);
+
     process.update_collection_statistics(&vm_state.config, collect_mature);
 
     profile.reclaim.stop();
@@ -40,11 +62,57 @@ pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
 
     profile.populate_tracing_statistics(trace_result);
 
+    if stress {
+        let violations = verifier::verify_heap(process, collect_mature);
+
+        verifier::terminate_on_violations(vm_state, process, violations);
+    }
+
+    if collect_mature && process.heap_limit_exceeded() {
+        process.terminate_with_reason(ExitReason::OutOfMemory);
+    }
+
+    finalizer::schedule(vm_state, process);
     vm_state.process_pools.schedule(process.clone());
 
     profile
 }
 
+/// Performs a full collection that compacts the heap of a process.
+///
+/// All live objects are moved into as few mature blocks as possible, and all
+/// other blocks are returned to the global allocator. Unlike `collect` this
+/// function is meant to be called by the thread running the process, and thus
+/// does not reschedule the process. Finalizers queued by the compaction have
+/// to be scheduled by the caller.
+pub fn compact(process: &RcProcess) -> Profile {
+    let mut profile = Profile::compact();
+
+    profile.total.start();
+    profile.prepare.start();
+
+    process.prepare_for_compaction();
+
+    profile.prepare.stop();
+    profile.trace.start();
+
+    let trace_result = trace(process, true, true);
+
+    profile.trace.stop();
+    profile.reclaim.start();
+
+    profile.off_heap_bytes = process.off_heap_bytes(true);
+
+    process.reclaim_compacted_blocks();
+
+    profile.reclaim.stop();
+    profile.total.stop();
+
+    profile.populate_tracing_statistics(trace_result);
+
+    profile
+}
+
 /// Traces through and marks all reachable objects.
 pub fn trace(process: &RcProcess,
              move_objects: bool,
@@ -64,6 +132,16 @@ pub fn trace(process: &RcProcess,
         result = result + trace_remembered_set(process, move_objects);
     }
 
+    // Weak references to objects that are only resurrected to run their
+    // finalizers have to be cleared, so these must be cleared first.
+    weak_reference::clear_heap(process, mature);
+
+    // Objects with a finalizer can only be considered unreachable once all
+    // other objects have been traced.
+    result = result + finalizer::resurrect(process, move_objects, mature);
+
+    weak_reference::update_heap(process, mature);
+
     result
 }
 
@@ -160,6 +238,8 @@ mod tests {
     use immix::global_allocator::GlobalAllocator;
     use immix::permanent_allocator::PermanentAllocator;
     use execution_context::ExecutionContext;
+    use heap_limits::HeapLimits;
+    use immix::block::OBJECTS_PER_BLOCK;
     use object::Object;
     use object_value;
     use process::{Process, RcProcess};
@@ -198,6 +278,84 @@ mod tests {
         assert!(pointer.is_marked());
     }
 
+    #[test]
+    fn test_collect_with_off_heap_bytes() {
+        let (_perm_alloc, process) = new_process();
+        let state = State::new(Config::new());
+
+        let string = object_value::string("abc".to_string());
+
+        process.allocate_without_prototype(string);
+
+        let profile = collect(&state, &process);
+
+        assert_eq!(profile.off_heap_bytes, 3);
+        assert_eq!(process.off_heap_bytes(true), 0);
+    }
+
+    #[test]
+    fn test_collect_exceeding_heap_limit() {
+        let (_perm_alloc, process) = new_process();
+        let state = State::new(Config::new());
+        let mut limits = HeapLimits::unlimited();
+
+        limits.young = 1;
+
+        process.set_heap_limits(limits);
+        process.set_register(0, process.allocate_empty());
+
+        collect(&state, &process);
+
+        assert_eq!(process.should_terminate(), false);
+
+        let objects = (0..OBJECTS_PER_BLOCK)
+            .map(|_| process.allocate_empty())
+            .collect();
+
+        process.set_register(1, process.allocate_without_prototype(
+            object_value::array(objects)));
+
+        collect(&state, &process);
+
+        assert_eq!(process.exit_reason(), ExitReason::OutOfMemory);
+    }
+
+    #[test]
+    fn test_compact() {
+        let (_perm_alloc, process) = new_process();
+        let young = process.allocate_empty();
+
+        let mature = process.local_data_mut()
+            .allocator
+            .allocate_mature(Object::new(object_value::none()));
+
+        // Garbage in the young generation should not survive the compaction.
+        process.allocate_empty();
+
+        process.set_register(0, young);
+        process.set_register(1, mature);
+
+        let profile = compact(&process);
+
+        assert_eq!(profile.marked, 2);
+        assert_eq!(profile.evacuated, 1);
+        assert_eq!(profile.promoted, 1);
+
+        let young = process.get_register(0).unwrap();
+        let mature = process.get_register(1).unwrap();
+
+        assert!(young.is_mature());
+        assert!(young.is_marked());
+        assert!(mature.is_marked());
+
+        let local_data = process.local_data();
+        let global_alloc = local_data.allocator.global_allocator();
+
+        assert_eq!(local_data.allocator.number_of_young_blocks(), 0);
+        assert_eq!(local_data.allocator.number_of_mature_blocks(), 1);
+        assert_eq!(lock!(global_alloc.blocks).len(), 2);
+    }
+
     #[test]
     fn test_trace_trace_without_moving_without_mature() {
         let (_perm_alloc, process) = new_process();
@@ -283,6 +441,42 @@ mod tests {
         assert_eq!(result.promoted, 0);
     }
 
+    #[test]
+    fn test_trace_with_weak_reference_to_moved_object() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let proto = process.allocate_empty();
+        let weak = process.allocate_weak_reference(target, proto);
+
+        target.block_mut().fragmented = true;
+
+        process.set_register(0, target);
+        process.set_register(1, weak);
+
+        trace(&process, true, false);
+
+        let target = process.get_register(0).unwrap();
+        let weak = process.get_register(1).unwrap();
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == target);
+        assert!(process.local_data().allocator.weak_references == vec![weak]);
+    }
+
+    #[test]
+    fn test_trace_with_weak_reference_to_unreachable_object() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let proto = process.allocate_empty();
+        let weak = process.allocate_weak_reference(target, proto);
+
+        process.set_register(0, weak);
+
+        trace(&process, false, false);
+
+        assert!(weak.get().value.as_weak_reference().unwrap().is_null());
+        assert!(process.local_data().allocator.weak_references.is_empty());
+    }
+
     #[test]
     fn test_trace_remembered_set_without_moving() {
         let (_perm_alloc, process) = new_process();
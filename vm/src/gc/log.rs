@@ -0,0 +1,140 @@
+//! Logging the details of every garbage collection.
+//!
+//! When enabled, a single line is written for every collection, containing the
+//! PID of the collected process, the type of collection, the time spent in
+//! every phase, and the number of marked, evacuated and promoted objects. For
+//! example:
+//!
+//!     gc pid=4 type=young total=0.21 prepare=0.01 trace=0.15 reclaim=0.05 \
+//!     marked=120 evacuated=0 promoted=14
+//!
+//! All timings are in milliseconds.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use gc::profile::Profile;
+use process_table::PID;
+
+/// The target to use for writing to STDERR instead of a file.
+pub const STDERR: &'static str = "stderr";
+
+pub struct Log {
+    /// The file to write to, or "stderr".
+    pub target: String,
+
+    /// The output to write to, set once the log has been opened.
+    output: Mutex<Option<Box<Write + Send>>>,
+}
+
+impl Log {
+    pub fn new(target: String) -> Self {
+        Log {
+            target: target,
+            output: Mutex::new(None),
+        }
+    }
+
+    /// Opens the target of the log.
+    ///
+    /// Files are created if they don't exist, and appended to if they do.
+    pub fn open(&self) -> io::Result<()> {
+        let output: Box<Write + Send> = if self.target == STDERR {
+            Box::new(io::stderr())
+        } else {
+            let file = OpenOptions::new().create(true)
+                .append(true)
+                .open(&self.target)?;
+
+            Box::new(file)
+        };
+
+        *lock!(self.output) = Some(output);
+
+        Ok(())
+    }
+
+    /// Writes the details of a collection to the log.
+    ///
+    /// Errors are ignored, as failing to log a collection should not
+    /// terminate the program.
+    pub fn write(&self, pid: PID, profile: &Profile) {
+        if let Some(ref mut output) = *lock!(self.output) {
+            let _ = output.write_all(format_line(pid, profile).as_bytes());
+        }
+    }
+}
+
+/// Returns the line to log for a collection.
+pub fn format_line(pid: PID, profile: &Profile) -> String {
+    format!("gc pid={} type={} total={:.2} prepare={:.2} trace={:.2} \
+             reclaim={:.2} marked={} evacuated={} promoted={} \
+             off_heap_bytes={}\n",
+            pid,
+            profile.collection_type.name(),
+            profile.total.duration_msec(),
+            profile.prepare.duration_msec(),
+            profile.trace.duration_msec(),
+            profile.reclaim.duration_msec(),
+            profile.marked,
+            profile.evacuated,
+            profile.promoted,
+            profile.off_heap_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Read;
+
+    use gc::trace_result::TraceResult;
+
+    fn profile() -> Profile {
+        let mut profile = Profile::young();
+
+        profile.populate_tracing_statistics(TraceResult::with(1, 2, 3));
+
+        profile
+    }
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(format_line(4, &profile()),
+                   "gc pid=4 type=young total=0.00 prepare=0.00 trace=0.00 \
+                    reclaim=0.00 marked=1 evacuated=2 promoted=3 \
+                    off_heap_bytes=0\n"
+                       .to_string());
+    }
+
+    #[test]
+    fn test_write_without_opening() {
+        let log = Log::new(STDERR.to_string());
+
+        log.write(1, &profile());
+
+        assert!(lock!(log.output).is_none());
+    }
+
+    #[test]
+    fn test_write_to_file() {
+        let path = env::temp_dir().join("inko_test_gc_log_write.log");
+        let _ = fs::remove_file(&path);
+
+        let log = Log::new(path.to_string_lossy().into_owned());
+
+        log.open().unwrap();
+        log.write(1, &profile());
+        log.write(2, &profile());
+
+        let mut contents = String::new();
+
+        File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents.lines().count(), 2);
+        assert!(contents.starts_with("gc pid=1 type=young"));
+    }
+}
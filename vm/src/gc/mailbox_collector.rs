@@ -3,8 +3,10 @@
 use gc::collector;
 use gc::profile::Profile;
 use gc::trace_result::TraceResult;
+use gc::verifier;
+use gc::weak_reference;
 use mailbox::Mailbox;
-use process::RcProcess;
+use process::{ExitReason, RcProcess};
 use vm::state::RcState;
 
 pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
@@ -20,7 +22,15 @@ pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
     profile.prepare.start();
 
     let lock = mailbox.write_lock.lock();
-    let move_objects = mailbox.allocator.prepare_for_collection();
+    let stress = vm_state.config.gc_stress;
+
+    let move_objects = if stress {
+        mailbox.allocator.prepare_for_full_evacuation();
+
+        true
+    } else {
+        mailbox.allocator.prepare_for_collection()
+    };
 
     profile.prepare.stop();
     profile.trace.start();
@@ -31,7 +41,19 @@ pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
     profile.reclaim.start();
 
     mailbox.allocator.reclaim_blocks();
+
+    profile.off_heap_bytes = mailbox.allocator.off_heap_bytes;
+
     process.update_mailbox_collection_statistics(&vm_state.config);
+
+    // The mailbox has to be verified while we hold the lock, as other
+    // processes may otherwise send messages while we're verifying it.
+    let violations = if stress {
+        verifier::verify_mailbox(process)
+    } else {
+        Vec::new()
+    };
+
     drop(lock); // unlock as soon as possible
 
     profile.reclaim.stop();
@@ -39,6 +61,12 @@ pub fn collect(vm_state: &RcState, process: &RcProcess) -> Profile {
 
     profile.populate_tracing_statistics(trace_result);
 
+    verifier::terminate_on_violations(vm_state, process, violations);
+
+    if process.mailbox_limit_exceeded() {
+        process.terminate_with_reason(ExitReason::OutOfMemory);
+    }
+
     vm_state.process_pools.schedule(process.clone());
 
     profile
@@ -48,22 +76,36 @@ pub fn trace(process: &RcProcess,
              mailbox: &Mailbox,
              move_objects: bool)
              -> TraceResult {
-    let roots = mailbox.mailbox_pointers();
+    let mut roots = mailbox.mailbox_pointers();
+
+    // The channels are kept alive until we finish tracing, as their messages
+    // would otherwise be deallocated while we're still using them.
+    let channels = mailbox.live_channels();
+
+    for channel in channels.iter() {
+        roots.append(&mut channel.pointers());
+    }
 
-    if move_objects {
+    let result = if move_objects {
         collector::trace_pointers_with_moving(process, roots, false)
     } else {
         collector::trace_pointers_without_moving(roots, false)
-    }
+    };
+
+    weak_reference::update_mailbox(process);
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use channel::Channel;
     use config::Config;
     use compiled_code::CompiledCode;
     use immix::global_allocator::GlobalAllocator;
     use immix::permanent_allocator::PermanentAllocator;
+    use object_value;
     use process::{Process, RcProcess};
     use vm::state::State;
 
@@ -103,6 +145,22 @@ mod tests {
         assert_eq!(profile.promoted, 0);
     }
 
+    #[test]
+    fn test_collect_with_off_heap_bytes() {
+        let (_perm_alloc, process) = new_process();
+        let state = State::new(Config::new());
+        let string = object_value::string("abc".to_string());
+        let message = process.allocate_without_prototype(string);
+
+        process.local_data_mut().mailbox.send_from_external(message);
+
+        let profile = collect(&state, &process);
+        let ref allocator = process.local_data().mailbox.allocator;
+
+        assert_eq!(profile.off_heap_bytes, 3);
+        assert_eq!(allocator.off_heap_bytes, 0);
+    }
+
     #[test]
     fn test_trace_without_moving() {
         let (_perm_alloc, process) = new_process();
@@ -120,6 +178,22 @@ mod tests {
         assert_eq!(result.promoted, 0);
     }
 
+    #[test]
+    fn test_trace_channel_messages() {
+        let (_perm_alloc, process) = new_process();
+        let channel = Channel::new(process.pid, None);
+
+        let mut local_data = process.local_data_mut();
+
+        local_data.mailbox.add_channel(&channel);
+        local_data.mailbox.send_to_channel(&channel, process.allocate_empty());
+        local_data.mailbox.allocator.prepare_for_collection();
+
+        let result = trace(&process, &local_data.mailbox, false);
+
+        assert_eq!(result.marked, 1);
+    }
+
     #[test]
     fn test_trace_with_moving() {
         let (_perm_alloc, process) = new_process();
@@ -138,4 +212,31 @@ mod tests {
         assert_eq!(result.evacuated, 1);
         assert_eq!(result.promoted, 0);
     }
+
+    #[test]
+    fn test_trace_with_weak_reference() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let proto = process.allocate_empty();
+        let weak = process.allocate_weak_reference(target, proto);
+
+        let message = process
+            .allocate_without_prototype(object_value::array(vec![target, weak]));
+
+        let mut local_data = process.local_data_mut();
+
+        local_data.mailbox.send_from_external(message);
+        local_data.mailbox.external[0].block_mut().fragmented = true;
+        local_data.mailbox.allocator.prepare_for_collection();
+
+        trace(&process, &local_data.mailbox, true);
+
+        let message = local_data.mailbox.external[0];
+        let values = message.get().value.as_array().unwrap();
+        let weak_target = values[1].get().value.as_weak_reference().unwrap();
+
+        assert!(values[0].is_mailbox());
+        assert!(weak_target == values[0]);
+        assert_eq!(local_data.mailbox.allocator.weak_references.len(), 1);
+    }
 }
@@ -1,6 +1,11 @@
 pub mod collector;
+pub mod finalizer;
 pub mod heap_collector;
+pub mod log;
 pub mod mailbox_collector;
 pub mod profile;
 pub mod request;
+pub mod statistics;
 pub mod trace_result;
+pub mod verifier;
+pub mod weak_reference;
@@ -11,6 +11,20 @@ pub enum CollectionType {
 
     /// A mailbox collection.
     Mailbox,
+
+    /// A full collection that compacts the entire heap.
+    Compact,
+}
+
+impl CollectionType {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CollectionType::Young => "young",
+            CollectionType::Full => "full",
+            CollectionType::Mailbox => "mailbox",
+            CollectionType::Compact => "compact",
+        }
+    }
 }
 
 pub struct Profile {
@@ -26,6 +40,10 @@ pub struct Profile {
     /// The number of objects promoted to the full generation.
     pub promoted: usize,
 
+    /// The approximate number of bytes allocated outside of the collected
+    /// heap since its previous collection.
+    pub off_heap_bytes: usize,
+
     /// The total garbage collection time.
     pub total: Timer,
 
@@ -46,6 +64,7 @@ impl Profile {
             marked: 0,
             evacuated: 0,
             promoted: 0,
+            off_heap_bytes: 0,
             total: Timer::new(),
             prepare: Timer::new(),
             trace: Timer::new(),
@@ -65,6 +84,10 @@ impl Profile {
         Self::new(CollectionType::Mailbox)
     }
 
+    pub fn compact() -> Self {
+        Self::new(CollectionType::Compact)
+    }
+
     pub fn populate_tracing_statistics(&mut self, result: TraceResult) {
         self.marked = result.marked;
         self.evacuated = result.evacuated;
@@ -85,6 +108,15 @@ mod tests {
         assert_eq!(profile.marked, 0);
         assert_eq!(profile.evacuated, 0);
         assert_eq!(profile.promoted, 0);
+        assert_eq!(profile.off_heap_bytes, 0);
+    }
+
+    #[test]
+    fn test_collection_type_name() {
+        assert_eq!(CollectionType::Young.name(), "young");
+        assert_eq!(CollectionType::Full.name(), "full");
+        assert_eq!(CollectionType::Mailbox.name(), "mailbox");
+        assert_eq!(CollectionType::Compact.name(), "compact");
     }
 
     #[test]
@@ -108,6 +140,13 @@ mod tests {
         assert_eq!(profile.collection_type, CollectionType::Mailbox);
     }
 
+    #[test]
+    fn test_compact() {
+        let profile = Profile::compact();
+
+        assert_eq!(profile.collection_type, CollectionType::Compact);
+    }
+
     #[test]
     fn test_populate_tracing_statistics() {
         let mut profile = Profile::new(CollectionType::Young);
@@ -5,6 +5,7 @@
 
 use gc::heap_collector;
 use gc::mailbox_collector;
+use gc::statistics;
 use process::RcProcess;
 use vm::state::RcState;
 
@@ -49,8 +50,7 @@ impl Request {
             return;
         }
 
-        // TODO: store profile details
-        let _profile = match self.collection_type {
+        let profile = match self.collection_type {
             CollectionType::Heap => {
                 heap_collector::collect(&self.vm_state, &self.process)
             }
@@ -59,9 +59,11 @@ impl Request {
             }
         };
 
-        println!("Finished {:?} collection in {:.2} ms",
-                 _profile.collection_type,
-                 _profile.total.duration_msec());
+        statistics::record(&self.vm_state, &self.process, &profile);
+
+        // Collections return blocks to the global allocator, possibly more
+        // than we want to retain.
+        self.vm_state.global_allocator.release_blocks();
     }
 }
 
@@ -139,4 +141,34 @@ mod tests {
 
         assert!(process.get_register(0).unwrap().is_marked());
     }
+
+    #[test]
+    fn test_perform_records_profile() {
+        let (_perm, process) = new_process();
+        let state = State::new(Config::new());
+        let request = Request::heap(state.clone(), process.clone());
+
+        process.running();
+        request.perform();
+
+        assert_eq!(lock!(process.gc_statistics).young.collections, 1);
+        assert_eq!(state.gc_statistics.lock().young.collections, 1);
+    }
+
+    #[test]
+    fn test_perform_releases_blocks() {
+        let (_perm, process) = new_process();
+        let mut config = Config::new();
+
+        config.free_block_limit = 1;
+
+        let state = State::new(config);
+        let request = Request::heap(state.clone(), process.clone());
+
+        process.running();
+        request.perform();
+
+        assert_eq!(lock!(state.global_allocator.blocks).len(), 1);
+        assert!(state.global_allocator.statistics().released > 0);
+    }
 }
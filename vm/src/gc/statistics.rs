@@ -0,0 +1,182 @@
+//! Aggregated statistics of garbage collections.
+//!
+//! Every collection produces a profile. These profiles are aggregated per
+//! collection type, both for every process and for the entire VM, making it
+//! possible to see how much time is spent collecting garbage and how effective
+//! said collections are.
+
+use gc::profile::{CollectionType, Profile};
+use process::RcProcess;
+use vm::state::State;
+
+/// The aggregated details of a single type of collection.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Totals {
+    /// The number of collections performed.
+    pub collections: usize,
+
+    /// The total number of marked objects.
+    pub marked: usize,
+
+    /// The total number of evacuated objects.
+    pub evacuated: usize,
+
+    /// The total number of promoted objects.
+    pub promoted: usize,
+
+    /// The total number of bytes allocated outside of the heap before these
+    /// collections.
+    pub off_heap_bytes: usize,
+
+    /// The total time spent collecting, in nanoseconds.
+    pub total: u64,
+
+    /// The total time spent preparing collections, in nanoseconds.
+    pub prepare: u64,
+
+    /// The total time spent tracing objects, in nanoseconds.
+    pub trace: u64,
+
+    /// The total time spent reclaiming blocks, in nanoseconds.
+    pub reclaim: u64,
+}
+
+impl Totals {
+    pub fn new() -> Self {
+        Totals {
+            collections: 0,
+            marked: 0,
+            evacuated: 0,
+            promoted: 0,
+            off_heap_bytes: 0,
+            total: 0,
+            prepare: 0,
+            trace: 0,
+            reclaim: 0,
+        }
+    }
+
+    pub fn record(&mut self, profile: &Profile) {
+        self.collections += 1;
+        self.marked += profile.marked;
+        self.evacuated += profile.evacuated;
+        self.promoted += profile.promoted;
+        self.off_heap_bytes += profile.off_heap_bytes;
+        self.total += profile.total.duration_nanosec();
+        self.prepare += profile.prepare.duration_nanosec();
+        self.trace += profile.trace.duration_nanosec();
+        self.reclaim += profile.reclaim.duration_nanosec();
+    }
+}
+
+/// The aggregated details of all collections, grouped by their type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Statistics {
+    pub young: Totals,
+    pub full: Totals,
+    pub mailbox: Totals,
+    pub compact: Totals,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Statistics {
+            young: Totals::new(),
+            full: Totals::new(),
+            mailbox: Totals::new(),
+            compact: Totals::new(),
+        }
+    }
+
+    /// Adds the details of a collection to the totals of its type.
+    pub fn record(&mut self, profile: &Profile) {
+        let totals = match profile.collection_type {
+            CollectionType::Young => &mut self.young,
+            CollectionType::Full => &mut self.full,
+            CollectionType::Mailbox => &mut self.mailbox,
+            CollectionType::Compact => &mut self.compact,
+        };
+
+        totals.record(profile);
+    }
+
+    /// Returns the totals of every collection type, along with the name of
+    /// the type.
+    pub fn all(&self) -> [(&'static str, &Totals); 4] {
+        [("young", &self.young),
+         ("full", &self.full),
+         ("mailbox", &self.mailbox),
+         ("compact", &self.compact)]
+    }
+}
+
+/// Records the profile of a collection of the given process.
+///
+/// The profile is added to the statistics of both the process and the VM,
+/// and written to the GC log if enabled.
+pub fn record(state: &State, process: &RcProcess, profile: &Profile) {
+    lock!(process.gc_statistics).record(profile);
+    state.gc_statistics.lock().record(profile);
+
+    if let Some(log) = state.gc_log.as_ref() {
+        log.write(process.pid, profile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gc::trace_result::TraceResult;
+
+    fn profile(collection_type: CollectionType) -> Profile {
+        let mut profile = Profile::new(collection_type);
+
+        profile.total.start();
+        profile.total.stop();
+        profile.populate_tracing_statistics(TraceResult::with(1, 2, 3));
+        profile.off_heap_bytes = 4;
+
+        profile
+    }
+
+    #[test]
+    fn test_totals_record() {
+        let mut totals = Totals::new();
+
+        totals.record(&profile(CollectionType::Young));
+        totals.record(&profile(CollectionType::Young));
+
+        assert_eq!(totals.collections, 2);
+        assert_eq!(totals.marked, 2);
+        assert_eq!(totals.evacuated, 4);
+        assert_eq!(totals.promoted, 6);
+        assert_eq!(totals.off_heap_bytes, 8);
+        assert_eq!(totals.prepare, 0);
+    }
+
+    #[test]
+    fn test_statistics_record() {
+        let mut stats = Statistics::new();
+
+        stats.record(&profile(CollectionType::Young));
+        stats.record(&profile(CollectionType::Full));
+        stats.record(&profile(CollectionType::Full));
+
+        assert_eq!(stats.young.collections, 1);
+        assert_eq!(stats.full.collections, 2);
+        assert_eq!(stats.mailbox.collections, 0);
+        assert_eq!(stats.compact.collections, 0);
+    }
+
+    #[test]
+    fn test_statistics_all() {
+        let mut stats = Statistics::new();
+
+        stats.record(&profile(CollectionType::Mailbox));
+
+        let all = stats.all();
+
+        assert_eq!(all[2].0, "mailbox");
+        assert_eq!(all[2].1.collections, 1);
+    }
+}
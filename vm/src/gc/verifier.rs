@@ -0,0 +1,450 @@
+//! Verifying the integrity of a process heap after a garbage collection.
+//!
+//! Bugs in the moving collector (e.g. a pointer that is not updated after an
+//! object is evacuated) usually don't show up until much later, when the
+//! memory of the object is re-used. To find these bugs as early as possible
+//! the verifier traverses all reachable objects right after a collection, and
+//! checks if every pointer:
+//!
+//! 1. Points to a block that belongs to a bucket, and is stored in said
+//!    bucket.
+//! 2. Points to an object that is not forwarded.
+//! 3. Points to a bucket owned by the process, or to a permanent object.
+//! 4. Points to an object that is marked as live, if the bucket of the object
+//!    was collected.
+//!
+//! Received messages are not copied out of the mailbox, so heap objects may
+//! point to mailbox objects. These objects are verified, but they are not
+//! checked for being live when verifying the heap.
+//!
+//! Every violation is reported along with the path to the object, such as
+//! "context 0 register 2 -> attribute foo -> index 3".
+
+use std::collections::HashSet;
+
+use binding::RcBinding;
+use immix::block::Block;
+use immix::bucket::{Bucket, MATURE, PERMANENT};
+use object_pointer::ObjectPointer;
+use object_value::ObjectValue;
+use process::RcProcess;
+use vm::machine::Machine;
+use vm::state::RcState;
+
+struct Verifier {
+    /// The buckets that were collected.
+    collected: Vec<*const Bucket>,
+
+    /// Other buckets objects may reside in, besides the permanent space.
+    others: Vec<*const Bucket>,
+
+    /// When set to true mature objects are checked for being live.
+    mature: bool,
+
+    /// The objects that have already been verified.
+    visited: HashSet<ObjectPointer>,
+
+    /// The objects to verify, and the path used to reach them.
+    pending: Vec<(ObjectPointer, String)>,
+
+    /// The problems found so far.
+    violations: Vec<String>,
+}
+
+impl Verifier {
+    fn new(collected: Vec<*const Bucket>,
+           others: Vec<*const Bucket>,
+           mature: bool)
+           -> Self {
+        Verifier {
+            collected: collected,
+            others: others,
+            mature: mature,
+            visited: HashSet::new(),
+            pending: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, pointer: ObjectPointer, path: String) {
+        // Tagged integers don't reside on the heap, so there's nothing to
+        // verify.
+        if !pointer.is_null() && !pointer.is_tagged_integer() {
+            self.pending.push((pointer, path));
+        }
+    }
+
+    fn push_binding(&mut self, binding: &RcBinding, path: String) {
+        self.push(binding.self_object(), format!("{} self", path));
+
+        for (index, local) in binding.locals().iter().enumerate() {
+            self.push(*local, format!("{} local {}", path, index));
+        }
+
+        if let Some(parent) = binding.parent() {
+            self.push_binding(&parent, format!("{} parent", path));
+        }
+    }
+
+    fn run(mut self) -> Vec<String> {
+        while let Some((pointer, path)) = self.pending.pop() {
+            if self.visited.contains(&pointer) {
+                continue;
+            }
+
+            self.visited.insert(pointer);
+
+            if self.verify_pointer(pointer, &path) {
+                self.push_children(pointer, &path);
+            }
+        }
+
+        self.violations
+    }
+
+    /// Verifies a single pointer, returning true if its children should be
+    /// verified as well.
+    fn verify_pointer(&mut self, pointer: ObjectPointer, path: &str) -> bool {
+        let block = pointer.block();
+
+        let bucket = if let Some(bucket) = block.bucket() {
+            bucket
+        } else {
+            self.violation(path, "is stored in a block without a bucket");
+
+            return false;
+        };
+
+        if pointer.is_forwarded() {
+            self.violation(path, "is a forwarding pointer");
+
+            return false;
+        }
+
+        // Permanent objects are not collected, so there's no need to verify
+        // them (or their children).
+        if bucket.age == PERMANENT {
+            return false;
+        }
+
+        let bucket_ptr = bucket as *const Bucket;
+        let collected = self.collected.contains(&bucket_ptr);
+
+        if !collected && !self.others.contains(&bucket_ptr) {
+            self.violation(path, "is stored in a bucket of another process");
+
+            return false;
+        }
+
+        if !bucket_contains(bucket, block) {
+            self.violation(path, "is stored in a block not owned by its bucket");
+
+            return false;
+        }
+
+        if collected && (bucket.age != MATURE || self.mature) &&
+           !pointer.is_marked() {
+            self.violation(path, "is not marked as live");
+        }
+
+        true
+    }
+
+    fn push_children(&mut self, pointer: ObjectPointer, path: &str) {
+        let object = pointer.get();
+
+        if let Some(prototype) = object.prototype() {
+            self.push(prototype, format!("{} -> prototype", path));
+        }
+
+        if let Some(header) = object.header() {
+            for (name, value) in header.attributes.iter() {
+                self.push(*value, format!("{} -> attribute {}", path, name));
+            }
+
+            for (name, value) in header.constants.iter() {
+                self.push(*value, format!("{} -> constant {}", path, name));
+            }
+
+            for (name, value) in header.methods.iter() {
+                self.push(*value, format!("{} -> method {}", path, name));
+            }
+
+            if let Some(scope) = header.outer_scope {
+                self.push(scope, format!("{} -> outer scope", path));
+            }
+        }
+
+        match object.value {
+            ObjectValue::Array(ref array) => {
+                for (index, value) in array.iter().enumerate() {
+                    self.push(*value, format!("{} -> index {}", path, index));
+                }
+            }
+            ObjectValue::Binding(ref binding) => {
+                self.push_binding(binding, format!("{} -> binding", path));
+            }
+            _ => {}
+        }
+    }
+
+    fn violation(&mut self, path: &str, problem: &str) {
+        self.violations.push(format!("{} {}", path, problem));
+    }
+}
+
+/// Returns true if the block belongs to the bucket.
+///
+/// The blocks of a mailbox bucket can only be iterated over while holding the
+/// write lock of the mailbox.
+fn bucket_contains(bucket: &Bucket, block: &Block) -> bool {
+    bucket.blocks
+        .iter()
+        .chain(bucket.recyclable_blocks.iter())
+        .any(|owned| &**owned as *const Block == block as *const Block)
+}
+
+/// Verifies all objects reachable from the heap of a process.
+///
+/// Mature objects are only checked for being live if `mature` is set to true,
+/// as a young collection doesn't mark mature objects.
+pub fn verify_heap(process: &RcProcess, mature: bool) -> Vec<String> {
+    let local_data = process.local_data();
+
+    // Other processes may allocate messages (and thus blocks) into the mailbox
+    // while we're verifying, so the lock is held until the verification is
+    // done.
+    let _lock = local_data.mailbox.write_lock.lock();
+
+    let ref allocator = local_data.allocator;
+
+    let mut buckets: Vec<*const Bucket> = allocator.young_generation
+        .iter()
+        .map(|bucket| bucket as *const Bucket)
+        .collect();
+
+    buckets.push(&allocator.mature_generation as *const Bucket);
+
+    let mailbox = vec![&local_data.mailbox.allocator.bucket as *const Bucket];
+    let mut verifier = Verifier::new(buckets, mailbox, mature);
+
+    for (index, context) in process.contexts().iter().enumerate() {
+        for (register, value) in context.register.values.iter().enumerate() {
+            verifier.push(*value,
+                          format!("context {} register {}", index, register));
+        }
+
+        verifier.push_binding(&context.binding,
+                              format!("context {} binding", index));
+    }
+
+    for (index, local) in local_data.mailbox.locals.iter().enumerate() {
+        verifier.push(*local, format!("mailbox local {}", index));
+    }
+
+    for pointer in local_data.remembered_set.iter() {
+        verifier.push(*pointer, "remembered set".to_string());
+    }
+
+    verifier.run()
+}
+
+/// Verifies all objects reachable from the mailbox of a process.
+///
+/// The caller must hold on to the write lock of the mailbox.
+pub fn verify_mailbox(process: &RcProcess) -> Vec<String> {
+    let ref mailbox = process.local_data().mailbox;
+    let buckets = vec![&mailbox.allocator.bucket as *const Bucket];
+    let mut verifier = Verifier::new(buckets, Vec::new(), true);
+
+    for (index, message) in mailbox.internal
+        .iter()
+        .chain(mailbox.external.iter())
+        .enumerate() {
+        verifier.push(*message, format!("mailbox message {}", index));
+    }
+
+    for (index, channel) in mailbox.live_channels().iter().enumerate() {
+        for pointer in channel.pointers() {
+            verifier.push(*pointer.get(),
+                          format!("channel {} message", index));
+        }
+    }
+
+    verifier.run()
+}
+
+/// Produces a human readable report of the violations found in a process.
+pub fn report(process: &RcProcess, violations: &[String]) -> String {
+    let mut message = format!("The heap of process {} is corrupted after a \
+                               garbage collection:\n",
+                              process.pid);
+
+    for violation in violations {
+        message.push_str(&format!("\n{}", violation));
+    }
+
+    message
+}
+
+/// Terminates the VM if any violations were found.
+pub fn terminate_on_violations(state: &RcState,
+                               process: &RcProcess,
+                               violations: Vec<String>) {
+    if violations.is_empty() {
+        return;
+    }
+
+    {
+        let mut exit_status = state.exit_status.lock();
+
+        if exit_status.is_ok() {
+            *exit_status = Err(report(process, &violations));
+        }
+    }
+
+    Machine::new(state.clone()).terminate();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compiled_code::CompiledCode;
+    use immix::global_allocator::GlobalAllocator;
+    use immix::permanent_allocator::PermanentAllocator;
+    use object::Object;
+    use object_value;
+    use process::Process;
+
+    fn new_process() -> (Box<PermanentAllocator>, RcProcess) {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+
+        let mut perm_alloc =
+            Box::new(PermanentAllocator::new(global_alloc.clone()));
+
+        let self_obj = perm_alloc.allocate_empty();
+
+        let code = CompiledCode::with_rc("a".to_string(),
+                                         "a".to_string(),
+                                         1,
+                                         Vec::new());
+
+        (perm_alloc, Process::from_code(1, 0, code, self_obj, global_alloc))
+    }
+
+    #[test]
+    fn test_verify_heap_valid() {
+        let (_perm_alloc, process) = new_process();
+        let pointer = process.allocate_empty();
+
+        pointer.mark();
+        process.set_register(0, pointer);
+
+        assert!(verify_heap(&process, true).is_empty());
+    }
+
+    #[test]
+    fn test_verify_heap_unmarked() {
+        let (_perm_alloc, process) = new_process();
+        let pointer = process.allocate_empty();
+
+        process.set_register(0, pointer);
+
+        assert_eq!(verify_heap(&process, true),
+                   vec!["context 0 register 0 is not marked as live"
+                            .to_string()]);
+    }
+
+    #[test]
+    fn test_verify_heap_unmarked_mature_without_mature() {
+        let (_perm_alloc, process) = new_process();
+
+        let pointer = process.local_data_mut()
+            .allocator
+            .allocate_mature(Object::new(object_value::none()));
+
+        process.set_register(0, pointer);
+
+        assert!(verify_heap(&process, false).is_empty());
+        assert_eq!(verify_heap(&process, true).len(), 1);
+    }
+
+    #[test]
+    fn test_verify_heap_forwarded() {
+        let (_perm_alloc, process) = new_process();
+        let old = process.allocate_empty();
+        let new = process.allocate_empty();
+        let object = process.allocate_empty();
+
+        old.get_mut().forward_to(new);
+        new.mark();
+        object.mark();
+        object.add_attribute(&process, "foo".to_string(), old);
+        process.set_register(0, object);
+
+        assert_eq!(verify_heap(&process, true),
+                   vec!["context 0 register 0 -> attribute foo is a \
+                         forwarding pointer"
+                            .to_string()]);
+    }
+
+    #[test]
+    fn test_verify_heap_mailbox_object() {
+        let (_perm_alloc, process) = new_process();
+        let pointer = process.local_data_mut()
+            .mailbox
+            .allocator
+            .allocate(Object::new(object_value::none()));
+
+        process.set_register(0, pointer);
+
+        assert!(verify_heap(&process, true).is_empty());
+    }
+
+    #[test]
+    fn test_verify_heap_object_of_other_process() {
+        let (_perm_alloc, process) = new_process();
+        let (_other_perm_alloc, other) = new_process();
+        let pointer = other.allocate_empty();
+
+        pointer.mark();
+        process.set_register(0, pointer);
+
+        assert_eq!(verify_heap(&process, true),
+                   vec!["context 0 register 0 is stored in a bucket of \
+                         another process"
+                            .to_string()]);
+    }
+
+    #[test]
+    fn test_verify_heap_ignores_permanent_objects() {
+        let (mut perm_alloc, process) = new_process();
+        let pointer = perm_alloc.allocate_empty();
+
+        process.set_register(0, pointer);
+
+        assert!(verify_heap(&process, true).is_empty());
+    }
+
+    #[test]
+    fn test_verify_mailbox() {
+        let (_perm_alloc, process) = new_process();
+        let (_sender_perm_alloc, sender) = new_process();
+        let message = sender.allocate_empty();
+
+        process.send_message(&sender, message);
+
+        assert_eq!(verify_mailbox(&process),
+                   vec!["mailbox message 0 is not marked as live"
+                            .to_string()]);
+    }
+
+    #[test]
+    fn test_report() {
+        let (_perm_alloc, process) = new_process();
+        let report = report(&process, &["a is broken".to_string()]);
+
+        assert!(report.contains("process 1 is corrupted"));
+        assert!(report.ends_with("\na is broken"));
+    }
+}
@@ -0,0 +1,294 @@
+//! Updating and clearing weak references after tracing.
+//!
+//! Weak references are not traced, instead every heap keeps track of the weak
+//! references allocated on it. Once all other objects have been traced, weak
+//! references to objects that were not marked are cleared. This happens
+//! before objects are resurrected for their finalizers, so weak references
+//! never refer to objects that are only kept around to run a finalizer.
+//!
+//! Once objects have been resurrected, weak references to objects that were
+//! moved are updated. Weak references that are no longer reachable themselves
+//! are forgotten, as are weak references that have been cleared or refer to
+//! objects that are never collected.
+
+use object_pointer::ObjectPointer;
+use object_value::ObjectValue;
+use process::RcProcess;
+
+/// Clears the weak references of a process that refer to unmarked objects.
+///
+/// This function must be called after tracing, but before resurrecting
+/// objects for their finalizers. This includes weak references that were not
+/// marked themselves, as these may still be resurrected.
+pub fn clear_heap(process: &RcProcess, mature: bool) {
+    let ref references = process.local_data().allocator.weak_references;
+
+    let is_garbage = |pointer: &ObjectPointer| {
+        !pointer.is_forwarded() && !pointer.is_marked() &&
+        (pointer.is_young() || mature && pointer.is_mature())
+    };
+
+    for reference in references.iter() {
+        if reference.is_forwarded() {
+            reference.resolve_forwarding_pointer();
+        }
+
+        update_target(*reference, &is_garbage);
+    }
+}
+
+/// Updates the weak references of a process after collecting its heap.
+///
+/// Mature objects are only considered if the mature generation was collected,
+/// as a young collection doesn't mark mature objects.
+///
+/// This function must be called after all objects have been traced, including
+/// objects resurrected for their finalizers.
+pub fn update_heap(process: &RcProcess, mature: bool) {
+    let ref mut references = process.local_data_mut().allocator.weak_references;
+
+    update(references,
+           |pointer| pointer.is_young() || mature && pointer.is_mature());
+}
+
+/// Updates the weak references of a process after collecting its mailbox.
+///
+/// Weak references on the process heap may refer to messages in the mailbox,
+/// so these are updated as well.
+pub fn update_mailbox(process: &RcProcess) {
+    let mut local_data = process.local_data_mut();
+
+    update(&mut local_data.mailbox.allocator.weak_references,
+           |pointer| pointer.is_mailbox());
+
+    update(&mut local_data.allocator.weak_references,
+           |pointer| pointer.is_mailbox());
+}
+
+/// Updates a list of weak references.
+///
+/// The `collected` closure returns true if the given object was part of the
+/// collected heap.
+fn update<F>(references: &mut Vec<ObjectPointer>, collected: F)
+    where F: Fn(&ObjectPointer) -> bool
+{
+    let is_garbage = |pointer: &ObjectPointer| {
+        !pointer.is_forwarded() && !pointer.is_marked() && collected(pointer)
+    };
+
+    references.retain(|reference| {
+        if reference.is_forwarded() {
+            reference.resolve_forwarding_pointer();
+        } else if is_garbage(reference) {
+            return false;
+        }
+
+        update_target(*reference, &is_garbage)
+    });
+}
+
+/// Updates or clears the target of a weak reference.
+///
+/// Returns true if the weak reference has to be updated again after future
+/// collections.
+fn update_target<F>(reference: ObjectPointer, is_garbage: &F) -> bool
+    where F: Fn(&ObjectPointer) -> bool
+{
+    let target = match reference.get_mut().value {
+        ObjectValue::WeakReference(ref mut target) => target,
+        _ => return false,
+    };
+
+    if target.is_null() || target.is_permanent() {
+        return false;
+    }
+
+    if target.is_forwarded() {
+        target.resolve_forwarding_pointer();
+    } else if is_garbage(target) {
+        *target = ObjectPointer::null();
+
+        return false;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compiled_code::CompiledCode;
+    use gc::finalizer;
+    use immix::global_allocator::GlobalAllocator;
+    use immix::permanent_allocator::PermanentAllocator;
+    use object::Object;
+    use object_value;
+    use process::Process;
+
+    fn new_process() -> (Box<PermanentAllocator>, RcProcess) {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+
+        let mut perm_alloc =
+            Box::new(PermanentAllocator::new(global_alloc.clone()));
+
+        let self_obj = perm_alloc.allocate_empty();
+
+        let code = CompiledCode::with_rc("a".to_string(),
+                                         "a".to_string(),
+                                         1,
+                                         Vec::new());
+
+        (perm_alloc, Process::from_code(1, 0, code, self_obj, global_alloc))
+    }
+
+    fn allocate_weak_reference(process: &RcProcess,
+                               target: ObjectPointer)
+                               -> ObjectPointer {
+        let proto = process.allocate_empty();
+
+        process.allocate_weak_reference(target, proto)
+    }
+
+    fn weak_references(process: &RcProcess) -> Vec<ObjectPointer> {
+        process.local_data().allocator.weak_references.clone()
+    }
+
+    #[test]
+    fn test_update_heap_with_unmarked_target() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let weak = allocate_weak_reference(&process, target);
+
+        weak.mark();
+        update_heap(&process, false);
+
+        assert!(weak.get().value.as_weak_reference().unwrap().is_null());
+        assert!(weak_references(&process).is_empty());
+    }
+
+    #[test]
+    fn test_update_heap_with_marked_target() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let weak = allocate_weak_reference(&process, target);
+
+        weak.mark();
+        target.mark();
+        update_heap(&process, false);
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == target);
+        assert!(weak_references(&process) == vec![weak]);
+    }
+
+    #[test]
+    fn test_update_heap_with_unmarked_weak_reference() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+
+        allocate_weak_reference(&process, target);
+        target.mark();
+        update_heap(&process, false);
+
+        assert!(weak_references(&process).is_empty());
+    }
+
+    #[test]
+    fn test_update_heap_with_forwarded_target() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let weak = allocate_weak_reference(&process, target);
+
+        let new_target = process.local_data_mut()
+            .allocator
+            .allocate_mature(Object::new(object_value::none()));
+
+        weak.mark();
+        target.get_mut().forward_to(new_target);
+        update_heap(&process, false);
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == new_target);
+        assert!(weak_references(&process) == vec![weak]);
+    }
+
+    #[test]
+    fn test_update_heap_with_mature_target_during_young_collection() {
+        let (_perm_alloc, process) = new_process();
+
+        let target = process.local_data_mut()
+            .allocator
+            .allocate_mature(Object::new(object_value::none()));
+
+        let weak = allocate_weak_reference(&process, target);
+
+        weak.mark();
+        update_heap(&process, false);
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == target);
+
+        update_heap(&process, true);
+
+        assert!(weak.get().value.as_weak_reference().unwrap().is_null());
+    }
+
+    #[test]
+    fn test_update_heap_with_tagged_integer_target() {
+        let (_perm_alloc, process) = new_process();
+        let target = ObjectPointer::integer(5);
+        let weak = allocate_weak_reference(&process, target);
+
+        weak.mark();
+        update_heap(&process, true);
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == target);
+        assert!(weak_references(&process).is_empty());
+    }
+
+    #[test]
+    fn test_clear_heap_with_finalizable_target() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let weak = allocate_weak_reference(&process, target);
+
+        process.register_finalizer(target, process.compiled_code());
+
+        weak.mark();
+        clear_heap(&process, false);
+        finalizer::resurrect(&process, false, false);
+        update_heap(&process, false);
+
+        assert!(target.is_marked());
+        assert!(weak.get().value.as_weak_reference().unwrap().is_null());
+        assert!(weak_references(&process).is_empty());
+        assert_eq!(process.local_data().finalize_queue.len(), 1);
+    }
+
+    #[test]
+    fn test_clear_heap_with_marked_target() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.allocate_empty();
+        let weak = allocate_weak_reference(&process, target);
+
+        weak.mark();
+        target.mark();
+        clear_heap(&process, false);
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == target);
+        assert!(weak_references(&process) == vec![weak]);
+    }
+
+    #[test]
+    fn test_update_mailbox() {
+        let (_perm_alloc, process) = new_process();
+        let target = process.local_data_mut()
+            .mailbox
+            .allocator
+            .allocate(Object::new(object_value::none()));
+
+        let weak = allocate_weak_reference(&process, target);
+
+        update_mailbox(&process);
+
+        assert!(weak.get().value.as_weak_reference().unwrap().is_null());
+        assert!(weak_references(&process).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Limits on the amount of memory a process can use.
+//!
+//! A process that keeps allocating memory would otherwise keep requesting
+//! blocks from the global allocator until the host runs out of memory. To
+//! prevent this every process can be limited to a number of blocks for the
+//! young generation, the mature generation, and its mailbox.
+//!
+//! Limits are checked at garbage collection safepoints. A process exceeding
+//! one of its limits is collected, and terminated with an out of memory
+//! reason if it still exceeds the limit afterwards. This means a process may
+//! use more memory than its limits allow for a short amount of time.
+//!
+//! The number of blocks used by all processes combined can be limited as well,
+//! in which case processes allocating blocks while the limit is exceeded are
+//! treated as if they exceeded their own limits.
+
+use config::Config;
+
+/// The maximum number of blocks a process can use. A limit of 0 means no limit
+/// is applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapLimits {
+    /// The maximum number of blocks of the young generation.
+    pub young: usize,
+
+    /// The maximum number of blocks of the mature generation.
+    pub mature: usize,
+
+    /// The maximum number of blocks of the mailbox.
+    pub mailbox: usize,
+}
+
+impl HeapLimits {
+    pub fn unlimited() -> Self {
+        HeapLimits {
+            young: 0,
+            mature: 0,
+            mailbox: 0,
+        }
+    }
+
+    /// Returns the limits to use for a process by default.
+    pub fn from_config(config: &Config) -> Self {
+        HeapLimits {
+            young: config.young_block_limit,
+            mature: config.mature_block_limit,
+            mailbox: config.mailbox_block_limit,
+        }
+    }
+
+    /// Returns true if the number of young or mature blocks exceeds their
+    /// limit.
+    pub fn heap_exceeded(&self, young: usize, mature: usize) -> bool {
+        exceeds(young, self.young) || exceeds(mature, self.mature)
+    }
+
+    /// Returns true if the number of mailbox blocks exceeds its limit.
+    pub fn mailbox_exceeded(&self, mailbox: usize) -> bool {
+        exceeds(mailbox, self.mailbox)
+    }
+}
+
+fn exceeds(blocks: usize, limit: usize) -> bool {
+    limit > 0 && blocks > limit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited() {
+        let limits = HeapLimits::unlimited();
+
+        assert_eq!(limits.heap_exceeded(1000, 1000), false);
+        assert_eq!(limits.mailbox_exceeded(1000), false);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = Config::new();
+
+        config.young_block_limit = 1;
+        config.mature_block_limit = 2;
+        config.mailbox_block_limit = 3;
+
+        let limits = HeapLimits::from_config(&config);
+
+        assert_eq!(limits.young, 1);
+        assert_eq!(limits.mature, 2);
+        assert_eq!(limits.mailbox, 3);
+    }
+
+    #[test]
+    fn test_heap_exceeded() {
+        let mut limits = HeapLimits::unlimited();
+
+        limits.young = 2;
+        limits.mature = 4;
+
+        assert_eq!(limits.heap_exceeded(2, 4), false);
+        assert!(limits.heap_exceeded(3, 0));
+        assert!(limits.heap_exceeded(0, 5));
+    }
+
+    #[test]
+    fn test_mailbox_exceeded() {
+        let mut limits = HeapLimits::unlimited();
+
+        limits.mailbox = 1;
+
+        assert_eq!(limits.mailbox_exceeded(1), false);
+        assert!(limits.mailbox_exceeded(2));
+    }
+}
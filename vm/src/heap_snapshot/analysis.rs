@@ -0,0 +1,490 @@
+//! Analysing heap snapshots.
+//!
+//! The memory retained by an object is the memory that would be released if
+//! the object were no longer reachable. This is calculated using the dominator
+//! tree of the object graph: an object dominates another object if every path
+//! from the roots to the other object goes through it. The retained size of an
+//! object is then the sum of its own size and the sizes of all objects it
+//! dominates.
+//!
+//! The dominator tree is built using the algorithm described in "A Simple,
+//! Fast Dominance Algorithm" by Cooper, Harvey, and Kennedy.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use heap_snapshot::reader::Snapshot;
+use process_table::PID;
+
+/// The address of the node that refers to all roots.
+const ROOT: usize = 0;
+
+pub struct Dominators {
+    /// The addresses of all reachable objects in post-order, the last node
+    /// being the virtual root.
+    addresses: Vec<usize>,
+
+    /// The index of every address in `addresses`.
+    indexes: HashMap<usize, usize>,
+
+    /// The index of the immediate dominator of every node.
+    dominators: Vec<usize>,
+
+    /// The retained size of every node.
+    retained: Vec<usize>,
+}
+
+/// The memory used by all objects using the same prototype.
+pub struct PrototypeSummary {
+    /// The address of the prototype, or 0 for objects without a prototype.
+    pub prototype: usize,
+
+    /// The number of objects using the prototype.
+    pub count: usize,
+
+    /// The total size of the objects, excluding the objects they refer to.
+    pub shallow_size: usize,
+
+    /// The total amount of memory retained by the objects.
+    ///
+    /// Objects retained by another object using the same prototype are only
+    /// counted once.
+    pub retained_size: usize,
+}
+
+/// A single object on a path from a root.
+pub struct Step {
+    /// The label of the root or edge leading to the object.
+    pub label: String,
+    pub address: usize,
+}
+
+/// The path from a root to an object.
+pub struct Path {
+    /// The process the root belongs to.
+    pub pid: PID,
+    pub steps: Vec<Step>,
+}
+
+impl Dominators {
+    /// Builds the dominator tree of the given snapshot.
+    pub fn new(snapshot: &Snapshot) -> Self {
+        let addresses = post_order(snapshot);
+        let mut indexes = HashMap::new();
+
+        for (index, address) in addresses.iter().enumerate() {
+            indexes.insert(*address, index);
+        }
+
+        let root = addresses.len() - 1;
+        let mut predecessors = vec![Vec::new(); addresses.len()];
+
+        for (index, address) in addresses.iter().enumerate() {
+            for successor in successors(snapshot, *address) {
+                if let Some(successor_index) = indexes.get(&successor) {
+                    predecessors[*successor_index].push(index);
+                }
+            }
+        }
+
+        let mut dominators = vec![None; addresses.len()];
+        let mut changed = true;
+
+        dominators[root] = Some(root);
+
+        while changed {
+            changed = false;
+
+            for node in (0..root).rev() {
+                let mut new_dominator = None;
+
+                for &predecessor in predecessors[node].iter() {
+                    if dominators[predecessor].is_none() {
+                        continue;
+                    }
+
+                    new_dominator = match new_dominator {
+                        Some(current) => {
+                            Some(intersect(&dominators, predecessor, current))
+                        }
+                        None => Some(predecessor),
+                    };
+                }
+
+                if new_dominator != dominators[node] {
+                    dominators[node] = new_dominator;
+                    changed = true;
+                }
+            }
+        }
+
+        let dominators: Vec<usize> =
+            dominators.into_iter().map(|index| index.unwrap()).collect();
+
+        // Dominators always come after the nodes they dominate, so by the time
+        // we add a node to its dominator its own size is complete.
+        let mut retained: Vec<usize> = addresses.iter()
+            .map(|address| {
+                snapshot.objects.get(address).map(|obj| obj.size).unwrap_or(0)
+            })
+            .collect();
+
+        for node in 0..root {
+            let size = retained[node];
+
+            retained[dominators[node]] += size;
+        }
+
+        Dominators {
+            addresses: addresses,
+            indexes: indexes,
+            dominators: dominators,
+            retained: retained,
+        }
+    }
+
+    /// Returns the retained size of an object.
+    pub fn retained_size(&self, address: usize) -> Option<usize> {
+        self.indexes.get(&address).map(|index| self.retained[*index])
+    }
+
+    /// Returns the immediate dominator of an object.
+    ///
+    /// None is returned for objects only dominated by the roots, and for
+    /// objects not in the snapshot.
+    pub fn immediate_dominator(&self, address: usize) -> Option<usize> {
+        self.indexes
+            .get(&address)
+            .map(|index| self.addresses[self.dominators[*index]])
+            .and_then(|address| if address == ROOT {
+                None
+            } else {
+                Some(address)
+            })
+    }
+
+    /// Returns the memory used per prototype, sorted from the largest to the
+    /// smallest retained size.
+    pub fn summarize(&self, snapshot: &Snapshot) -> Vec<PrototypeSummary> {
+        let root = self.addresses.len() - 1;
+        let mut children = vec![Vec::new(); self.addresses.len()];
+
+        for node in 0..root {
+            children[self.dominators[node]].push(node);
+        }
+
+        let mut summaries: HashMap<usize, PrototypeSummary> = HashMap::new();
+
+        // The number of objects per prototype on the current path through the
+        // dominator tree. An object's retained size is only counted if none of
+        // its dominators use the same prototype.
+        let mut active: HashMap<usize, usize> = HashMap::new();
+        let mut stack: Vec<(usize, bool)> =
+            children[root].iter().map(|node| (*node, false)).collect();
+
+        while let Some((node, exit)) = stack.pop() {
+            let object = &snapshot.objects[&self.addresses[node]];
+            let prototype = object.prototype;
+
+            if exit {
+                *active.get_mut(&prototype).unwrap() -= 1;
+                continue;
+            }
+
+            let count = active.entry(prototype).or_insert(0);
+            let summary = summaries.entry(prototype)
+                .or_insert_with(|| {
+                    PrototypeSummary {
+                        prototype: prototype,
+                        count: 0,
+                        shallow_size: 0,
+                        retained_size: 0,
+                    }
+                });
+
+            summary.count += 1;
+            summary.shallow_size += object.size;
+
+            if *count == 0 {
+                summary.retained_size += self.retained[node];
+            }
+
+            *count += 1;
+
+            stack.push((node, true));
+
+            for child in children[node].iter() {
+                stack.push((*child, false));
+            }
+        }
+
+        let mut summaries: Vec<PrototypeSummary> =
+            summaries.into_iter().map(|(_, summary)| summary).collect();
+
+        summaries.sort_by(|a, b| {
+            b.retained_size
+                .cmp(&a.retained_size)
+                .then(a.prototype.cmp(&b.prototype))
+        });
+
+        summaries
+    }
+}
+
+/// Returns the shortest path from any root to the given object.
+pub fn shortest_path(snapshot: &Snapshot, address: usize) -> Option<Path> {
+    // The object each object was reached from, and the label of the edge.
+    let mut parents: HashMap<usize, (usize, String)> = HashMap::new();
+    let mut roots = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    for root in snapshot.roots.iter() {
+        if !roots.contains_key(&root.address) {
+            roots.insert(root.address, root);
+            queue.push_back(root.address);
+        }
+    }
+
+    let mut found = false;
+
+    while let Some(current) = queue.pop_front() {
+        if current == address {
+            found = true;
+            break;
+        }
+
+        let object = match snapshot.objects.get(&current) {
+            Some(object) => object,
+            None => continue,
+        };
+
+        for &(child, ref label) in object.edges.iter() {
+            if roots.contains_key(&child) || parents.contains_key(&child) {
+                continue;
+            }
+
+            parents.insert(child, (current, label.clone()));
+            queue.push_back(child);
+        }
+    }
+
+    if !found {
+        return None;
+    }
+
+    let mut steps = Vec::new();
+    let mut current = address;
+
+    while let Some(&(parent, ref label)) = parents.get(&current) {
+        steps.push(Step {
+            label: label.clone(),
+            address: current,
+        });
+
+        current = parent;
+    }
+
+    let root = roots[&current];
+
+    steps.push(Step {
+        label: root.label.clone(),
+        address: current,
+    });
+
+    steps.reverse();
+
+    Some(Path {
+        pid: root.pid,
+        steps: steps,
+    })
+}
+
+fn successors(snapshot: &Snapshot, address: usize) -> Vec<usize> {
+    if address == ROOT {
+        return snapshot.roots.iter().map(|root| root.address).collect();
+    }
+
+    match snapshot.objects.get(&address) {
+        Some(object) => {
+            object.edges
+                .iter()
+                .map(|&(child, _)| child)
+                .filter(|child| snapshot.objects.contains_key(child))
+                .collect()
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Returns the addresses of all reachable objects in depth-first post-order,
+/// ending with the virtual root.
+fn post_order(snapshot: &Snapshot) -> Vec<usize> {
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![(ROOT, successors(snapshot, ROOT), 0)];
+
+    visited.insert(ROOT);
+
+    while !stack.is_empty() {
+        let next = {
+            let top = stack.last_mut().unwrap();
+
+            if top.2 < top.1.len() {
+                top.2 += 1;
+
+                Some(top.1[top.2 - 1])
+            } else {
+                None
+            }
+        };
+
+        match next {
+            Some(child) => {
+                if visited.insert(child) {
+                    stack.push((child, successors(snapshot, child), 0));
+                }
+            }
+            None => order.push(stack.pop().unwrap().0),
+        }
+    }
+
+    order
+}
+
+fn intersect(dominators: &Vec<Option<usize>>,
+             mut first: usize,
+             mut second: usize)
+             -> usize {
+    while first != second {
+        while first < second {
+            first = dominators[first].unwrap();
+        }
+
+        while second < first {
+            second = dominators[second].unwrap();
+        }
+    }
+
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heap_snapshot::reader::Snapshot;
+
+    // The object graph used for these tests:
+    //
+    //     root 1 -> 0x10 -> 0x20 -> 0x30
+    //                  \---> 0x40 <- root 2
+    //
+    // 0x10 and 0x20 use the same prototype (0x100), the others have no
+    // prototype.
+    const SNAPSHOT: &'static str = "inko-heap-snapshot 1
+name 0x100 Person
+process 1
+root 1 0x10 context 0 register 0
+root 1 0x40 mailbox local 0
+object 0x10 1 10 none 0x100
+edge 0x10 0x20 attribute @friend
+edge 0x10 0x40 attribute @name
+object 0x20 1 20 none 0x100
+edge 0x20 0x30 attribute @name
+object 0x30 1 30 string 0x0
+object 0x40 1 40 string 0x0
+";
+
+    fn snapshot() -> Snapshot {
+        Snapshot::parse(SNAPSHOT).unwrap()
+    }
+
+    #[test]
+    fn test_dominators_retained_size() {
+        let snapshot = snapshot();
+        let dominators = Dominators::new(&snapshot);
+
+        assert_eq!(dominators.retained_size(0x10), Some(60));
+        assert_eq!(dominators.retained_size(0x20), Some(50));
+        assert_eq!(dominators.retained_size(0x30), Some(30));
+        assert_eq!(dominators.retained_size(0x40), Some(40));
+        assert_eq!(dominators.retained_size(0x50), None);
+    }
+
+    #[test]
+    fn test_dominators_immediate_dominator() {
+        let snapshot = snapshot();
+        let dominators = Dominators::new(&snapshot);
+
+        assert_eq!(dominators.immediate_dominator(0x10), None);
+        assert_eq!(dominators.immediate_dominator(0x20), Some(0x10));
+        assert_eq!(dominators.immediate_dominator(0x30), Some(0x20));
+        assert_eq!(dominators.immediate_dominator(0x40), None);
+    }
+
+    #[test]
+    fn test_dominators_with_cycle() {
+        let input = "inko-heap-snapshot 1
+root 1 0x10 context 0 register 0
+object 0x10 1 10 none 0x0
+edge 0x10 0x20 index 0
+object 0x20 1 20 none 0x0
+edge 0x20 0x10 index 0
+";
+
+        let snapshot = Snapshot::parse(input).unwrap();
+        let dominators = Dominators::new(&snapshot);
+
+        assert_eq!(dominators.retained_size(0x10), Some(30));
+        assert_eq!(dominators.immediate_dominator(0x20), Some(0x10));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let snapshot = snapshot();
+        let summaries = Dominators::new(&snapshot).summarize(&snapshot);
+
+        assert_eq!(summaries.len(), 2);
+
+        assert_eq!(summaries[0].prototype, 0);
+        assert_eq!(summaries[0].count, 2);
+        assert_eq!(summaries[0].shallow_size, 70);
+        assert_eq!(summaries[0].retained_size, 70);
+
+        // 0x20 is retained by 0x10, so it's only counted once.
+        assert_eq!(summaries[1].prototype, 0x100);
+        assert_eq!(summaries[1].count, 2);
+        assert_eq!(summaries[1].shallow_size, 30);
+        assert_eq!(summaries[1].retained_size, 60);
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let snapshot = snapshot();
+        let path = shortest_path(&snapshot, 0x30).unwrap();
+        let steps: Vec<(usize, &str)> = path.steps
+            .iter()
+            .map(|step| (step.address, step.label.as_str()))
+            .collect();
+
+        assert_eq!(path.pid, 1);
+        assert_eq!(steps,
+                   vec![(0x10, "context 0 register 0"),
+                        (0x20, "attribute @friend"),
+                        (0x30, "attribute @name")]);
+    }
+
+    #[test]
+    fn test_shortest_path_prefers_roots() {
+        let snapshot = snapshot();
+        let path = shortest_path(&snapshot, 0x40).unwrap();
+
+        assert_eq!(path.steps.len(), 1);
+        assert_eq!(path.steps[0].label, "mailbox local 0".to_string());
+    }
+
+    #[test]
+    fn test_shortest_path_unreachable() {
+        let snapshot = snapshot();
+
+        assert!(shortest_path(&snapshot, 0x50).is_none());
+    }
+}
@@ -0,0 +1,45 @@
+//! Heap snapshots for finding out what objects are retained.
+//!
+//! A heap snapshot contains every object reachable from the roots of one or
+//! more processes, along with the references between these objects. Snapshots
+//! are written by the VM and read by the "iheap" analyzer, which reports the
+//! memory retained per prototype and the shortest paths from the roots to
+//! individual objects.
+//!
+//! Snapshots are stored as text, using one record per line. Every record
+//! starts with its type, followed by a number of space separated fields. A
+//! snapshot looks as follows:
+//!
+//!     inko-heap-snapshot 1
+//!     name 0x7f3c4c000020 Integer
+//!     process 1
+//!     root 1 0x7f3c4c008040 context 0 register 2
+//!     object 0x7f3c4c008040 1 48 string 0x7f3c4c000060
+//!     edge 0x7f3c4c008040 0x7f3c4c000060 prototype
+//!
+//! The records are:
+//!
+//! * `name ADDRESS NAME`: the name of a permanent object, such as the
+//!   prototype for integers.
+//! * `process PID`: a process included in the snapshot.
+//! * `root PID ADDRESS LABEL`: a root of a process, such as a register.
+//! * `object ADDRESS OWNER SIZE KIND PROTOTYPE`: an object, the PID of the
+//!   process owning it (or "permanent" for permanent objects), its size in
+//!   bytes, the type of its value, and the address of its prototype (0x0 if it
+//!   doesn't have one).
+//! * `edge FROM TO LABEL`: a reference from one object to another, such as an
+//!   attribute.
+//!
+//! Labels and names are always the last field, and may contain spaces.
+//!
+//! Tagged integers are not objects, and thus are not included.
+
+pub mod analysis;
+pub mod reader;
+pub mod writer;
+
+/// The first line of every snapshot.
+pub const HEADER: &'static str = "inko-heap-snapshot 1";
+
+/// The owner of permanent objects.
+pub const PERMANENT_OWNER: &'static str = "permanent";
@@ -0,0 +1,292 @@
+//! Reading heap snapshots written by the VM.
+
+use std::collections::HashMap;
+
+use heap_snapshot::{HEADER, PERMANENT_OWNER};
+use process_table::PID;
+
+/// An object stored in a snapshot.
+pub struct SnapshotObject {
+    pub address: usize,
+
+    /// The process that owns the object, or None for permanent objects.
+    pub owner: Option<PID>,
+
+    /// The size of the object in bytes, excluding the objects it refers to.
+    pub size: usize,
+
+    /// The type of value stored in the object, such as "string".
+    pub kind: String,
+
+    /// The address of the prototype, or 0 if the object has no prototype.
+    pub prototype: usize,
+
+    /// The objects this object refers to, along with the labels of these
+    /// references.
+    pub edges: Vec<(usize, String)>,
+}
+
+/// A root of a process.
+pub struct Root {
+    pub pid: PID,
+    pub address: usize,
+    pub label: String,
+}
+
+pub struct Snapshot {
+    pub objects: HashMap<usize, SnapshotObject>,
+    pub roots: Vec<Root>,
+
+    /// The names of well known objects, such as the Integer prototype.
+    pub names: HashMap<usize, String>,
+
+    /// The PIDs of the processes included in the snapshot.
+    pub processes: Vec<PID>,
+}
+
+impl Snapshot {
+    /// Parses a snapshot from a String.
+    pub fn parse(input: &str) -> Result<Snapshot, String> {
+        let mut lines = input.lines().enumerate();
+
+        match lines.next() {
+            Some((_, line)) if line == HEADER => {}
+            _ => return Err("The input is not a heap snapshot".to_string()),
+        }
+
+        let mut snapshot = Snapshot {
+            objects: HashMap::new(),
+            roots: Vec::new(),
+            names: HashMap::new(),
+            processes: Vec::new(),
+        };
+
+        // Edges may refer to objects defined further down the snapshot, so
+        // we only add them once all objects have been parsed.
+        let mut edges = Vec::new();
+
+        for (index, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let number = index + 1;
+            let mut fields = line.splitn(2, ' ');
+            let kind = fields.next().unwrap();
+            let rest = fields.next().unwrap_or("");
+
+            match kind {
+                "name" => {
+                    let fields = split(rest, 2, number)?;
+
+                    snapshot.names
+                        .insert(parse_address(fields[0], number)?,
+                                fields[1].to_string());
+                }
+                "process" => {
+                    snapshot.processes.push(parse_number(rest, number)?);
+                }
+                "root" => {
+                    let fields = split(rest, 3, number)?;
+
+                    snapshot.roots.push(Root {
+                        pid: parse_number(fields[0], number)?,
+                        address: parse_address(fields[1], number)?,
+                        label: fields[2].to_string(),
+                    });
+                }
+                "object" => {
+                    let fields = split(rest, 5, number)?;
+                    let address = parse_address(fields[0], number)?;
+
+                    let owner = if fields[1] == PERMANENT_OWNER {
+                        None
+                    } else {
+                        Some(parse_number(fields[1], number)?)
+                    };
+
+                    let object = SnapshotObject {
+                        address: address,
+                        owner: owner,
+                        size: parse_number(fields[2], number)?,
+                        kind: fields[3].to_string(),
+                        prototype: parse_address(fields[4], number)?,
+                        edges: Vec::new(),
+                    };
+
+                    snapshot.objects.insert(address, object);
+                }
+                "edge" => {
+                    let fields = split(rest, 3, number)?;
+
+                    edges.push((parse_address(fields[0], number)?,
+                                parse_address(fields[1], number)?,
+                                fields[2].to_string(),
+                                number));
+                }
+                _ => {
+                    return Err(format!("Line {}: unknown record {:?}",
+                                       number,
+                                       kind));
+                }
+            }
+        }
+
+        for (from, to, label, number) in edges {
+            match snapshot.objects.get_mut(&from) {
+                Some(object) => object.edges.push((to, label)),
+                None => {
+                    return Err(format!("Line {}: undefined object {:#x}",
+                                       number,
+                                       from));
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Returns a human readable description of the object at the address.
+    pub fn describe(&self, address: usize) -> String {
+        if let Some(name) = self.names.get(&address) {
+            return format!("{:#x} ({})", address, name);
+        }
+
+        match self.objects.get(&address) {
+            Some(object) => {
+                format!("{:#x} ({}, {})",
+                        address,
+                        object.kind,
+                        self.prototype_name(object.prototype))
+            }
+            None => format!("{:#x}", address),
+        }
+    }
+
+    /// Returns the name of a prototype, or its address if it has no name.
+    pub fn prototype_name(&self, address: usize) -> String {
+        if address == 0 {
+            return "no prototype".to_string();
+        }
+
+        self.names
+            .get(&address)
+            .cloned()
+            .unwrap_or_else(|| format!("{:#x}", address))
+    }
+}
+
+/// Splits the fields of a record, the last field containing the remainder of
+/// the line.
+fn split(input: &str, amount: usize, line: usize) -> Result<Vec<&str>, String> {
+    let fields: Vec<&str> = input.splitn(amount, ' ').collect();
+
+    if fields.len() == amount {
+        Ok(fields)
+    } else {
+        Err(format!("Line {}: expected {} fields but got {}",
+                    line,
+                    amount,
+                    fields.len()))
+    }
+}
+
+fn parse_number(input: &str, line: usize) -> Result<usize, String> {
+    input.parse::<usize>()
+        .map_err(|_| format!("Line {}: {:?} is not a valid number", line, input))
+}
+
+/// Parses a hexadecimal address such as "0x1f".
+pub fn parse_address(input: &str, line: usize) -> Result<usize, String> {
+    let digits = if input.starts_with("0x") {
+        &input[2..]
+    } else {
+        input
+    };
+
+    usize::from_str_radix(digits, 16)
+        .map_err(|_| format!("Line {}: {:?} is not a valid address", line, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &'static str = "inko-heap-snapshot 1
+name 0x10 String
+process 1
+root 1 0x20 context 0 register 1
+object 0x20 1 40 array 0x0
+edge 0x20 0x30 index 0
+object 0x30 1 32 string 0x10
+edge 0x30 0x10 prototype
+object 0x10 permanent 64 none 0x0
+";
+
+    #[test]
+    fn test_parse() {
+        let snapshot = Snapshot::parse(SNAPSHOT).unwrap();
+
+        assert_eq!(snapshot.processes, vec![1]);
+        assert_eq!(snapshot.objects.len(), 3);
+        assert_eq!(snapshot.roots.len(), 1);
+        assert_eq!(snapshot.roots[0].address, 0x20);
+        assert_eq!(snapshot.roots[0].label, "context 0 register 1".to_string());
+        assert_eq!(snapshot.names.get(&0x10), Some(&"String".to_string()));
+
+        let array = snapshot.objects.get(&0x20).unwrap();
+
+        assert_eq!(array.owner, Some(1));
+        assert_eq!(array.size, 40);
+        assert_eq!(array.kind, "array".to_string());
+        assert_eq!(array.edges, vec![(0x30, "index 0".to_string())]);
+
+        assert!(snapshot.objects.get(&0x10).unwrap().owner.is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid_header() {
+        assert!(Snapshot::parse("hello").is_err());
+        assert!(Snapshot::parse("").is_err());
+    }
+
+    #[test]
+    fn test_parse_invalid_record() {
+        let input = format!("{}\nfoo 1 2", HEADER);
+
+        assert!(Snapshot::parse(&input).is_err());
+    }
+
+    #[test]
+    fn test_parse_missing_fields() {
+        let input = format!("{}\nobject 0x10 1 32", HEADER);
+
+        assert!(Snapshot::parse(&input).is_err());
+    }
+
+    #[test]
+    fn test_parse_edge_for_undefined_object() {
+        let input = format!("{}\nedge 0x10 0x20 prototype", HEADER);
+
+        assert!(Snapshot::parse(&input).is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        let snapshot = Snapshot::parse(SNAPSHOT).unwrap();
+
+        assert_eq!(snapshot.describe(0x10), "0x10 (String)".to_string());
+        assert_eq!(snapshot.describe(0x30), "0x30 (string, String)".to_string());
+        assert_eq!(snapshot.describe(0x20),
+                   "0x20 (array, no prototype)".to_string());
+
+        assert_eq!(snapshot.describe(0x40), "0x40".to_string());
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(parse_address("0x1f", 1), Ok(0x1f));
+        assert_eq!(parse_address("1f", 1), Ok(0x1f));
+        assert!(parse_address("0xzz", 1).is_err());
+    }
+}
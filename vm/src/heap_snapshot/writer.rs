@@ -0,0 +1,450 @@
+//! Writing heap snapshots of processes.
+//!
+//! Taking a snapshot of a process is only safe if the process isn't running,
+//! as the writer traverses the heap without any synchronisation. A process can
+//! take a snapshot of itself, other processes are only included if they are
+//! suspended. These processes are prevented from running until the snapshot
+//! has been written.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+
+use binding::RcBinding;
+use heap_snapshot::{HEADER, PERMANENT_OWNER};
+use object::Object;
+use object_header::ObjectHeader;
+use object_pointer::ObjectPointer;
+use object_value::ObjectValue;
+use process::RcProcess;
+use process_table::PID;
+use vm::state::State;
+
+pub struct Writer {
+    /// The snapshot written so far.
+    output: String,
+
+    /// The objects that have already been written.
+    visited: HashSet<ObjectPointer>,
+
+    /// The objects that still have to be written.
+    pending: Vec<ObjectPointer>,
+}
+
+impl Writer {
+    /// Returns a new writer, naming the permanent objects of the given state.
+    pub fn new(state: &State) -> Self {
+        let mut writer = Writer {
+            output: String::new(),
+            visited: HashSet::new(),
+            pending: Vec::new(),
+        };
+
+        writer.line(HEADER);
+
+        let names = [(state.top_level, "top level"),
+                     (state.integer_prototype, "Integer"),
+                     (state.float_prototype, "Float"),
+                     (state.string_prototype, "String"),
+                     (state.array_prototype, "Array"),
+                     (state.true_prototype, "True"),
+                     (state.false_prototype, "False"),
+                     (state.file_prototype, "File"),
+                     (state.method_prototype, "Method"),
+                     (state.compiled_code_prototype, "CompiledCode"),
+                     (state.binding_prototype, "Binding"),
+                     (state.channel_prototype, "Channel"),
+                     (state.weak_reference_prototype, "WeakReference"),
+                     (state.true_object, "true"),
+                     (state.false_object, "false")];
+
+        for &(pointer, name) in names.iter() {
+            let line = format!("name {} {}", address(pointer), name);
+
+            writer.line(&line);
+        }
+
+        writer
+    }
+
+    /// Writes all objects reachable from the roots of a process.
+    pub fn write_process(&mut self, process: &RcProcess) {
+        let pid = process.pid;
+        let local_data = process.local_data();
+
+        self.line(&format!("process {}", pid));
+
+        for (index, context) in process.contexts().iter().enumerate() {
+            for (register, value) in context.register.values.iter().enumerate() {
+                let label = format!("context {} register {}", index, register);
+
+                self.root(pid, *value, &label);
+            }
+
+            let mut edges = Vec::new();
+
+            binding_edges(&context.binding,
+                          &format!("context {} binding", index),
+                          &mut edges);
+
+            for (label, pointer) in edges {
+                self.root(pid, pointer, &label);
+            }
+        }
+
+        // Other processes may send messages while we're writing the mailbox.
+        let ref mailbox = local_data.mailbox;
+        let _lock = mailbox.write_lock.lock();
+
+        for (index, local) in mailbox.locals.iter().enumerate() {
+            self.root(pid, *local, &format!("mailbox local {}", index));
+        }
+
+        for (index, message) in mailbox.internal
+            .iter()
+            .chain(mailbox.external.iter())
+            .enumerate() {
+            self.root(pid, *message, &format!("mailbox message {}", index));
+        }
+
+        for (index, channel) in mailbox.live_channels().iter().enumerate() {
+            for pointer in channel.pointers() {
+                self.root(pid, *pointer.get(), &format!("channel {}", index));
+            }
+        }
+
+        self.write_pending(pid);
+    }
+
+    /// Returns the snapshot written so far.
+    pub fn finish(self) -> String {
+        self.output
+    }
+
+    fn root(&mut self, pid: PID, pointer: ObjectPointer, label: &str) {
+        if is_object(pointer) {
+            self.line(&format!("root {} {} {}", pid, address(pointer), label));
+            self.pending.push(pointer);
+        }
+    }
+
+    fn write_pending(&mut self, pid: PID) {
+        while let Some(pointer) = self.pending.pop() {
+            if self.visited.contains(&pointer) {
+                continue;
+            }
+
+            self.visited.insert(pointer);
+
+            let object = pointer.get();
+
+            let owner = if pointer.is_permanent() {
+                PERMANENT_OWNER.to_string()
+            } else {
+                pid.to_string()
+            };
+
+            let line = format!("object {} {} {} {} {}",
+                               address(pointer),
+                               owner,
+                               shallow_size(object),
+                               object.value.type_name(),
+                               address(object.prototype));
+
+            self.line(&line);
+
+            for (label, child) in object_edges(object) {
+                if is_object(child) {
+                    let line = format!("edge {} {} {}",
+                                       address(pointer),
+                                       address(child),
+                                       label);
+
+                    self.line(&line);
+                    self.pending.push(child);
+                }
+            }
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+}
+
+/// Writes a snapshot of the given processes to a file.
+///
+/// The processes must not be running while the snapshot is written.
+pub fn write_file(state: &State,
+                  processes: &[RcProcess],
+                  path: &str)
+                  -> io::Result<()> {
+    let mut writer = Writer::new(state);
+
+    for process in processes {
+        writer.write_process(process);
+    }
+
+    File::create(path)?.write_all(writer.finish().as_bytes())
+}
+
+/// Writes a snapshot of the given processes and all suspended processes.
+///
+/// The given processes must not be running, or must be the process taking the
+/// snapshot. Other processes that are running or being garbage collected are
+/// left out, as their heaps may change while the snapshot is written.
+pub fn write_file_with_suspended(state: &State,
+                                 mut processes: Vec<RcProcess>,
+                                 path: &str)
+                                 -> io::Result<()> {
+    let mut suspended = Vec::new();
+
+    {
+        let table = read_lock!(state.process_table);
+
+        for pid in table.pids() {
+            if processes.iter().any(|process| process.pid == pid) {
+                continue;
+            }
+
+            if let Some(process) = table.get(&pid) {
+                if let Some(status) = process.suspend_for_snapshot() {
+                    suspended.push((process, status));
+                }
+            }
+        }
+    }
+
+    for &(ref process, _) in suspended.iter() {
+        processes.push(process.clone());
+    }
+
+    let result = write_file(state, &processes, path);
+
+    for (process, status) in suspended {
+        process.resume_after_snapshot(status);
+    }
+
+    result
+}
+
+fn is_object(pointer: ObjectPointer) -> bool {
+    !pointer.is_null() && !pointer.is_tagged_integer()
+}
+
+fn address(pointer: ObjectPointer) -> String {
+    format!("{:#x}", pointer.raw.raw as usize)
+}
+
+/// Returns the number of bytes used by an object, excluding the objects it
+/// refers to.
+fn shallow_size(object: &Object) -> usize {
+    let mut size = mem::size_of::<Object>();
+
+    if let Some(header) = object.header() {
+        size += mem::size_of::<ObjectHeader>();
+
+        for map in [&header.attributes, &header.constants, &header.methods]
+            .iter() {
+            for (name, _) in map.iter() {
+                size += name.capacity() + mem::size_of::<ObjectPointer>();
+            }
+        }
+    }
+
+    match object.value {
+        ObjectValue::String(ref string) => size += string.capacity(),
+        ObjectValue::Array(ref array) => {
+            size += array.capacity() * mem::size_of::<ObjectPointer>();
+        }
+        _ => {}
+    }
+
+    size
+}
+
+/// Returns the objects an object refers to, along with a label describing
+/// each reference.
+fn object_edges(object: &Object) -> Vec<(String, ObjectPointer)> {
+    let mut edges = Vec::new();
+
+    if let Some(prototype) = object.prototype() {
+        edges.push(("prototype".to_string(), prototype));
+    }
+
+    if let Some(header) = object.header() {
+        for (name, value) in header.attributes.iter() {
+            edges.push((format!("attribute {}", name), *value));
+        }
+
+        for (name, value) in header.constants.iter() {
+            edges.push((format!("constant {}", name), *value));
+        }
+
+        for (name, value) in header.methods.iter() {
+            edges.push((format!("method {}", name), *value));
+        }
+
+        if let Some(scope) = header.outer_scope {
+            edges.push(("outer scope".to_string(), scope));
+        }
+    }
+
+    match object.value {
+        ObjectValue::Array(ref array) => {
+            for (index, value) in array.iter().enumerate() {
+                edges.push((format!("index {}", index), *value));
+            }
+        }
+        ObjectValue::Binding(ref binding) => {
+            binding_edges(binding, "binding", &mut edges);
+        }
+        _ => {}
+    }
+
+    edges
+}
+
+fn binding_edges(binding: &RcBinding,
+                 label: &str,
+                 edges: &mut Vec<(String, ObjectPointer)>) {
+    edges.push((format!("{} self", label), binding.self_object()));
+
+    for (index, local) in binding.locals().iter().enumerate() {
+        edges.push((format!("{} local {}", label, index), *local));
+    }
+
+    if let Some(parent) = binding.parent() {
+        binding_edges(&parent, &format!("{} parent", label), edges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+    use compiled_code::CompiledCode;
+    use object_value;
+    use process::Process;
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use vm::state::RcState;
+
+    fn setup() -> (RcState, RcProcess) {
+        let state = State::new(Config::new());
+        let process = new_process(&state, 1);
+
+        (state, process)
+    }
+
+    fn new_process(state: &RcState, pid: PID) -> RcProcess {
+        let code = CompiledCode::with_rc("a".to_string(),
+                                         "a".to_string(),
+                                         1,
+                                         Vec::new());
+
+        Process::from_code(pid,
+                           0,
+                           code,
+                           state.top_level,
+                           state.global_allocator.clone())
+    }
+
+    fn add_process(state: &RcState) -> RcProcess {
+        let mut table = write_lock!(state.process_table);
+        let pid = table.reserve().unwrap();
+        let process = new_process(state, pid);
+
+        table.map(pid, process.clone());
+
+        process
+    }
+
+    #[test]
+    fn test_write_process() {
+        let (state, process) = setup();
+        let string = process.allocate(object_value::string("a".to_string()),
+                                      state.string_prototype);
+
+        let array =
+            process.allocate_without_prototype(object_value::array(vec![string]));
+
+        process.set_register(0, array);
+        process.set_register(1, ObjectPointer::integer(5));
+
+        let mut writer = Writer::new(&state);
+
+        writer.write_process(&process);
+
+        let output = writer.finish();
+
+        assert!(output.starts_with(HEADER));
+        assert!(output.contains(&format!("name {} String",
+                                         address(state.string_prototype))));
+
+        assert!(output.contains(&format!("root 1 {} context 0 register 0",
+                                         address(array))));
+
+        assert!(output.contains(&format!("edge {} {} index 0",
+                                         address(array),
+                                         address(string))));
+
+        assert!(output.contains(&format!("object {} 1 ", address(string))));
+        assert_eq!(output.contains("register 1"), false);
+    }
+
+    #[test]
+    fn test_write_process_permanent_objects() {
+        let (state, process) = setup();
+        let mut writer = Writer::new(&state);
+
+        writer.write_process(&process);
+
+        let output = writer.finish();
+
+        assert!(output.contains(&format!("object {} permanent ",
+                                         address(state.top_level))));
+    }
+
+    #[test]
+    fn test_write_file_with_suspended() {
+        let (state, _) = setup();
+        let process = add_process(&state);
+        let suspended = add_process(&state);
+        let running = add_process(&state);
+        let path = env::temp_dir().join("inko-heap-snapshot-test.txt");
+
+        running.running();
+
+        write_file_with_suspended(&state,
+                                  vec![process.clone()],
+                                  path.to_str().unwrap())
+            .unwrap();
+
+        let mut output = String::new();
+
+        File::open(&path).unwrap().read_to_string(&mut output).unwrap();
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(output.matches(&format!("process {}\n", process.pid))
+                       .count(),
+                   1);
+
+        assert!(output.contains(&format!("process {}\n", suspended.pid)));
+        assert_eq!(output.contains(&format!("process {}\n", running.pid)),
+                   false);
+
+        assert!(suspended.available_for_execution());
+    }
+
+    #[test]
+    fn test_shallow_size() {
+        let object = Object::new(object_value::string("abc".to_string()));
+
+        assert!(shallow_size(&object) >= mem::size_of::<Object>() + 3);
+    }
+}
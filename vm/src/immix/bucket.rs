@@ -288,6 +288,33 @@ impl Bucket {
         evacuate
     }
 
+    /// Prepares this bucket for a compacting collection.
+    ///
+    /// All blocks are marked as fragmented, causing all live objects to be
+    /// evacuated into new blocks. Recyclable blocks are not re-used, as they
+    /// would be evacuated as well.
+    pub fn prepare_for_compaction(&mut self) {
+        self.blocks.extend(self.recyclable_blocks.drain(0..));
+
+        for block in self.blocks.iter_mut() {
+            block.prepare_for_collection();
+            block.set_fragmented();
+        }
+
+        self.available_histogram.reset();
+        self.mark_histogram.reset();
+
+        // Allocating into the current block would prevent it from being
+        // reclaimed, so we start allocating into a new block instead.
+        self.current_block = ptr::null::<Block>() as *mut Block;
+    }
+
+    /// Returns the number of blocks in this bucket, including recyclable
+    /// blocks.
+    pub fn number_of_blocks(&self) -> usize {
+        self.blocks.len() + self.recyclable_blocks.len()
+    }
+
     /// Returns a mutable iterator for all blocks.
     pub fn all_blocks_mut(&mut self)
                           -> Chain<IterMut<Box<Block>>, IterMut<Box<Block>>> {
@@ -397,6 +424,18 @@ mod tests {
         assert!(bucket.current_block == &mut *bucket.blocks[1] as *mut Block);
     }
 
+    #[test]
+    fn test_number_of_blocks() {
+        let mut bucket = Bucket::new();
+
+        assert_eq!(bucket.number_of_blocks(), 0);
+
+        bucket.add_block(Block::new());
+        bucket.recyclable_blocks.push(Block::new());
+
+        assert_eq!(bucket.number_of_blocks(), 2);
+    }
+
     #[test]
     fn test_find_available_hole_first_line_free() {
         let mut bucket = Bucket::new();
@@ -573,4 +612,24 @@ mod tests {
         assert!(block.fragmented);
         assert!(block.marked_objects_bitmap.is_empty());
     }
+
+    #[test]
+    fn test_prepare_for_compaction() {
+        let mut bucket = Bucket::new();
+
+        bucket.add_block(Block::new());
+        bucket.recyclable_blocks.push(Block::new());
+        bucket.mark_histogram.increment(1, 1);
+
+        bucket.prepare_for_compaction();
+
+        assert_eq!(bucket.blocks.len(), 2);
+        assert_eq!(bucket.recyclable_blocks.len(), 0);
+        assert!(bucket.mark_histogram.get(1).is_none());
+        assert!(bucket.current_block.is_null());
+
+        for block in bucket.blocks.iter() {
+            assert!(block.fragmented);
+        }
+    }
 }
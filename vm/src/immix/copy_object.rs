@@ -2,8 +2,32 @@
 //!
 //! The CopyObject trait can be implemented by allocators to support copying of
 //! objects into a heap.
+//!
+//! Data that can be shared between heaps is referenced instead of copied.
+//! Tagged integers and permanent objects are used as-is. The VM moves objects
+//! into the permanent space when they're used as a prototype, so prototypes
+//! and the methods defined on them are never copied. Strings share their
+//! contents using reference counting, as strings are immutable. An object that
+//! is referred to multiple times by a message is only copied once. This also
+//! means cycles are copied as cycles.
+//!
+//! Files and bindings refer to resources owned by a single process, and can't
+//! be copied.
+//!
+//! Weak references don't keep their targets alive, so they don't cause their
+//! targets to be copied either. A copied weak reference refers to the copy of
+//! its target if the target is copied as well (or shared), otherwise the copy
+//! is cleared.
+//!
+//! Copying the same object into many heaps (e.g. when sending a message to a
+//! process group) can be done using an ObjectGraph. The graph is traversed
+//! once when creating the ObjectGraph, after which it can be copied into every
+//! heap without having to traverse it again.
+
+use std::collections::{HashMap, HashSet};
 
 use object::Object;
+use object_header::ObjectHeader;
 use object_value;
 use object_value::ObjectValue;
 use object_pointer::ObjectPointer;
@@ -12,57 +36,471 @@ pub trait CopyObject: Sized {
     /// Allocates a copied object.
     fn allocate_copy(&mut self, Object) -> ObjectPointer;
 
-    /// Performs a deep copy of `object_ptr`
+    /// Registers a copied weak reference, allowing the garbage collector to
+    /// update it.
+    fn register_weak_reference(&mut self, _: ObjectPointer) {}
+
+    /// Performs a deep copy of `to_copy_ptr`.
     ///
-    /// The copy of the input object is allocated on the current heap.
-    fn copy_object(&mut self, to_copy_ptr: ObjectPointer) -> ObjectPointer {
-        if to_copy_ptr.is_permanent() {
+    /// The copy of the input object is allocated on the current heap. An error
+    /// is returned if any of the objects can't be copied, in which case
+    /// nothing is copied.
+    fn copy_object(&mut self,
+                   to_copy_ptr: ObjectPointer)
+                   -> Result<ObjectPointer, String> {
+        ensure_copyable(to_copy_ptr)?;
+
+        Ok(self.copy_copyable_object(to_copy_ptr))
+    }
+
+    /// Performs a deep copy of an object that is known to be copyable, such
+    /// as a message that has already been copied into a mailbox.
+    fn copy_copyable_object(&mut self,
+                            to_copy_ptr: ObjectPointer)
+                            -> ObjectPointer {
+        let mut copies = HashMap::new();
+        let copy = self.copy_with(to_copy_ptr, &mut copies);
+
+        // Weak references are copied as-is, so we have to point them to the
+        // copies of their targets once all objects have been copied.
+        for copy_ptr in copies.values() {
+            let object = copy_ptr.get_mut();
+
+            if let ObjectValue::WeakReference(ref mut target) = object.value {
+                if !target.is_null() && !is_shared(*target) {
+                    *target = copies.get(&*target)
+                        .cloned()
+                        .unwrap_or_else(ObjectPointer::null);
+                }
+            }
+        }
+
+        copy
+    }
+
+    /// Copies all objects of an object graph.
+    ///
+    /// The result is the same as copying the object the graph was created
+    /// from using `copy_object`.
+    fn copy_graph(&mut self, graph: &ObjectGraph) -> ObjectPointer {
+        // All copies are allocated first, so objects can refer to copies that
+        // come after them in the graph.
+        let copies = graph.objects
+            .iter()
+            .map(|_| self.allocate_copy(Object::new(object_value::none())))
+            .collect::<Vec<_>>();
+
+        for (object, copy_ptr) in graph.objects.iter().zip(copies.iter()) {
+            let value_copy = match object.value {
+                GraphValue::Plain(ref value) => copy_plain_value(value),
+                GraphValue::Array(ref slots) => {
+                    let values = slots.iter()
+                        .map(|slot| slot.resolve(&copies))
+                        .collect();
+
+                    object_value::array(values)
+                }
+                GraphValue::WeakReference(target) => {
+                    self.register_weak_reference(*copy_ptr);
+
+                    let target_copy = target.map(|slot| slot.resolve(&copies))
+                        .unwrap_or_else(ObjectPointer::null);
+
+                    object_value::weak_reference(target_copy)
+                }
+            };
+
+            let copy = copy_ptr.get_mut();
+
+            copy.value = value_copy;
+
+            if let Some(proto) = object.prototype {
+                copy.set_prototype(proto.resolve(&copies));
+            }
+
+            if let Some(ref header) = object.header {
+                copy.set_header(header.resolve(&copies));
+            }
+
+            if copy_ptr.is_finalizable() {
+                copy_ptr.mark_for_finalization();
+            }
+        }
+
+        graph.root.resolve(&copies)
+    }
+
+    /// Copies an object using a map of the objects copied so far, mapping the
+    /// original objects to their copies.
+    fn copy_with(&mut self,
+                 to_copy_ptr: ObjectPointer,
+                 copies: &mut HashMap<ObjectPointer, ObjectPointer>)
+                 -> ObjectPointer {
+        if is_shared(to_copy_ptr) {
             return to_copy_ptr;
         }
 
+        if let Some(copy_ptr) = copies.get(&to_copy_ptr) {
+            return *copy_ptr;
+        }
+
+        // The copy is registered before copying the objects it refers to, so
+        // objects referring back to it use the copy.
+        let copy_ptr = self.allocate_copy(Object::new(object_value::none()));
+
+        copies.insert(to_copy_ptr, copy_ptr);
+
         let to_copy = to_copy_ptr.get();
 
-        // Copy over the object value
         let value_copy = match to_copy.value {
             ObjectValue::None => object_value::none(),
             ObjectValue::Integer(num) => object_value::integer(num),
             ObjectValue::Float(num) => object_value::float(num),
             ObjectValue::String(ref string) => {
-                object_value::string(*string.clone())
+                ObjectValue::String(string.clone())
             }
             ObjectValue::Array(ref raw_vec) => {
-                let new_map = raw_vec.iter()
-                    .map(|val_ptr| self.copy_object(*val_ptr));
+                let values = raw_vec.iter()
+                    .map(|val_ptr| self.copy_with(*val_ptr, copies))
+                    .collect();
 
-                object_value::array(new_map.collect::<Vec<_>>())
-            }
-            ObjectValue::File(_) => {
-                panic!("ObjectValue::File can not be cloned");
+                object_value::array(values)
             }
             ObjectValue::Error(num) => object_value::error(num),
             ObjectValue::CompiledCode(ref code) => {
                 object_value::compiled_code(code.clone())
             }
+            ObjectValue::Channel(ref channel) => {
+                object_value::channel(channel.clone())
+            }
+            ObjectValue::WeakReference(target) => {
+                self.register_weak_reference(copy_ptr);
+
+                object_value::weak_reference(target)
+            }
+            ObjectValue::File(_) |
+            ObjectValue::Binding(_) => {
+                panic!("{} values can not be copied",
+                       to_copy.value.type_name());
+            }
+        };
+
+        let proto_copy = to_copy.prototype()
+            .map(|proto_ptr| self.copy_with(proto_ptr, copies));
+
+        let header_copy = to_copy.header()
+            .map(|header| header.copy_to(self, copies));
+
+        {
+            let copy = copy_ptr.get_mut();
+
+            copy.value = value_copy;
+
+            if let Some(proto_copy) = proto_copy {
+                copy.set_prototype(proto_copy);
+            }
+
+            if let Some(header_copy) = header_copy {
+                copy.set_header(header_copy);
+            }
+        }
+
+        // The copy was allocated without a value or header, so we have to
+        // check if it needs finalizing once these are set.
+        if copy_ptr.is_finalizable() {
+            copy_ptr.mark_for_finalization();
+        }
+
+        copy_ptr
+    }
+}
+
+/// Returns an error if any of the objects reachable from the pointer can't be
+/// copied.
+pub fn ensure_copyable(pointer: ObjectPointer) -> Result<(), String> {
+    let mut visited = HashSet::new();
+    let mut pending = vec![pointer];
+
+    while let Some(pointer) = pending.pop() {
+        if is_shared(pointer) || visited.contains(&pointer) {
+            continue;
+        }
+
+        visited.insert(pointer);
+
+        let object = pointer.get();
+
+        match object.value {
+            ObjectValue::File(_) |
+            ObjectValue::Binding(_) => {
+                return Err(format!("{} values can not be copied",
+                                   object.value.type_name()));
+            }
+            _ => {}
+        }
+
+        let mut pointers = Vec::new();
+
+        object.push_pointers(&mut pointers);
+
+        for pointer_pointer in pointers {
+            pending.push(*pointer_pointer.get());
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns true if the object can be used by multiple heaps without copying
+/// it.
+fn is_shared(pointer: ObjectPointer) -> bool {
+    pointer.is_tagged_integer() || pointer.is_permanent()
+}
+
+/// Copies a value that doesn't refer to any objects.
+fn copy_plain_value(value: &ObjectValue) -> ObjectValue {
+    match *value {
+        ObjectValue::None => object_value::none(),
+        ObjectValue::Integer(num) => object_value::integer(num),
+        ObjectValue::Float(num) => object_value::float(num),
+        ObjectValue::String(ref string) => ObjectValue::String(string.clone()),
+        ObjectValue::Error(num) => object_value::error(num),
+        ObjectValue::CompiledCode(ref code) => {
+            object_value::compiled_code(code.clone())
+        }
+        ObjectValue::Channel(ref channel) => {
+            object_value::channel(channel.clone())
+        }
+        _ => panic!("{} values can not be copied as is", value.type_name()),
+    }
+}
+
+/// A reference to an object in an ObjectGraph.
+#[derive(Clone, Copy)]
+enum Slot {
+    /// An object shared between heaps, which is used as-is.
+    Shared(ObjectPointer),
+
+    /// The index of an object to copy.
+    Copied(usize),
+}
+
+impl Slot {
+    /// Returns the object to use for this slot, given the copies of all
+    /// objects in the graph.
+    fn resolve(&self, copies: &[ObjectPointer]) -> ObjectPointer {
+        match *self {
+            Slot::Shared(pointer) => pointer,
+            Slot::Copied(index) => copies[index],
+        }
+    }
+}
+
+/// The value of an object in an ObjectGraph.
+enum GraphValue {
+    /// A value that doesn't refer to any objects.
+    Plain(ObjectValue),
+
+    /// An array of objects.
+    Array(Vec<Slot>),
+
+    /// A weak reference, which is cleared if its target is not part of the
+    /// graph.
+    WeakReference(Option<Slot>),
+}
+
+/// The header of an object in an ObjectGraph.
+struct GraphHeader {
+    attributes: Vec<(String, Slot)>,
+    constants: Vec<(String, Slot)>,
+    methods: Vec<(String, Slot)>,
+    outer_scope: Option<Slot>,
+}
+
+impl GraphHeader {
+    fn resolve(&self, copies: &[ObjectPointer]) -> ObjectHeader {
+        let mut header = ObjectHeader::new();
+
+        for &(ref key, slot) in self.attributes.iter() {
+            header.add_attribute(key.clone(), slot.resolve(copies));
+        }
+
+        for &(ref key, slot) in self.constants.iter() {
+            header.add_constant(key.clone(), slot.resolve(copies));
+        }
+
+        for &(ref key, slot) in self.methods.iter() {
+            header.add_method(key.clone(), slot.resolve(copies));
+        }
+
+        header.outer_scope = self.outer_scope.map(|slot| slot.resolve(copies));
+
+        header
+    }
+}
+
+/// An object in an ObjectGraph.
+struct GraphObject {
+    value: GraphValue,
+    prototype: Option<Slot>,
+    header: Option<GraphHeader>,
+}
+
+/// The objects reachable from an object, traversed once so they can be copied
+/// into many heaps.
+///
+/// The graph stores the values of the objects to copy, such as the contents of
+/// strings. As such it remains valid when the original objects are modified
+/// or garbage collected.
+pub struct ObjectGraph {
+    objects: Vec<GraphObject>,
+    root: Slot,
+}
+
+impl ObjectGraph {
+    /// Traverses the objects reachable from the given pointer.
+    ///
+    /// An error is returned if any of the objects can't be copied.
+    pub fn new(pointer: ObjectPointer) -> Result<ObjectGraph, String> {
+        let mut builder = GraphBuilder {
+            objects: Vec::new(),
+            indexes: HashMap::new(),
+            weak_references: Vec::new(),
+        };
+
+        let root = builder.add(pointer)?;
+
+        Ok(builder.finish(root))
+    }
+}
+
+/// A type for traversing objects into an ObjectGraph.
+struct GraphBuilder {
+    objects: Vec<GraphObject>,
+
+    /// The indexes of the objects added so far.
+    indexes: HashMap<ObjectPointer, usize>,
+
+    /// The weak references added so far, along with their targets.
+    weak_references: Vec<(usize, ObjectPointer)>,
+}
+
+impl GraphBuilder {
+    fn add(&mut self, pointer: ObjectPointer) -> Result<Slot, String> {
+        if is_shared(pointer) {
+            return Ok(Slot::Shared(pointer));
+        }
+
+        if let Some(index) = self.indexes.get(&pointer) {
+            return Ok(Slot::Copied(*index));
+        }
+
+        // Just like `copy_with` the object is registered before adding the
+        // objects it refers to, so cycles are preserved.
+        let index = self.objects.len();
+
+        self.objects.push(GraphObject {
+            value: GraphValue::Plain(object_value::none()),
+            prototype: None,
+            header: None,
+        });
+
+        self.indexes.insert(pointer, index);
+
+        let object = pointer.get();
+
+        let value = match object.value {
+            ObjectValue::Array(ref pointers) => {
+                let mut slots = Vec::with_capacity(pointers.len());
+
+                for pointer in pointers.iter() {
+                    slots.push(self.add(*pointer)?);
+                }
+
+                GraphValue::Array(slots)
+            }
+            ObjectValue::WeakReference(target) => {
+                self.weak_references.push((index, target));
+
+                GraphValue::WeakReference(None)
+            }
+            ObjectValue::File(_) |
             ObjectValue::Binding(_) => {
-                panic!("ObjectValue::Binding can not be cloned");
+                return Err(format!("{} values can not be copied",
+                                   object.value.type_name()));
             }
+            ref value => GraphValue::Plain(copy_plain_value(value)),
         };
 
-        let mut copy = if let Some(proto_ptr) = to_copy.prototype() {
-            let proto_copy = self.copy_object(proto_ptr);
+        let prototype = if let Some(proto) = object.prototype() {
+            Some(self.add(proto)?)
+        } else {
+            None
+        };
 
-            Object::with_prototype(value_copy, proto_copy)
+        let header = if let Some(header) = object.header() {
+            Some(self.add_header(header)?)
         } else {
-            Object::new(value_copy)
+            None
         };
 
-        if let Some(header) = to_copy.header() {
-            let header_copy = header.copy_to(self);
+        let ref mut graph_object = self.objects[index];
+
+        graph_object.value = value;
+        graph_object.prototype = prototype;
+        graph_object.header = header;
+
+        Ok(Slot::Copied(index))
+    }
+
+    fn add_header(&mut self,
+                  header: &ObjectHeader)
+                  -> Result<GraphHeader, String> {
+        let mut graph_header = GraphHeader {
+            attributes: Vec::with_capacity(header.attributes.len()),
+            constants: Vec::with_capacity(header.constants.len()),
+            methods: Vec::with_capacity(header.methods.len()),
+            outer_scope: None,
+        };
 
-            copy.set_header(header_copy);
+        for (key, value) in header.attributes.iter() {
+            graph_header.attributes.push((key.clone(), self.add(*value)?));
         }
 
-        self.allocate_copy(copy)
+        for (key, value) in header.constants.iter() {
+            graph_header.constants.push((key.clone(), self.add(*value)?));
+        }
+
+        for (key, value) in header.methods.iter() {
+            graph_header.methods.push((key.clone(), self.add(*value)?));
+        }
+
+        if let Some(scope) = header.outer_scope {
+            graph_header.outer_scope = Some(self.add(scope)?);
+        }
+
+        Ok(graph_header)
+    }
+
+    /// Returns the graph, pointing weak references to their targets if these
+    /// are part of the graph.
+    fn finish(mut self, root: Slot) -> ObjectGraph {
+        for &(index, target) in self.weak_references.iter() {
+            let slot = if target.is_null() {
+                None
+            } else if is_shared(target) {
+                Some(Slot::Shared(target))
+            } else {
+                self.indexes.get(&target).map(|index| Slot::Copied(*index))
+            };
+
+            self.objects[index].value = GraphValue::WeakReference(slot);
+        }
+
+        ObjectGraph {
+            objects: self.objects,
+            root: root,
+        }
     }
 }
 
@@ -72,10 +510,14 @@ mod tests {
     use immix::global_allocator::GlobalAllocator;
     use immix::local_allocator::LocalAllocator;
     use binding::Binding;
+    use channel::Channel;
     use compiled_code::CompiledCode;
     use object::Object;
     use object_pointer::ObjectPointer;
     use object_value;
+    use immix::permanent_allocator::PermanentAllocator;
+    use std::fs::File;
+    use std::sync::Arc;
 
     struct DummyAllocator {
         pub allocator: LocalAllocator,
@@ -99,7 +541,7 @@ mod tests {
     fn test_copy_none() {
         let mut dummy = DummyAllocator::new();
         let pointer = dummy.allocator.allocate_empty();
-        let copy = dummy.copy_object(pointer);
+        let copy = dummy.copy_object(pointer).unwrap();
 
         assert!(copy.get().value.is_none());
     }
@@ -112,7 +554,7 @@ mod tests {
 
         pointer.get_mut().set_prototype(proto);
 
-        let copy = dummy.copy_object(pointer);
+        let copy = dummy.copy_object(pointer).unwrap();
 
         assert!(copy.get().prototype().is_some());
     }
@@ -125,7 +567,7 @@ mod tests {
 
         ptr1.get_mut().add_attribute("a".to_string(), ptr2);
 
-        let copy = dummy.copy_object(ptr1);
+        let copy = dummy.copy_object(ptr1).unwrap();
 
         assert!(copy.get().header().is_some());
     }
@@ -136,19 +578,29 @@ mod tests {
         let pointer = dummy.allocator
             .allocate_without_prototype(object_value::integer(5));
 
-        let copy = dummy.copy_object(pointer);
+        let copy = dummy.copy_object(pointer).unwrap();
 
         assert!(copy.get().value.is_integer());
         assert_eq!(copy.get().value.as_integer().unwrap(), 5);
     }
 
+    #[test]
+    fn test_copy_tagged_integer() {
+        let mut dummy = DummyAllocator::new();
+        let pointer = ObjectPointer::integer(5);
+        let copy = dummy.copy_object(pointer).unwrap();
+
+        assert!(copy == pointer);
+        assert_eq!(copy.integer_value().unwrap(), 5);
+    }
+
     #[test]
     fn test_copy_float() {
         let mut dummy = DummyAllocator::new();
         let pointer = dummy.allocator
             .allocate_without_prototype(object_value::float(2.5));
 
-        let copy = dummy.copy_object(pointer);
+        let copy = dummy.copy_object(pointer).unwrap();
 
         assert!(copy.get().value.is_float());
         assert_eq!(copy.get().value.as_float().unwrap(), 2.5);
@@ -160,7 +612,7 @@ mod tests {
         let pointer = dummy.allocator
             .allocate_without_prototype(object_value::string("a".to_string()));
 
-        let copy = dummy.copy_object(pointer);
+        let copy = dummy.copy_object(pointer).unwrap();
 
         assert!(copy.get().value.is_string());
         assert_eq!(copy.get().value.as_string().unwrap(), &"a".to_string());
@@ -174,7 +626,7 @@ mod tests {
         let array = dummy.allocator
             .allocate_without_prototype(object_value::array(vec![ptr1, ptr2]));
 
-        let copy = dummy.copy_object(array);
+        let copy = dummy.copy_object(array).unwrap();
 
         assert!(copy.get().value.is_array());
         assert_eq!(copy.get().value.as_array().unwrap().len(), 2);
@@ -186,7 +638,7 @@ mod tests {
         let ptr = dummy.allocator
             .allocate_without_prototype(object_value::error(2));
 
-        let copy = dummy.copy_object(ptr);
+        let copy = dummy.copy_object(ptr).unwrap();
 
         assert!(copy.get().value.is_error());
     }
@@ -199,16 +651,32 @@ mod tests {
                                        1,
                                        Vec::new());
 
+        let value = object_value::compiled_code(cc.clone());
+        let ptr = dummy.allocator.allocate_without_prototype(value);
+
+        let copy = dummy.copy_object(ptr).unwrap();
+        let copied_code = copy.get().value.as_compiled_code().unwrap();
+
+        assert!(Arc::ptr_eq(&copied_code, &cc));
+    }
+
+    #[test]
+    fn test_copy_channel() {
+        let mut dummy = DummyAllocator::new();
+        let channel = Channel::new(1, None);
         let ptr = dummy.allocator
-            .allocate_without_prototype(object_value::compiled_code(cc));
+            .allocate_without_prototype(object_value::channel(channel.clone()));
 
-        let copy = dummy.copy_object(ptr);
+        let copy = dummy.copy_object(ptr).unwrap();
+        let copied_channel = copy.get().value.as_channel().unwrap();
 
-        assert!(copy.get().value.is_compiled_code());
+        // Both objects should refer to the same channel.
+        copied_channel.close();
+
+        assert!(channel.is_closed());
     }
 
     #[test]
-    #[should_panic]
     fn test_copy_binding() {
         let mut dummy = DummyAllocator::new();
         let self_obj = dummy.allocator.allocate_empty();
@@ -216,6 +684,236 @@ mod tests {
         let pointer = dummy.allocator
             .allocate_without_prototype(object_value::binding(binding));
 
-        dummy.copy_object(pointer);
+        assert!(dummy.copy_object(pointer).is_err());
+    }
+
+    #[test]
+    fn test_copy_nested_file() {
+        let mut dummy = DummyAllocator::new();
+        let file = File::open("/dev/null").unwrap();
+        let file_ptr = dummy.allocator
+            .allocate_without_prototype(object_value::file(file));
+
+        let array = dummy.allocator
+            .allocate_without_prototype(object_value::array(vec![file_ptr]));
+
+        assert!(dummy.copy_object(array).is_err());
+    }
+
+    #[test]
+    fn test_copy_shared_object_once() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+        let mut dummy = DummyAllocator::new();
+
+        // Prototypes used by the VM live in the permanent space, along with
+        // the methods defined on them.
+        let proto = perm_alloc.allocate_empty();
+        let method = perm_alloc.allocate_empty();
+
+        proto.get_mut().add_method("foo".to_string(), method);
+
+        let shared = dummy.allocator.allocate_empty();
+        let ptr1 = dummy.allocator.allocate_with_prototype(object_value::none(),
+                                                           proto);
+
+        let ptr2 = dummy.allocator.allocate_with_prototype(object_value::none(),
+                                                           proto);
+
+        ptr1.get_mut().add_attribute("shared".to_string(), shared);
+        ptr2.get_mut().add_attribute("shared".to_string(), shared);
+
+        let array = dummy.allocator
+            .allocate_without_prototype(object_value::array(vec![ptr1, ptr2]));
+
+        let copy = dummy.copy_object(array).unwrap();
+        let values = copy.get().value.as_array().unwrap();
+        let name = "shared".to_string();
+
+        let shared1 = values[0].get().lookup_attribute(&name).unwrap();
+        let shared2 = values[1].get().lookup_attribute(&name).unwrap();
+
+        assert!(values[0].get().prototype().unwrap() == proto);
+        assert!(values[1].get().prototype().unwrap() == proto);
+        assert!(values[0].get().lookup_method(&"foo".to_string()).unwrap() ==
+                method);
+
+        assert!(shared1 == shared2);
+        assert!(shared1 != shared);
+    }
+
+    #[test]
+    fn test_copy_cycle() {
+        let mut dummy = DummyAllocator::new();
+        let pointer = dummy.allocator.allocate_empty();
+
+        pointer.get_mut().add_attribute("self".to_string(), pointer);
+
+        let copy = dummy.copy_object(pointer).unwrap();
+        let attribute = copy.get().lookup_attribute(&"self".to_string());
+
+        assert!(copy != pointer);
+        assert!(attribute == Some(copy));
+    }
+
+    #[test]
+    fn test_copy_shares_string_contents() {
+        let mut dummy = DummyAllocator::new();
+        let pointer = dummy.allocator
+            .allocate_without_prototype(object_value::string("a".to_string()));
+
+        let copy = dummy.copy_object(pointer).unwrap();
+
+        let original = pointer.get().value.as_string().unwrap() as *const _;
+        let copied = copy.get().value.as_string().unwrap() as *const _;
+
+        assert_eq!(original, copied);
+    }
+
+    #[test]
+    fn test_copy_permanent_prototype() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+        let mut dummy = DummyAllocator::new();
+        let proto = perm_alloc.allocate_empty();
+        let pointer = dummy.allocator
+            .allocate_with_prototype(object_value::none(), proto);
+
+        let copy = dummy.copy_object(pointer).unwrap();
+
+        assert!(copy.get().prototype().unwrap() == proto);
+    }
+
+    #[test]
+    fn test_copy_weak_reference_with_copied_target() {
+        let mut dummy = DummyAllocator::new();
+        let target = dummy.allocator.allocate_empty();
+        let weak = dummy.allocator
+            .allocate_without_prototype(object_value::weak_reference(target));
+
+        let array = dummy.allocator
+            .allocate_without_prototype(object_value::array(vec![target, weak]));
+
+        let copy = dummy.copy_object(array).unwrap();
+        let values = copy.get().value.as_array().unwrap();
+        let weak_target = values[1].get().value.as_weak_reference().unwrap();
+
+        assert!(weak_target == values[0]);
+        assert!(weak_target != target);
+    }
+
+    #[test]
+    fn test_copy_weak_reference_without_copied_target() {
+        let mut dummy = DummyAllocator::new();
+        let target = dummy.allocator.allocate_empty();
+        let weak = dummy.allocator
+            .allocate_without_prototype(object_value::weak_reference(target));
+
+        let copy = dummy.copy_object(weak).unwrap();
+
+        assert!(copy.get().value.as_weak_reference().unwrap().is_null());
+    }
+
+    #[test]
+    fn test_copy_weak_reference_with_permanent_target() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+        let mut dummy = DummyAllocator::new();
+        let target = perm_alloc.allocate_empty();
+        let weak = dummy.allocator
+            .allocate_without_prototype(object_value::weak_reference(target));
+
+        let copy = dummy.copy_object(weak).unwrap();
+
+        assert!(copy.get().value.as_weak_reference().unwrap() == target);
+    }
+
+    #[test]
+    fn test_copy_graph() {
+        let mut dummy = DummyAllocator::new();
+        let proto = dummy.allocator.allocate_empty();
+        let string = dummy.allocator
+            .allocate_without_prototype(object_value::string("a".to_string()));
+
+        let pointer = dummy.allocator
+            .allocate_with_prototype(object_value::array(vec![string]), proto);
+
+        pointer.get_mut().add_attribute("@a".to_string(), string);
+
+        let graph = ObjectGraph::new(pointer).unwrap();
+        let copy1 = dummy.copy_graph(&graph);
+        let copy2 = dummy.copy_graph(&graph);
+
+        assert!(copy1 != pointer);
+        assert!(copy1 != copy2);
+
+        let values = copy1.get().value.as_array().unwrap();
+        let attribute = copy1.get().lookup_attribute(&"@a".to_string());
+
+        assert!(values[0] != string);
+        assert!(attribute == Some(values[0]));
+        assert!(values[0].get().value.as_string().unwrap() == "a");
+        assert!(copy1.get().prototype().unwrap() != proto);
+    }
+
+    #[test]
+    fn test_copy_graph_cycle() {
+        let mut dummy = DummyAllocator::new();
+        let pointer = dummy.allocator.allocate_empty();
+
+        pointer.get_mut().add_attribute("self".to_string(), pointer);
+
+        let graph = ObjectGraph::new(pointer).unwrap();
+        let copy = dummy.copy_graph(&graph);
+        let attribute = copy.get().lookup_attribute(&"self".to_string());
+
+        assert!(attribute == Some(copy));
+    }
+
+    #[test]
+    fn test_copy_graph_tagged_integer() {
+        let mut dummy = DummyAllocator::new();
+        let pointer = ObjectPointer::integer(5);
+        let graph = ObjectGraph::new(pointer).unwrap();
+
+        assert!(dummy.copy_graph(&graph) == pointer);
+    }
+
+    #[test]
+    fn test_copy_graph_weak_references() {
+        let mut dummy = DummyAllocator::new();
+        let target = dummy.allocator.allocate_empty();
+        let other = dummy.allocator.allocate_empty();
+        let weak1 = dummy.allocator
+            .allocate_without_prototype(object_value::weak_reference(target));
+
+        let weak2 = dummy.allocator
+            .allocate_without_prototype(object_value::weak_reference(other));
+
+        let array = dummy.allocator.allocate_without_prototype(
+            object_value::array(vec![weak1, weak2, target]));
+
+        let graph = ObjectGraph::new(array).unwrap();
+        let copy = dummy.copy_graph(&graph);
+        let values = copy.get().value.as_array().unwrap();
+
+        let target1 = values[0].get().value.as_weak_reference().unwrap();
+        let target2 = values[1].get().value.as_weak_reference().unwrap();
+
+        assert!(target1 == values[2]);
+        assert!(target2.is_null());
+    }
+
+    #[test]
+    fn test_object_graph_with_nested_file() {
+        let mut dummy = DummyAllocator::new();
+        let file = File::open("/dev/null").unwrap();
+        let file_ptr = dummy.allocator
+            .allocate_without_prototype(object_value::file(file));
+
+        let array = dummy.allocator
+            .allocate_without_prototype(object_value::array(vec![file_ptr]));
+
+        assert!(ObjectGraph::new(array).is_err());
     }
 }
@@ -3,7 +3,18 @@
 //! The global allocator is used by process-local allocators to request the
 //! allocation of new blocks or the re-using of existing (and returned) free
 //! blocks.
+//!
+//! Free blocks are retained so they can be re-used without having to allocate
+//! memory. To prevent a burst of allocations from permanently increasing the
+//! memory usage of the VM, free blocks beyond a configurable limit are
+//! released back to the operating system after every garbage collection.
+//!
+//! The number of blocks in use by all processes combined can be limited.
+//! Blocks are still handed out when this limit is exceeded, as allocations
+//! can't fail. Instead the allocator records that the limit was exceeded, and
+//! the processes requesting blocks are collected at their next safepoint.
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use immix::block::{Block, BLOCK_SIZE};
 
@@ -12,42 +23,174 @@ pub const PRE_ALLOCATE_BLOCKS: usize = (1 * 1024 * 1024) / BLOCK_SIZE;
 
 pub type RcGlobalAllocator = Arc<GlobalAllocator>;
 
+/// The number of blocks managed by the global allocator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockStatistics {
+    /// The number of free blocks retained for re-use.
+    pub held: usize,
+
+    /// The number of blocks in use by allocators.
+    pub in_use: usize,
+
+    /// The total number of blocks released to the operating system.
+    pub released: usize,
+}
+
 /// Structure used for storing the state of the global allocator.
 pub struct GlobalAllocator {
     pub blocks: Mutex<Vec<Box<Block>>>,
+
+    /// The maximum number of free blocks to retain. A value of 0 means all
+    /// free blocks are retained.
+    pub free_block_limit: usize,
+
+    /// The maximum number of blocks that can be in use at the same time. A
+    /// value of 0 means no limit is applied.
+    pub block_limit: usize,
+
+    /// Set to true if the number of blocks in use exceeded the block limit
+    /// the last time a block was requested or returned.
+    limit_exceeded: AtomicBool,
+
+    /// The total number of blocks allocated.
+    allocated: AtomicUsize,
+
+    /// The total number of blocks released to the operating system.
+    released: AtomicUsize,
 }
 
 impl GlobalAllocator {
     /// Creates a new GlobalAllocator with a number of blocks pre-allocated.
     pub fn new() -> RcGlobalAllocator {
+        GlobalAllocator::with_free_block_limit(0)
+    }
+
+    /// Creates a new GlobalAllocator with a number of blocks pre-allocated,
+    /// retaining at most `limit` free blocks.
+    pub fn with_free_block_limit(limit: usize) -> RcGlobalAllocator {
+        GlobalAllocator::with_limits(limit, 0)
+    }
+
+    /// Creates a new GlobalAllocator with a number of blocks pre-allocated,
+    /// retaining at most `free_block_limit` free blocks, and using at most
+    /// `block_limit` blocks.
+    pub fn with_limits(free_block_limit: usize,
+                       block_limit: usize)
+                       -> RcGlobalAllocator {
         let mut blocks = Vec::with_capacity(PRE_ALLOCATE_BLOCKS);
 
         for _ in 0..blocks.capacity() {
             blocks.push(Block::new());
         }
 
-        Arc::new(GlobalAllocator { blocks: Mutex::new(blocks) })
+        Arc::new(GlobalAllocator::with_blocks(blocks,
+                                              free_block_limit,
+                                              block_limit))
     }
 
     /// Creates a new global allocator without pre-allocating any blocks.
     pub fn without_preallocated_blocks() -> RcGlobalAllocator {
-        Arc::new(GlobalAllocator { blocks: Mutex::new(Vec::new()) })
+        Arc::new(GlobalAllocator::with_blocks(Vec::new(), 0, 0))
+    }
+
+    fn with_blocks(blocks: Vec<Box<Block>>,
+                   free_block_limit: usize,
+                   block_limit: usize)
+                   -> Self {
+        GlobalAllocator {
+            allocated: AtomicUsize::new(blocks.len()),
+            blocks: Mutex::new(blocks),
+            free_block_limit: free_block_limit,
+            block_limit: block_limit,
+            limit_exceeded: AtomicBool::new(false),
+            released: AtomicUsize::new(0),
+        }
     }
 
     /// Requests a new free block from the pool
+    ///
+    /// A block is returned even if the block limit is exceeded, use
+    /// `limit_exceeded` to check if this is the case.
     pub fn request_block(&self) -> Box<Block> {
         let mut blocks = lock!(self.blocks);
 
-        if blocks.len() > 0 {
-            blocks.pop().unwrap()
+        let block = if let Some(block) = blocks.pop() {
+            block
         } else {
+            self.allocated.fetch_add(1, Ordering::Relaxed);
+
             Block::new()
-        }
+        };
+
+        self.update_limit_exceeded(blocks.len());
+
+        block
     }
 
     /// Adds a block to the pool so it can be re-used.
     pub fn add_block(&self, block: Box<Block>) {
-        lock!(self.blocks).push(block);
+        let mut blocks = lock!(self.blocks);
+
+        blocks.push(block);
+
+        self.update_limit_exceeded(blocks.len());
+    }
+
+    /// Returns true if more blocks are in use than allowed by the block limit.
+    pub fn limit_exceeded(&self) -> bool {
+        self.limit_exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Releases the free blocks exceeding the free block limit to the
+    /// operating system, returning the number of released blocks.
+    pub fn release_blocks(&self) -> usize {
+        if self.free_block_limit == 0 {
+            return 0;
+        }
+
+        let mut blocks = lock!(self.blocks);
+
+        if blocks.len() <= self.free_block_limit {
+            return 0;
+        }
+
+        let amount = blocks.len() - self.free_block_limit;
+
+        // Dropping a block deallocates its memory.
+        blocks.truncate(self.free_block_limit);
+
+        self.released.fetch_add(amount, Ordering::Relaxed);
+
+        amount
+    }
+
+    /// Returns the number of blocks held, in use, and released.
+    pub fn statistics(&self) -> BlockStatistics {
+        let held = lock!(self.blocks).len();
+
+        BlockStatistics {
+            held: held,
+            in_use: self.blocks_in_use(held),
+            released: self.released.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the number of blocks in use, given the number of blocks held.
+    fn blocks_in_use(&self, held: usize) -> usize {
+        let allocated = self.allocated.load(Ordering::Relaxed);
+        let released = self.released.load(Ordering::Relaxed);
+
+        // Blocks allocated outside of the global allocator may be added to the
+        // pool, so we can't assume these numbers always add up.
+        allocated.saturating_sub(held + released)
+    }
+
+    fn update_limit_exceeded(&self, held: usize) {
+        if self.block_limit > 0 {
+            let exceeded = self.blocks_in_use(held) > self.block_limit;
+
+            self.limit_exceeded.store(exceeded, Ordering::Relaxed);
+        }
     }
 }
 
@@ -80,6 +223,24 @@ mod tests {
         assert_eq!(lock!(alloc.blocks).len(), 0);
     }
 
+    #[test]
+    fn test_request_block_with_block_limit() {
+        let alloc = GlobalAllocator::with_limits(0, 1);
+        let block1 = alloc.request_block();
+
+        assert_eq!(alloc.limit_exceeded(), false);
+
+        let block2 = alloc.request_block();
+
+        assert!(alloc.limit_exceeded());
+
+        alloc.add_block(block2);
+
+        assert_eq!(alloc.limit_exceeded(), false);
+
+        alloc.add_block(block1);
+    }
+
     #[test]
     fn test_add_block() {
         let alloc = GlobalAllocator::without_preallocated_blocks();
@@ -89,4 +250,46 @@ mod tests {
 
         assert_eq!(lock!(alloc.blocks).len(), 1);
     }
+
+    #[test]
+    fn test_release_blocks_without_limit() {
+        let alloc = GlobalAllocator::new();
+
+        assert_eq!(alloc.release_blocks(), 0);
+        assert_eq!(lock!(alloc.blocks).len(), PRE_ALLOCATE_BLOCKS);
+    }
+
+    #[test]
+    fn test_release_blocks_with_limit() {
+        let alloc = GlobalAllocator::with_free_block_limit(2);
+
+        assert_eq!(alloc.release_blocks(), PRE_ALLOCATE_BLOCKS - 2);
+        assert_eq!(lock!(alloc.blocks).len(), 2);
+        assert_eq!(alloc.release_blocks(), 0);
+    }
+
+    #[test]
+    fn test_statistics() {
+        let alloc = GlobalAllocator::with_free_block_limit(2);
+        let block1 = alloc.request_block();
+        let block2 = alloc.request_block();
+
+        alloc.release_blocks();
+
+        let stats = alloc.statistics();
+
+        assert_eq!(stats.held, 2);
+        assert_eq!(stats.in_use, 2);
+        assert_eq!(stats.released, PRE_ALLOCATE_BLOCKS - 4);
+
+        alloc.add_block(block1);
+        alloc.add_block(block2);
+        alloc.release_blocks();
+
+        let stats = alloc.statistics();
+
+        assert_eq!(stats.held, 2);
+        assert_eq!(stats.in_use, 0);
+        assert_eq!(stats.released, PRE_ALLOCATE_BLOCKS - 2);
+    }
 }
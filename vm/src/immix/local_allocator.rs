@@ -48,6 +48,24 @@ pub struct LocalAllocator {
     /// The number of blocks that need to be allocated for the mature generation
     /// before a garbage collection occurs.
     pub mature_block_allocation_threshold: usize,
+
+    /// The approximate number of bytes allocated outside of the young
+    /// generation's blocks (e.g. for the contents of strings) since the last
+    /// young collection.
+    pub young_off_heap_bytes: usize,
+
+    /// The approximate number of bytes allocated outside of the mature
+    /// generation's blocks since the last mature collection.
+    pub mature_off_heap_bytes: usize,
+
+    /// The weak references allocated on this heap, used by the garbage
+    /// collector to update or clear their targets.
+    pub weak_references: Vec<ObjectPointer>,
+
+    /// Set to true when a block is allocated while the global allocator
+    /// exceeds its block limit. This is reset by a full collection if the
+    /// limit is no longer exceeded afterwards.
+    pub global_limit_exceeded: bool,
 }
 
 impl LocalAllocator {
@@ -64,6 +82,10 @@ impl LocalAllocator {
             young_block_allocation_threshold: (1 * 1024 * 1024) / BLOCK_SIZE,
             mature_block_allocations: 0,
             mature_block_allocation_threshold: (1 * 1024 * 1024) / BLOCK_SIZE,
+            young_off_heap_bytes: 0,
+            mature_off_heap_bytes: 0,
+            weak_references: Vec::new(),
+            global_limit_exceeded: false,
         }
     }
 
@@ -107,6 +129,25 @@ impl LocalAllocator {
         move_objects
     }
 
+    /// Prepares for a collection that evacuates every object that can be
+    /// moved.
+    ///
+    /// Unlike a compacting collection young objects are not promoted, unless
+    /// their bucket is due for promotion.
+    pub fn prepare_for_full_evacuation(&mut self, mature: bool) {
+        for bucket in self.young_generation.iter_mut() {
+            bucket.prepare_for_compaction();
+        }
+
+        if mature {
+            self.mature_generation.prepare_for_compaction();
+        } else {
+            for mut block in self.mature_generation.all_blocks_mut() {
+                block.update_line_map();
+            }
+        }
+    }
+
     /// Returns unused blocks to the global allocator.
     pub fn reclaim_blocks(&mut self, mature: bool) {
         for bucket in self.young_generation.iter_mut() {
@@ -119,6 +160,11 @@ impl LocalAllocator {
             for block in self.mature_generation.reclaim_blocks() {
                 self.global_allocator.add_block(block);
             }
+
+            if self.global_limit_exceeded {
+                self.global_limit_exceeded =
+                    self.global_allocator.limit_exceeded();
+            }
         } else {
             for mut block in self.mature_generation.all_blocks_mut() {
                 block.update_line_map();
@@ -126,6 +172,36 @@ impl LocalAllocator {
         }
     }
 
+    /// Prepares for a compacting collection of the entire heap.
+    ///
+    /// All young objects are promoted to the mature generation, and all mature
+    /// objects are evacuated into new blocks.
+    pub fn prepare_for_compaction(&mut self) {
+        for bucket in self.young_generation.iter_mut() {
+            bucket.prepare_for_compaction();
+            bucket.promote = true;
+        }
+
+        self.mature_generation.prepare_for_compaction();
+    }
+
+    /// Returns the blocks emptied by a compacting collection to the global
+    /// allocator.
+    pub fn reclaim_compacted_blocks(&mut self) {
+        self.reclaim_blocks(true);
+
+        // The young buckets were only promoted for the compaction, so we need
+        // to restore the state they were in before.
+        for bucket in self.young_generation.iter_mut() {
+            bucket.promote = bucket.age == YOUNG_MAX_AGE;
+        }
+
+        self.young_block_allocations = 0;
+        self.mature_block_allocations = 0;
+        self.young_off_heap_bytes = 0;
+        self.mature_off_heap_bytes = 0;
+    }
+
     pub fn allocate_with_prototype(&mut self,
                                    value: ObjectValue,
                                    proto: ObjectPointer)
@@ -148,21 +224,40 @@ impl LocalAllocator {
         self.allocate_without_prototype(object_value::none())
     }
 
+    /// Allocates a weak reference to the given object.
+    pub fn allocate_weak_reference(&mut self,
+                                   target: ObjectPointer,
+                                   proto: ObjectPointer)
+                                   -> ObjectPointer {
+        let value = object_value::weak_reference(target);
+        let pointer = self.allocate_with_prototype(value, proto);
+
+        self.weak_references.push(pointer);
+
+        pointer
+    }
+
     pub fn allocate_eden(&mut self, object: Object) -> ObjectPointer {
+        self.young_off_heap_bytes += object.value.off_heap_size();
+
         let (new_block, pointer) = self.allocate_eden_raw(object);
 
         if new_block {
             self.young_block_allocations += 1;
+            self.check_global_limit();
         }
 
         pointer
     }
 
     pub fn allocate_mature(&mut self, object: Object) -> ObjectPointer {
+        self.mature_off_heap_bytes += object.value.off_heap_size();
+
         let (new_block, pointer) = self.allocate_mature_raw(object);
 
         if new_block {
             self.mature_block_allocations += 1;
+            self.check_global_limit();
         }
 
         pointer
@@ -186,14 +281,46 @@ impl LocalAllocator {
 
     /// Returns true if the number of allocated blocks for the young generation
     /// exceeds its threshold.
+    ///
+    /// Memory allocated outside of the heap is counted in blocks, so objects
+    /// such as large strings trigger collections like other objects do.
     pub fn young_block_allocation_threshold_exceeded(&self) -> bool {
-        self.young_block_allocations >= self.young_block_allocation_threshold
+        let blocks = self.young_block_allocations +
+                     self.young_off_heap_bytes / BLOCK_SIZE;
+
+        blocks >= self.young_block_allocation_threshold
     }
 
     /// Returns true if the number of allocated blocks for the mature generation
     /// exceeds its threshold.
     pub fn mature_block_allocation_threshold_exceeded(&self) -> bool {
-        self.mature_block_allocations >= self.mature_block_allocation_threshold
+        let blocks = self.mature_block_allocations +
+                     self.mature_off_heap_bytes / BLOCK_SIZE;
+
+        blocks >= self.mature_block_allocation_threshold
+    }
+
+    /// Returns the number of bytes allocated outside of the heap since the
+    /// last collection.
+    pub fn off_heap_bytes(&self, mature: bool) -> usize {
+        if mature {
+            self.young_off_heap_bytes + self.mature_off_heap_bytes
+        } else {
+            self.young_off_heap_bytes
+        }
+    }
+
+    /// Returns the number of blocks allocated for the young generation.
+    pub fn number_of_young_blocks(&self) -> usize {
+        self.young_generation
+            .iter()
+            .map(|bucket| bucket.number_of_blocks())
+            .sum()
+    }
+
+    /// Returns the number of blocks allocated for the mature generation.
+    pub fn number_of_mature_blocks(&self) -> usize {
+        self.mature_generation.number_of_blocks()
     }
 
     /// Increments the young generation allocation threshold.
@@ -212,6 +339,12 @@ impl LocalAllocator {
         self.mature_block_allocation_threshold = threshold as usize;
     }
 
+    fn check_global_limit(&mut self) {
+        if self.global_allocator.limit_exceeded() {
+            self.global_limit_exceeded = true;
+        }
+    }
+
     // Because Rust's borrow checker is sometimes dumb as a brick when it comes
     // to scoping mutable borrows we have to use two layers of indirection (a
     // function and a macro) to make the following allocation functions work.
@@ -233,18 +366,27 @@ impl CopyObject for LocalAllocator {
     fn allocate_copy(&mut self, object: Object) -> ObjectPointer {
         self.allocate_eden(object)
     }
+
+    fn register_weak_reference(&mut self, pointer: ObjectPointer) {
+        self.weak_references.push(pointer);
+    }
 }
 
 impl Drop for LocalAllocator {
     fn drop(&mut self) {
         for bucket in self.young_generation.iter_mut() {
-            for mut block in bucket.blocks.drain(0..) {
+            for mut block in bucket.blocks
+                .drain(0..)
+                .chain(bucket.recyclable_blocks.drain(0..)) {
                 block.reset();
                 self.global_allocator.add_block(block);
             }
         }
 
-        for mut block in self.mature_generation.blocks.drain(0..) {
+        for mut block in self.mature_generation
+            .blocks
+            .drain(0..)
+            .chain(self.mature_generation.recyclable_blocks.drain(0..)) {
             block.reset();
             self.global_allocator.add_block(block);
         }
@@ -254,6 +396,7 @@ impl Drop for LocalAllocator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use immix::block::BLOCK_SIZE;
     use immix::global_allocator::GlobalAllocator;
     use immix::copy_object::CopyObject;
     use object::Object;
@@ -324,6 +467,63 @@ mod tests {
         assert_eq!(alloc.mature_generation.blocks.len(), 0);
     }
 
+    #[test]
+    fn test_prepare_for_compaction() {
+        let mut alloc = local_allocator();
+
+        alloc.allocate_empty();
+        alloc.allocate_mature(Object::new(object_value::none()));
+        alloc.prepare_for_compaction();
+
+        for bucket in alloc.young_generation.iter() {
+            assert!(bucket.promote);
+        }
+
+        assert!(alloc.eden_space_mut().blocks[0].fragmented);
+        assert!(alloc.mature_generation.blocks[0].fragmented);
+        assert!(alloc.mature_generation.current_block.is_null());
+    }
+
+    #[test]
+    fn test_prepare_for_full_evacuation() {
+        let mut alloc = local_allocator();
+
+        alloc.allocate_empty();
+        alloc.allocate_mature(Object::new(object_value::none()));
+        alloc.prepare_for_full_evacuation(false);
+
+        assert!(alloc.eden_space_mut().blocks[0].fragmented);
+        assert_eq!(alloc.eden_space_mut().promote, false);
+        assert_eq!(alloc.mature_generation.blocks[0].fragmented, false);
+
+        alloc.prepare_for_full_evacuation(true);
+
+        assert!(alloc.mature_generation.blocks[0].fragmented);
+    }
+
+    #[test]
+    fn test_reclaim_compacted_blocks() {
+        let mut alloc = local_allocator();
+
+        alloc.allocate_empty();
+        alloc.allocate_mature(Object::new(object_value::none()));
+        alloc.young_off_heap_bytes = 1;
+        alloc.mature_off_heap_bytes = 1;
+        alloc.prepare_for_compaction();
+        alloc.reclaim_compacted_blocks();
+
+        for bucket in alloc.young_generation.iter() {
+            assert_eq!(bucket.promote, false);
+        }
+
+        assert_eq!(alloc.number_of_young_blocks(), 0);
+        assert_eq!(alloc.number_of_mature_blocks(), 0);
+        assert_eq!(alloc.young_block_allocations, 0);
+        assert_eq!(alloc.mature_block_allocations, 0);
+        assert_eq!(alloc.off_heap_bytes(true), 0);
+        assert_eq!(lock!(alloc.global_allocator.blocks).len(), 2);
+    }
+
     #[test]
     fn test_allocate_with_prototype() {
         let mut alloc = local_allocator();
@@ -353,6 +553,18 @@ mod tests {
         assert!(pointer.get().prototype().is_none());
     }
 
+    #[test]
+    fn test_allocate_weak_reference() {
+        let mut alloc = local_allocator();
+        let proto = alloc.allocate_empty();
+        let target = alloc.allocate_empty();
+        let pointer = alloc.allocate_weak_reference(target, proto);
+
+        assert!(pointer.get().prototype == proto);
+        assert!(pointer.get().value.as_weak_reference().unwrap() == target);
+        assert!(alloc.weak_references == vec![pointer]);
+    }
+
     #[test]
     fn test_allocate_eden() {
         let mut alloc = local_allocator();
@@ -381,6 +593,41 @@ mod tests {
         assert!(ptr2.is_mature());
     }
 
+    #[test]
+    fn test_global_limit_exceeded() {
+        let mut alloc = LocalAllocator::new(GlobalAllocator::with_limits(0, 1));
+
+        alloc.allocate_empty();
+
+        assert_eq!(alloc.global_limit_exceeded, false);
+
+        alloc.allocate_mature(Object::new(object_value::none()));
+
+        assert!(alloc.global_limit_exceeded);
+
+        alloc.reclaim_blocks(false);
+
+        assert!(alloc.global_limit_exceeded);
+
+        alloc.reclaim_blocks(true);
+
+        assert_eq!(alloc.global_limit_exceeded, false);
+    }
+
+    #[test]
+    fn test_number_of_blocks() {
+        let mut alloc = local_allocator();
+
+        assert_eq!(alloc.number_of_young_blocks(), 0);
+        assert_eq!(alloc.number_of_mature_blocks(), 0);
+
+        alloc.allocate_eden(Object::new(object_value::none()));
+        alloc.allocate_mature(Object::new(object_value::none()));
+
+        assert_eq!(alloc.number_of_young_blocks(), 1);
+        assert_eq!(alloc.number_of_mature_blocks(), 1);
+    }
+
     #[test]
     fn test_increment_young_ages() {
         let mut alloc = local_allocator();
@@ -462,6 +709,41 @@ mod tests {
         assert!(alloc.mature_block_allocation_threshold_exceeded());
     }
 
+    #[test]
+    fn test_block_allocation_threshold_exceeded_with_off_heap_bytes() {
+        let mut alloc = local_allocator();
+
+        alloc.young_block_allocation_threshold = 2;
+        alloc.mature_block_allocation_threshold = 2;
+        alloc.young_off_heap_bytes = BLOCK_SIZE;
+        alloc.mature_off_heap_bytes = BLOCK_SIZE;
+
+        assert_eq!(alloc.young_block_allocation_threshold_exceeded(), false);
+        assert_eq!(alloc.mature_block_allocation_threshold_exceeded(), false);
+
+        alloc.young_off_heap_bytes = BLOCK_SIZE * 2;
+        alloc.mature_off_heap_bytes = BLOCK_SIZE * 2;
+
+        assert!(alloc.young_block_allocation_threshold_exceeded());
+        assert!(alloc.mature_block_allocation_threshold_exceeded());
+    }
+
+    #[test]
+    fn test_off_heap_bytes() {
+        let mut alloc = local_allocator();
+
+        let young = object_value::string("abc".to_string());
+        let mature = Object::new(object_value::string("ab".to_string()));
+
+        alloc.allocate_without_prototype(young);
+        alloc.allocate_mature(mature);
+
+        assert_eq!(alloc.young_off_heap_bytes, 3);
+        assert_eq!(alloc.mature_off_heap_bytes, 2);
+        assert_eq!(alloc.off_heap_bytes(false), 3);
+        assert_eq!(alloc.off_heap_bytes(true), 5);
+    }
+
     #[test]
     fn test_increment_young_threshold() {
         let mut alloc = local_allocator();
@@ -488,12 +770,22 @@ mod tests {
     fn test_copy_object() {
         let mut alloc = local_allocator();
         let pointer = alloc.allocate_without_prototype(object_value::integer(5));
-        let copy = alloc.copy_object(pointer);
+        let copy = alloc.copy_object(pointer).unwrap();
 
         assert!(copy.is_young());
         assert!(copy.get().value.is_integer());
     }
 
+    #[test]
+    fn test_copy_object_with_weak_reference() {
+        let mut alloc = local_allocator();
+        let value = object_value::weak_reference(ObjectPointer::integer(5));
+        let pointer = alloc.allocate_without_prototype(value);
+        let copy = alloc.copy_object(pointer).unwrap();
+
+        assert!(alloc.weak_references.contains(&copy));
+    }
+
     #[test]
     fn test_drop() {
         let mut alloc = local_allocator();
@@ -509,4 +801,20 @@ mod tests {
 
         assert_eq!(lock!(global_alloc.blocks).len(), 2);
     }
+
+    #[test]
+    fn test_drop_with_recyclable_blocks() {
+        let mut alloc = local_allocator();
+        let global_alloc = alloc.global_allocator();
+
+        let block1 = global_alloc.request_block();
+        let block2 = global_alloc.request_block();
+
+        alloc.eden_space_mut().recyclable_blocks.push(block1);
+        alloc.mature_generation.recyclable_blocks.push(block2);
+
+        drop(alloc);
+
+        assert_eq!(lock!(global_alloc.blocks).len(), 2);
+    }
 }
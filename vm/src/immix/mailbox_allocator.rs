@@ -25,6 +25,14 @@ pub struct MailboxAllocator {
     /// The number of blocks that can be allocated before garbage collection
     /// is triggered.
     pub block_allocation_threshold: usize,
+
+    /// The approximate number of bytes allocated outside of the heap (e.g.
+    /// for the contents of arrays) since the last garbage collection cycle.
+    pub off_heap_bytes: usize,
+
+    /// The weak references copied into this heap, used by the garbage
+    /// collector to update or clear their targets.
+    pub weak_references: Vec<ObjectPointer>,
 }
 
 impl MailboxAllocator {
@@ -34,10 +42,14 @@ impl MailboxAllocator {
             bucket: Bucket::with_age(MAILBOX),
             block_allocations: 0,
             block_allocation_threshold: (1 * 1024 * 1024) / BLOCK_SIZE,
+            off_heap_bytes: 0,
+            weak_references: Vec::new(),
         }
     }
 
     pub fn allocate(&mut self, object: Object) -> ObjectPointer {
+        self.off_heap_bytes += object.value.off_heap_size();
+
         let (new_block, pointer) = self.bucket
             .allocate(&self.global_allocator, object);
 
@@ -54,6 +66,11 @@ impl MailboxAllocator {
         self.bucket.prepare_for_collection()
     }
 
+    /// Prepares a garbage collection cycle that evacuates every object.
+    pub fn prepare_for_full_evacuation(&mut self) {
+        self.bucket.prepare_for_compaction();
+    }
+
     /// Returns unused blocks to the global allocator.
     pub fn reclaim_blocks(&mut self) {
         for block in self.bucket.reclaim_blocks() {
@@ -61,8 +78,17 @@ impl MailboxAllocator {
         }
     }
 
+    /// Returns the number of blocks allocated by this allocator.
+    pub fn number_of_blocks(&self) -> usize {
+        self.bucket.number_of_blocks()
+    }
+
+    /// Returns true if the number of allocated blocks exceeds the threshold,
+    /// counting memory allocated outside of the heap in blocks.
     pub fn allocation_threshold_exceeded(&self) -> bool {
-        self.block_allocations >= self.block_allocation_threshold
+        let blocks = self.block_allocations + self.off_heap_bytes / BLOCK_SIZE;
+
+        blocks >= self.block_allocation_threshold
     }
 
     /// Increments the allocation threshold by the given factor.
@@ -77,11 +103,18 @@ impl CopyObject for MailboxAllocator {
     fn allocate_copy(&mut self, object: Object) -> ObjectPointer {
         self.allocate(object)
     }
+
+    fn register_weak_reference(&mut self, pointer: ObjectPointer) {
+        self.weak_references.push(pointer);
+    }
 }
 
 impl Drop for MailboxAllocator {
     fn drop(&mut self) {
-        for mut block in self.bucket.blocks.drain(0..) {
+        for mut block in self.bucket
+            .blocks
+            .drain(0..)
+            .chain(self.bucket.recyclable_blocks.drain(0..)) {
             block.reset();
             self.global_allocator.add_block(block);
         }
@@ -91,6 +124,7 @@ impl Drop for MailboxAllocator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use immix::block::BLOCK_SIZE;
     use immix::global_allocator::GlobalAllocator;
     use immix::local_allocator::LocalAllocator;
     use immix::copy_object::CopyObject;
@@ -121,12 +155,26 @@ mod tests {
         let original =
             local_alloc.allocate_without_prototype(object_value::integer(5));
 
-        let copy = mbox_alloc.copy_object(original);
+        let copy = mbox_alloc.copy_object(original).unwrap();
 
         assert!(copy.is_mailbox());
         assert!(copy.get().value.is_integer());
     }
 
+    #[test]
+    fn test_copy_object_with_weak_reference() {
+        let mut mbox_alloc = mailbox_allocator();
+        let global_alloc = mbox_alloc.global_allocator.clone();
+        let mut local_alloc = LocalAllocator::new(global_alloc);
+        let target = local_alloc.allocate_empty();
+        let proto = local_alloc.allocate_empty();
+
+        let original = local_alloc.allocate_weak_reference(target, proto);
+        let copy = mbox_alloc.copy_object(original).unwrap();
+
+        assert!(mbox_alloc.weak_references == vec![copy]);
+    }
+
     #[test]
     fn test_drop() {
         let mut alloc = mailbox_allocator();
@@ -139,6 +187,19 @@ mod tests {
         assert_eq!(lock!(global_alloc.blocks).len(), 1);
     }
 
+    #[test]
+    fn test_drop_with_recyclable_blocks() {
+        let mut alloc = mailbox_allocator();
+        let global_alloc = alloc.global_allocator.clone();
+        let block = global_alloc.request_block();
+
+        alloc.bucket.recyclable_blocks.push(block);
+
+        drop(alloc);
+
+        assert_eq!(lock!(global_alloc.blocks).len(), 1);
+    }
+
     #[test]
     fn test_allocation_threshold_exceeded() {
         let mut alloc = mailbox_allocator();
@@ -152,6 +213,20 @@ mod tests {
         assert!(alloc.allocation_threshold_exceeded());
     }
 
+    #[test]
+    fn test_allocation_threshold_exceeded_with_off_heap_bytes() {
+        let mut alloc = mailbox_allocator();
+
+        alloc.block_allocation_threshold = 1;
+
+        let value = object_value::string(String::with_capacity(BLOCK_SIZE));
+
+        alloc.allocate(Object::new(value));
+
+        assert_eq!(alloc.off_heap_bytes, BLOCK_SIZE);
+        assert!(alloc.allocation_threshold_exceeded());
+    }
+
     #[test]
     fn test_increment_threshold() {
         let mut alloc = mailbox_allocator();
@@ -12,17 +12,26 @@ pub mod tagged_pointer;
 pub mod binding;
 pub mod bytecode_parser;
 pub mod call_frame;
+pub mod channel;
 pub mod compiled_code;
 pub mod config;
+pub mod deadlock;
+pub mod deterministic;
+pub mod distribution;
 pub mod errors;
+pub mod heap_limits;
+pub mod heap_snapshot;
 pub mod object;
 pub mod object_header;
 pub mod object_pointer;
 pub mod object_value;
 pub mod immix;
 pub mod register;
+pub mod serializer;
 pub mod mailbox;
 pub mod process;
+pub mod process_groups;
+pub mod process_registry;
 pub mod process_table;
 pub mod pool;
 pub mod pools;
@@ -11,9 +11,7 @@ macro_rules! num_op {
         let receiver_ptr = $process.get_register($ins.arg(1)?)?;
         let arg_ptr = $process.get_register($ins.arg(2)?)?;
 
-        let receiver = receiver_ptr.get();
-        let arg = arg_ptr.get();
-        let result = to_expr!(receiver.value.$as_name()? $op arg.value.$as_name()?);
+        let result = to_expr!(receiver_ptr.$as_name()? $op arg_ptr.$as_name()?);
 
         let obj = $process
             .allocate(object_value::$tname(result), $vm.state.$proto.clone());
@@ -30,9 +28,7 @@ macro_rules! num_bool_op {
         let receiver_ptr = $process.get_register($ins.arg(1)?)?;
         let arg_ptr = $process.get_register($ins.arg(2)?)?;
 
-        let receiver = receiver_ptr.get();
-        let arg = arg_ptr.get();
-        let result = to_expr!(receiver.value.$as_name()? $op arg.value.$as_name()?);
+        let result = to_expr!(receiver_ptr.$as_name()? $op arg_ptr.$as_name()?);
 
         let boolean = if result {
             $vm.state.true_object.clone()
@@ -49,24 +45,36 @@ macro_rules! num_bool_op {
 
 macro_rules! integer_op {
     ($vm: expr, $process: expr, $ins: expr, $op: tt) => ({
-        num_op!($vm, $process, $ins, $op, integer, as_integer, integer_prototype)
+        let register = $ins.arg(0)?;
+        let receiver_ptr = $process.get_register($ins.arg(1)?)?;
+        let arg_ptr = $process.get_register($ins.arg(2)?)?;
+
+        let result = to_expr!(receiver_ptr.integer_value()? $op
+                              arg_ptr.integer_value()?);
+
+        let obj = $process
+            .allocate_integer(result, $vm.state.integer_prototype.clone());
+
+        $process.set_register(register, obj);
+
+        Ok(Action::None)
     });
 }
 
 macro_rules! integer_bool_op {
     ($vm: expr, $process: expr, $ins: expr, $op: tt) => ({
-        num_bool_op!($vm, $process, $ins, $op, as_integer)
+        num_bool_op!($vm, $process, $ins, $op, integer_value)
     });
 }
 
 macro_rules! float_op {
     ($vm: expr, $process: expr, $ins: expr, $op: tt) => ({
-        num_op!($vm, $process, $ins, $op, float, as_float, float_prototype)
+        num_op!($vm, $process, $ins, $op, float, float_value, float_prototype)
     });
 }
 
 macro_rules! float_bool_op {
     ($vm: expr, $process: expr, $ins: expr, $op: tt) => ({
-        num_bool_op!($vm, $process, $ins, $op, as_float)
+        num_bool_op!($vm, $process, $ins, $op, float_value)
     });
 }
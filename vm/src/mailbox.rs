@@ -1,10 +1,42 @@
 use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use parking_lot::Mutex;
 
-use immix::copy_object::CopyObject;
+use channel::{Channel, RcChannel, SendResult};
+
+use immix::copy_object::{CopyObject, ObjectGraph};
 use immix::global_allocator::RcGlobalAllocator;
 use immix::mailbox_allocator::MailboxAllocator;
 use object_pointer::{ObjectPointer, ObjectPointerPointer};
+use serializer;
+
+/// What to do when sending a message to a mailbox that is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The sender is suspended until there is room in the mailbox.
+    Suspend,
+
+    /// The sender receives an error instead.
+    Error,
+
+    /// The message is dropped.
+    Drop,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "suspend" => Ok(OverflowPolicy::Suspend),
+            "error" => Ok(OverflowPolicy::Error),
+            "drop" => Ok(OverflowPolicy::Drop),
+            _ => Err(format!("The mailbox policy {:?} is invalid", name)),
+        }
+    }
+}
 
 pub struct Mailbox {
     pub external: VecDeque<ObjectPointer>,
@@ -12,6 +44,36 @@ pub struct Mailbox {
     pub locals: VecDeque<ObjectPointer>,
     pub allocator: MailboxAllocator,
     pub write_lock: Mutex<()>,
+
+    /// The channels owned by the process of this mailbox. Messages sent to
+    /// these channels are allocated using the mailbox allocator.
+    pub channels: Vec<Weak<Channel>>,
+
+    /// The maximum number of messages this mailbox can hold, if any.
+    ///
+    /// Messages a process sends to itself are not subject to this limit.
+    pub capacity: Option<usize>,
+
+    /// The number of messages dropped because the mailbox was full.
+    pub dropped: AtomicUsize,
+
+    /// The number of times a sender was suspended because the mailbox was
+    /// full.
+    pub blocked: AtomicUsize,
+
+    /// The number of messages stored in the mailbox.
+    ///
+    /// This counter allows other threads to obtain the size of the mailbox,
+    /// without having to access the queues used by the receiver.
+    pub size: AtomicUsize,
+
+    /// The number of messages sent by other processes that have yet to be
+    /// received.
+    ///
+    /// This counter is used to enforce the capacity of the mailbox, as the
+    /// queue of internal messages can only be accessed by the receiver.
+    /// Senders increment it while holding the write lock.
+    pub external_size: AtomicUsize,
 }
 
 impl Mailbox {
@@ -22,31 +84,162 @@ impl Mailbox {
             locals: VecDeque::new(),
             allocator: MailboxAllocator::new(global_allocator),
             write_lock: Mutex::new(()),
+            channels: Vec::new(),
+            capacity: None,
+            dropped: AtomicUsize::new(0),
+            blocked: AtomicUsize::new(0),
+            size: AtomicUsize::new(0),
+            external_size: AtomicUsize::new(0),
         }
     }
 
-    pub fn send_from_external(&mut self, original: ObjectPointer) {
+    /// Sends a message from another process.
+    ///
+    /// Returns false if the mailbox is full, in which case the message is not
+    /// copied. The message must be copyable, which can be checked using
+    /// `copy_object::ensure_copyable`.
+    pub fn send_from_external(&mut self, original: ObjectPointer) -> bool {
         let _lock = self.write_lock.lock();
 
-        self.external.push_back(self.allocator.copy_object(original));
+        if self.is_full_with_lock() {
+            return false;
+        }
+
+        self.external.push_back(self.allocator.copy_copyable_object(original));
+        self.record_external_message();
+
+        true
+    }
+
+    /// Sends a message from another process, using an object graph of the
+    /// message.
+    ///
+    /// Just like `send_from_external` false is returned if the mailbox is
+    /// full, in which case the message is not copied.
+    pub fn send_graph(&mut self, graph: &ObjectGraph) -> bool {
+        let _lock = self.write_lock.lock();
+
+        if self.is_full_with_lock() {
+            return false;
+        }
+
+        self.external.push_back(self.allocator.copy_graph(graph));
+        self.record_external_message();
+
+        true
+    }
+
+    /// Sends a message serialized by another VM instance.
+    ///
+    /// The message is deserialized directly into the mailbox, resulting in the
+    /// same state as a message sent using `send_from_external`. Just like
+    /// `send_from_external` false is returned if the mailbox is full.
+    pub fn send_serialized(&mut self,
+                           bytes: &[u8],
+                           permanent: &[ObjectPointer])
+                           -> Result<bool, String> {
+        let _lock = self.write_lock.lock();
+
+        if self.is_full_with_lock() {
+            return Ok(false);
+        }
+
+        let pointer =
+            serializer::deserialize(bytes, permanent, &mut self.allocator)?;
+
+        self.external.push_back(pointer);
+        self.record_external_message();
+
+        Ok(true)
+    }
+
+    /// Sends a message to a channel owned by the process of this mailbox.
+    ///
+    /// Just like `send_from_external` the message must be copyable.
+    pub fn send_to_channel(&mut self,
+                           channel: &Channel,
+                           original: ObjectPointer)
+                           -> SendResult {
+        let _lock = self.write_lock.lock();
+        let allocator = &mut self.allocator;
+
+        channel.push_with(|| allocator.copy_copyable_object(original))
+    }
+
+    /// Adds a channel owned by the process of this mailbox.
+    pub fn add_channel(&mut self, channel: &RcChannel) {
+        // Channels that are no longer in use can be removed, as their messages
+        // will never be received.
+        self.channels.retain(|weak| weak.upgrade().is_some());
+        self.channels.push(Arc::downgrade(channel));
     }
 
     pub fn send_from_self(&mut self, pointer: ObjectPointer) {
         self.locals.push_back(pointer);
+        self.size.fetch_add(1, Ordering::Release);
     }
 
     pub fn receive(&mut self) -> Option<ObjectPointer> {
-        if let Some(pointer) = self.locals.pop_front() {
-            return Some(pointer);
-        }
+        let mut message = self.locals.pop_front();
+
+        if message.is_none() {
+            if self.internal.len() == 0 {
+                let _lock = self.write_lock.lock();
+
+                self.internal.append(&mut self.external.drain(0..).collect());
+            }
 
-        if self.internal.len() == 0 {
-            let _lock = self.write_lock.lock();
+            message = self.internal.pop_front();
+
+            if message.is_some() {
+                self.external_size.fetch_sub(1, Ordering::Release);
+            }
+        }
 
-            self.internal.append(&mut self.external.drain(0..).collect());
+        if message.is_some() {
+            self.size.fetch_sub(1, Ordering::Release);
         }
 
-        self.internal.pop_front()
+        message
+    }
+
+    /// Returns the number of messages stored in the mailbox.
+    ///
+    /// This method can be used safely by any thread.
+    pub fn len(&self) -> usize {
+        self.size.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of blocks allocated for the mailbox.
+    pub fn number_of_blocks(&self) -> usize {
+        let _lock = self.write_lock.lock();
+
+        self.allocator.number_of_blocks()
+    }
+
+    /// Returns true if no more messages can be sent to this mailbox.
+    pub fn is_full(&self) -> bool {
+        let _lock = self.write_lock.lock();
+
+        self.is_full_with_lock()
+    }
+
+    /// Records a message that was dropped because the mailbox was full.
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a sender being suspended because the mailbox was full.
+    pub fn record_blocked(&self) {
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn blocked(&self) -> usize {
+        self.blocked.load(Ordering::Relaxed)
     }
 
     pub fn has_local_pointers(&self) -> bool {
@@ -61,6 +254,11 @@ impl Mailbox {
             .collect()
     }
 
+    /// Returns the channels owned by the process that are still in use.
+    pub fn live_channels(&self) -> Vec<RcChannel> {
+        self.channels.iter().filter_map(|weak| weak.upgrade()).collect()
+    }
+
     pub fn local_pointers(&self) -> Vec<ObjectPointerPointer> {
         self.locals.iter().map(|pointer| pointer.pointer()).collect()
     }
@@ -68,4 +266,81 @@ impl Mailbox {
     pub fn should_collect(&self) -> bool {
         self.allocator.allocation_threshold_exceeded()
     }
+
+    /// Returns true if the mailbox is full.
+    ///
+    /// The caller must hold on to the write lock. Only messages sent by other
+    /// processes count towards the capacity.
+    fn is_full_with_lock(&self) -> bool {
+        if let Some(capacity) = self.capacity {
+            self.external_size.load(Ordering::Acquire) >= capacity
+        } else {
+            false
+        }
+    }
+
+    /// Records a message sent by another process.
+    ///
+    /// The caller must hold on to the write lock.
+    fn record_external_message(&self) {
+        self.size.fetch_add(1, Ordering::Release);
+        self.external_size.fetch_add(1, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use immix::global_allocator::GlobalAllocator;
+
+    #[test]
+    fn test_overflow_policy_from_str() {
+        assert_eq!(OverflowPolicy::from_str("suspend"),
+                   Ok(OverflowPolicy::Suspend));
+
+        assert_eq!(OverflowPolicy::from_str("error"), Ok(OverflowPolicy::Error));
+        assert_eq!(OverflowPolicy::from_str("drop"), Ok(OverflowPolicy::Drop));
+        assert!(OverflowPolicy::from_str("foo").is_err());
+    }
+
+    #[test]
+    fn test_len() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut mailbox = Mailbox::new(global_alloc);
+
+        mailbox.send_from_self(ObjectPointer::integer(1));
+        mailbox.send_from_external(ObjectPointer::integer(2));
+
+        assert_eq!(mailbox.len(), 2);
+
+        mailbox.receive();
+        mailbox.receive();
+
+        assert_eq!(mailbox.len(), 0);
+        assert!(mailbox.receive().is_none());
+        assert_eq!(mailbox.len(), 0);
+    }
+
+    #[test]
+    fn test_is_full_ignores_local_messages() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut mailbox = Mailbox::new(global_alloc);
+
+        mailbox.capacity = Some(1);
+        mailbox.send_from_self(ObjectPointer::integer(1));
+
+        assert_eq!(mailbox.is_full(), false);
+        assert!(mailbox.send_from_external(ObjectPointer::integer(2)));
+        assert!(mailbox.is_full());
+        assert_eq!(mailbox.send_from_external(ObjectPointer::integer(3)),
+                   false);
+
+        mailbox.receive();
+
+        assert!(mailbox.is_full());
+
+        mailbox.receive();
+
+        assert_eq!(mailbox.is_full(), false);
+    }
 }
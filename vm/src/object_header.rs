@@ -51,29 +51,36 @@ impl ObjectHeader {
     }
 
     /// Copies all pointers in this header to the given allocator.
-    pub fn copy_to<T: CopyObject>(&self, allocator: &mut T) -> ObjectHeader {
+    ///
+    /// The map of copied objects is used to copy every object only once, as
+    /// done by `CopyObject::copy_with`.
+    pub fn copy_to<T: CopyObject>(&self,
+                                  allocator: &mut T,
+                                  copies: &mut HashMap<ObjectPointer,
+                                                       ObjectPointer>)
+                                  -> ObjectHeader {
         let mut copy = ObjectHeader::new();
 
         for (key, value) in self.attributes.iter() {
-            let value_copy = allocator.copy_object(value.clone());
+            let value_copy = allocator.copy_with(*value, copies);
 
             copy.add_attribute(key.clone(), value_copy);
         }
 
         for (key, value) in self.constants.iter() {
-            let value_copy = allocator.copy_object(value.clone());
+            let value_copy = allocator.copy_with(*value, copies);
 
             copy.add_constant(key.clone(), value_copy);
         }
 
         for (key, value) in self.methods.iter() {
-            let value_copy = allocator.copy_object(value.clone());
+            let value_copy = allocator.copy_with(*value, copies);
 
             copy.add_method(key.clone(), value_copy);
         }
 
-        if let Some(scope) = self.outer_scope.as_ref() {
-            let outer_copy = allocator.copy_object(scope.clone());
+        if let Some(scope) = self.outer_scope {
+            let outer_copy = allocator.copy_with(scope, copies);
 
             copy.outer_scope = Some(outer_copy);
         }
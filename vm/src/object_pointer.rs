@@ -1,3 +1,5 @@
+use std::fs;
+use std::i64;
 use std::mem::transmute;
 use std::hash::{Hash, Hasher};
 
@@ -6,9 +8,15 @@ use immix::block;
 use immix::bucket::{MATURE, MAILBOX, PERMANENT};
 use immix::local_allocator::YOUNG_MAX_AGE;
 
+use binding::RcBinding;
+use channel::RcChannel;
+use compiled_code::RcCompiledCode;
+
 use object::{Object, ObjectStatus};
+use object_value::ObjectValue;
 use process::RcProcess;
 use tagged_pointer::TaggedPointer;
+use vm::state::State;
 
 /// Performs a write to an object and tracks it in the write barrier.
 macro_rules! write_object {
@@ -53,7 +61,19 @@ unsafe impl Send for ObjectPointerPointer {}
 unsafe impl Sync for ObjectPointerPointer {}
 
 /// The mask to use for tagging a pointer as an integer.
-pub const INTEGER_MARK: usize = 0x1; // TODO: implement integers
+pub const INTEGER_MARK: usize = 0x1;
+
+/// The mask to use for checking which tag is set.
+const TAG_MASK: usize = 0x3;
+
+/// The number of bits to shift integers by when storing them in a pointer.
+const INTEGER_SHIFT: usize = 2;
+
+/// The smallest integer that can be stored in a pointer.
+pub const MIN_INTEGER: i64 = i64::MIN >> INTEGER_SHIFT;
+
+/// The largest integer that can be stored in a pointer.
+pub const MAX_INTEGER: i64 = i64::MAX >> INTEGER_SHIFT;
 
 /// The mask to use for forwarding pointers
 pub const FORWARDING_MASK: usize = 0x2;
@@ -79,6 +99,195 @@ impl ObjectPointer {
         ObjectPointer { raw: TaggedPointer::null() }
     }
 
+    /// Creates a pointer storing the given integer.
+    ///
+    /// The integer must be between `MIN_INTEGER` and `MAX_INTEGER`, use
+    /// `integer_fits` to check this.
+    pub fn integer(value: i64) -> ObjectPointer {
+        let raw = (value << INTEGER_SHIFT) as usize | INTEGER_MARK;
+
+        ObjectPointer::new(raw as RawObjectPointer)
+    }
+
+    /// Returns true if the integer can be stored in a pointer.
+    pub fn integer_fits(value: i64) -> bool {
+        value >= MIN_INTEGER && value <= MAX_INTEGER
+    }
+
+    /// Returns true if the pointer stores an integer, instead of pointing to
+    /// an object.
+    ///
+    /// Tagged integers don't point to any memory, thus methods such as `get`
+    /// and `block` must not be used for them. They are never garbage collected
+    /// or copied, and are treated as permanent objects.
+    #[inline(always)]
+    pub fn is_tagged_integer(&self) -> bool {
+        self.raw.raw as usize & TAG_MASK == INTEGER_MARK
+    }
+
+    /// Returns true if the pointer is a tagged integer, or points to an
+    /// integer object.
+    pub fn is_integer(&self) -> bool {
+        self.is_tagged_integer() || self.get().value.is_integer()
+    }
+
+    /// Returns the integer stored in the pointer, or in the object it points
+    /// to.
+    pub fn integer_value(&self) -> Result<i64, String> {
+        if self.is_tagged_integer() {
+            Ok(self.raw.raw as isize as i64 >> INTEGER_SHIFT)
+        } else {
+            self.get().value.as_integer()
+        }
+    }
+
+    /// Returns the float of the object this pointer points to.
+    pub fn float_value(&self) -> Result<f64, String> {
+        if self.is_tagged_integer() {
+            Err("ObjectValue::as_float() called on a non float".to_string())
+        } else {
+            self.get().value.as_float()
+        }
+    }
+
+    /// Returns the string of the object this pointer points to.
+    pub fn string_value(&self) -> Result<&String, String> {
+        self.value("as_string")?.as_string()
+    }
+
+    /// Returns the array of the object this pointer points to.
+    pub fn array_value(&self) -> Result<&Vec<ObjectPointer>, String> {
+        self.value("as_array")?.as_array()
+    }
+
+    /// Returns a mutable reference to the array of the object this pointer
+    /// points to.
+    pub fn array_value_mut(&self) -> Result<&mut Vec<ObjectPointer>, String> {
+        self.value_mut("as_array_mut")?.as_array_mut()
+    }
+
+    /// Returns the file of the object this pointer points to.
+    pub fn file_value(&self) -> Result<&fs::File, String> {
+        self.value("as_file")?.as_file()
+    }
+
+    /// Returns a mutable reference to the file of the object this pointer
+    /// points to.
+    pub fn file_value_mut(&self) -> Result<&mut fs::File, String> {
+        self.value_mut("as_file_mut")?.as_file_mut()
+    }
+
+    /// Returns the error code of the object this pointer points to.
+    pub fn error_value(&self) -> Result<u16, String> {
+        self.value("as_error")?.as_error()
+    }
+
+    /// Returns the compiled code of the object this pointer points to.
+    pub fn compiled_code_value(&self) -> Result<RcCompiledCode, String> {
+        self.value("as_compiled_code")?.as_compiled_code()
+    }
+
+    /// Returns the binding of the object this pointer points to.
+    pub fn binding_value(&self) -> Result<RcBinding, String> {
+        self.value("as_binding")?.as_binding()
+    }
+
+    /// Returns the channel of the object this pointer points to.
+    pub fn channel_value(&self) -> Result<RcChannel, String> {
+        self.value("as_channel")?.as_channel()
+    }
+
+    /// Returns the target of the weak reference this pointer points to.
+    pub fn weak_reference_value(&self) -> Result<ObjectPointer, String> {
+        self.value("as_weak_reference")?.as_weak_reference()
+    }
+
+    /// Returns the value of the object this pointer points to.
+    ///
+    /// Tagged integers don't point to an object, so an error is returned for
+    /// these instead.
+    fn value(&self, method: &str) -> Result<&ObjectValue, String> {
+        if self.is_tagged_integer() {
+            Err(format!("ObjectValue::{}() called on an integer", method))
+        } else {
+            Ok(&self.get().value)
+        }
+    }
+
+    fn value_mut(&self, method: &str) -> Result<&mut ObjectValue, String> {
+        if self.is_tagged_integer() {
+            Err(format!("ObjectValue::{}() called on an integer", method))
+        } else {
+            Ok(&mut self.get_mut().value)
+        }
+    }
+
+    /// Returns an error if the object can't be modified, which is the case
+    /// for tagged integers.
+    pub fn ensure_mutable(&self) -> Result<(), String> {
+        if self.is_tagged_integer() {
+            Err("Integers can not be modified".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the prototype of the object.
+    ///
+    /// The prototype of a tagged integer is the integer prototype.
+    pub fn prototype(&self, state: &State) -> Option<ObjectPointer> {
+        if self.is_tagged_integer() {
+            Some(state.integer_prototype)
+        } else {
+            self.get().prototype()
+        }
+    }
+
+    /// Returns the object to store when this pointer is used as a prototype.
+    ///
+    /// Tagged integers are not objects, so the integer prototype is stored in
+    /// their place. Method and constant lookups behave the same either way.
+    pub fn as_prototype(&self, state: &State) -> ObjectPointer {
+        if self.is_tagged_integer() {
+            state.integer_prototype
+        } else {
+            *self
+        }
+    }
+
+    /// Looks up a method, using the integer prototype for tagged integers.
+    pub fn lookup_method(&self,
+                         state: &State,
+                         name: &String)
+                         -> Option<ObjectPointer> {
+        if self.is_tagged_integer() {
+            state.integer_prototype.get().lookup_method(name)
+        } else {
+            self.get().lookup_method(name)
+        }
+    }
+
+    /// Looks up a constant, using the integer prototype for tagged integers.
+    pub fn lookup_constant(&self,
+                           state: &State,
+                           name: &String)
+                           -> Option<ObjectPointer> {
+        if self.is_tagged_integer() {
+            state.integer_prototype.get().lookup_constant(name)
+        } else {
+            self.get().lookup_constant(name)
+        }
+    }
+
+    /// Looks up an attribute. Tagged integers don't have any attributes.
+    pub fn lookup_attribute(&self, name: &String) -> Option<ObjectPointer> {
+        if self.is_tagged_integer() {
+            None
+        } else {
+            self.get().lookup_attribute(name)
+        }
+    }
+
     /// Returns a forwarding pointer to the current pointer.
     pub fn forwarding_pointer(&self) -> ObjectPointer {
         let raw = TaggedPointer::with_mask(self.raw.raw, FORWARDING_MASK);
@@ -89,6 +298,10 @@ impl ObjectPointer {
     /// Returns true if the current pointer points to a forwarded object.
     #[inline(always)]
     pub fn is_forwarded(&self) -> bool {
+        if self.is_tagged_integer() {
+            return false;
+        }
+
         self.get().prototype.raw.mask_is_set(FORWARDING_MASK)
     }
 
@@ -140,14 +353,22 @@ impl ObjectPointer {
     }
 
     /// Returns an immutable reference to the Object.
+    ///
+    /// This method must not be used for tagged integers.
     #[inline(always)]
     pub fn get(&self) -> &Object {
+        debug_assert!(!self.is_tagged_integer());
+
         self.raw.as_ref().unwrap()
     }
 
     /// Returns a mutable reference to the Object.
+    ///
+    /// This method must not be used for tagged integers.
     #[inline(always)]
     pub fn get_mut(&self) -> &mut Object {
+        debug_assert!(!self.is_tagged_integer());
+
         self.raw.as_mut().unwrap()
     }
 
@@ -158,22 +379,28 @@ impl ObjectPointer {
     }
 
     /// Returns true if the current pointer points to a permanent object.
+    ///
+    /// Tagged integers are treated as permanent objects.
     pub fn is_permanent(&self) -> bool {
+        self.is_tagged_integer() ||
         self.block().bucket().unwrap().age == PERMANENT
     }
 
     /// Returns true if the current pointer points to a mature object.
     pub fn is_mature(&self) -> bool {
+        !self.is_tagged_integer() &&
         self.block().bucket().unwrap().age == MATURE
     }
 
     /// Returns true if the current pointer points to a mailbox object.
     pub fn is_mailbox(&self) -> bool {
+        !self.is_tagged_integer() &&
         self.block().bucket().unwrap().age == MAILBOX
     }
 
     /// Returns true if the current pointer points to a young object.
     pub fn is_young(&self) -> bool {
+        !self.is_tagged_integer() &&
         self.block().bucket().unwrap().age <= YOUNG_MAX_AGE
     }
 
@@ -198,6 +425,10 @@ impl ObjectPointer {
     /// race conditions when determining the object/line indexes, and reduces
     /// the overhead of having to call `self.raw.untagged()` multiple times.
     pub fn mark(&self) {
+        if self.is_tagged_integer() {
+            return;
+        }
+
         let pointer = self.raw.untagged();
         let header = block_header_of(pointer);
         let ref mut block = header.block_mut();
@@ -216,7 +447,14 @@ impl ObjectPointer {
     /// incorrect object/line indexes. This can happen when one tried checks if
     /// an object is marked while another thread is updating the pointer's
     /// address (e.g. after evacuating the underlying object).
+    ///
+    /// Tagged integers are always considered to be marked, ensuring the
+    /// collector skips them.
     pub fn is_marked(&self) -> bool {
+        if self.is_tagged_integer() {
+            return true;
+        }
+
         let pointer = self.raw.untagged();
         let header = block_header_of(pointer);
         let ref mut block = header.block_mut();
@@ -344,7 +582,9 @@ mod tests {
     use immix::bucket::{Bucket, MATURE, MAILBOX, PERMANENT};
     use immix::block::Block;
     use object::{Object, ObjectStatus};
+    use object_value;
     use object_value::ObjectValue;
+    use config::Config;
 
     fn fake_raw_pointer() -> RawObjectPointer {
         0x4 as RawObjectPointer
@@ -705,6 +945,100 @@ mod tests {
         assert!(pointer1 != pointer2);
     }
 
+    #[test]
+    fn test_object_pointer_integer() {
+        let pointer = ObjectPointer::integer(5);
+
+        assert!(pointer.is_tagged_integer());
+        assert!(pointer.is_integer());
+        assert_eq!(pointer.integer_value().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_object_pointer_integer_with_negative_values() {
+        let min = ObjectPointer::integer(MIN_INTEGER);
+        let negative = ObjectPointer::integer(-5);
+
+        assert_eq!(min.integer_value().unwrap(), MIN_INTEGER);
+        assert_eq!(negative.integer_value().unwrap(), -5);
+    }
+
+    #[test]
+    fn test_object_pointer_integer_fits() {
+        assert!(ObjectPointer::integer_fits(MAX_INTEGER));
+        assert!(ObjectPointer::integer_fits(MIN_INTEGER));
+        assert_eq!(ObjectPointer::integer_fits(MAX_INTEGER + 1), false);
+        assert_eq!(ObjectPointer::integer_fits(MIN_INTEGER - 1), false);
+    }
+
+    #[test]
+    fn test_object_pointer_is_tagged_integer_with_regular_pointers() {
+        let mut allocator = local_allocator();
+        let pointer = allocator.allocate_empty();
+        let integer =
+            allocator.allocate_without_prototype(ObjectValue::Integer(5));
+
+        assert_eq!(pointer.is_tagged_integer(), false);
+        assert_eq!(integer.is_tagged_integer(), false);
+        assert!(integer.is_integer());
+        assert_eq!(integer.integer_value().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_object_pointer_float_value_with_tagged_integer() {
+        assert!(ObjectPointer::integer(5).float_value().is_err());
+    }
+
+    #[test]
+    fn test_object_pointer_string_value() {
+        let object = Object::new(object_value::string("a".to_string()));
+        let pointer = object_pointer_for(&object);
+
+        assert_eq!(pointer.string_value().unwrap(), "a");
+        assert!(ObjectPointer::integer(5).string_value().is_err());
+    }
+
+    #[test]
+    fn test_object_pointer_array_value_mut_with_tagged_integer() {
+        assert!(ObjectPointer::integer(5).array_value_mut().is_err());
+    }
+
+    #[test]
+    fn test_object_pointer_ensure_mutable() {
+        let mut allocator = local_allocator();
+
+        assert!(allocator.allocate_empty().ensure_mutable().is_ok());
+        assert!(ObjectPointer::integer(5).ensure_mutable().is_err());
+    }
+
+    #[test]
+    fn test_object_pointer_tagged_integer_gc_status() {
+        let pointer = ObjectPointer::integer(5);
+
+        assert!(pointer.is_permanent());
+        assert!(pointer.is_marked());
+        assert_eq!(pointer.is_young(), false);
+        assert_eq!(pointer.is_mature(), false);
+        assert_eq!(pointer.is_mailbox(), false);
+        assert_eq!(pointer.is_forwarded(), false);
+    }
+
+    #[test]
+    fn test_object_pointer_tagged_integer_lookups() {
+        let state = State::new(Config::new());
+        let pointer = ObjectPointer::integer(5);
+        let method = state.permanent_allocator.lock().allocate_empty();
+        let name = "foo".to_string();
+
+        state.integer_prototype.get_mut().add_method(name.clone(), method);
+        state.integer_prototype.get_mut().add_constant(name.clone(), method);
+
+        assert!(pointer.prototype(&state).unwrap() == state.integer_prototype);
+        assert!(pointer.lookup_method(&state, &name).unwrap() == method);
+        assert!(pointer.lookup_constant(&state, &name).unwrap() == method);
+        assert!(pointer.lookup_attribute(&name).is_none());
+    }
+
     #[test]
     fn test_object_pointer_hashing() {
         let mut allocator = local_allocator();
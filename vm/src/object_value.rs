@@ -6,8 +6,10 @@
 
 use std::fs;
 use std::mem;
+use std::sync::Arc;
 
 use binding::RcBinding;
+use channel::RcChannel;
 use object_pointer::ObjectPointer;
 use compiled_code::RcCompiledCode;
 
@@ -16,12 +18,18 @@ pub enum ObjectValue {
     None,
     Integer(i64),
     Float(f64),
-    String(Box<String>),
+    /// Strings are immutable, allowing copies of string objects to share
+    /// the same contents.
+    String(Arc<String>),
     Array(Box<Vec<ObjectPointer>>),
     File(Box<fs::File>),
     Error(u16),
     CompiledCode(RcCompiledCode),
     Binding(RcBinding),
+    Channel(RcChannel),
+    /// A reference to an object that doesn't keep the object alive. The
+    /// pointer is set to NULL once the object has been garbage collected.
+    WeakReference(ObjectPointer),
 }
 
 impl ObjectValue {
@@ -88,6 +96,20 @@ impl ObjectValue {
         }
     }
 
+    pub fn is_channel(&self) -> bool {
+        match *self {
+            ObjectValue::Channel(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_weak_reference(&self) -> bool {
+        match *self {
+            ObjectValue::WeakReference(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn as_integer(&self) -> Result<i64, String> {
         match *self {
             ObjectValue::Integer(val) => Ok(val),
@@ -171,10 +193,61 @@ impl ObjectValue {
         }
     }
 
+    pub fn as_channel(&self) -> Result<RcChannel, String> {
+        match *self {
+            ObjectValue::Channel(ref val) => Ok(val.clone()),
+            _ => {
+                Err("ObjectValue::as_channel() called on a non channel"
+                    .to_string())
+            }
+        }
+    }
+
+    pub fn as_weak_reference(&self) -> Result<ObjectPointer, String> {
+        match *self {
+            ObjectValue::WeakReference(val) => Ok(val),
+            _ => {
+                Err("ObjectValue::as_weak_reference() called on a non weak \
+                     reference"
+                    .to_string())
+            }
+        }
+    }
+
     pub fn take(&mut self) -> ObjectValue {
         mem::replace(self, ObjectValue::None)
     }
 
+    /// Returns the name of the type of this value.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            ObjectValue::None => "none",
+            ObjectValue::Integer(_) => "integer",
+            ObjectValue::Float(_) => "float",
+            ObjectValue::String(_) => "string",
+            ObjectValue::Array(_) => "array",
+            ObjectValue::File(_) => "file",
+            ObjectValue::Error(_) => "error",
+            ObjectValue::CompiledCode(_) => "compiled_code",
+            ObjectValue::Binding(_) => "binding",
+            ObjectValue::Channel(_) => "channel",
+            ObjectValue::WeakReference(_) => "weak_reference",
+        }
+    }
+
+    /// Returns the approximate number of bytes used by this value outside of
+    /// the heap, such as the contents of a string.
+    pub fn off_heap_size(&self) -> usize {
+        match *self {
+            ObjectValue::String(ref string) => string.capacity(),
+            ObjectValue::Array(ref array) => {
+                array.capacity() * mem::size_of::<ObjectPointer>()
+            }
+            ObjectValue::File(_) => mem::size_of::<fs::File>(),
+            _ => 0,
+        }
+    }
+
     /// Returns true if this value should be deallocated explicitly.
     pub fn should_deallocate_native(&self) -> bool {
         match self {
@@ -197,7 +270,7 @@ pub fn float(value: f64) -> ObjectValue {
 }
 
 pub fn string(value: String) -> ObjectValue {
-    ObjectValue::String(Box::new(value))
+    ObjectValue::String(Arc::new(value))
 }
 
 pub fn array(value: Vec<ObjectPointer>) -> ObjectValue {
@@ -220,11 +293,22 @@ pub fn binding(value: RcBinding) -> ObjectValue {
     ObjectValue::Binding(value)
 }
 
+pub fn channel(value: RcChannel) -> ObjectValue {
+    ObjectValue::Channel(value)
+}
+
+pub fn weak_reference(value: ObjectPointer) -> ObjectValue {
+    ObjectValue::WeakReference(value)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs::File;
+    use std::mem;
+    use std::sync::Arc;
     use binding::Binding;
+    use channel::Channel;
     use compiled_code::CompiledCode;
     use object_pointer::ObjectPointer;
 
@@ -254,7 +338,7 @@ mod tests {
 
     #[test]
     fn test_is_string() {
-        assert!(ObjectValue::String(Box::new(String::new())).is_string());
+        assert!(ObjectValue::String(Arc::new(String::new())).is_string());
         assert_eq!(ObjectValue::None.is_string(), false);
     }
 
@@ -292,6 +376,20 @@ mod tests {
         assert_eq!(ObjectValue::None.is_binding(), false);
     }
 
+    #[test]
+    fn test_is_channel() {
+        assert!(ObjectValue::Channel(Channel::new(1, None)).is_channel());
+        assert_eq!(ObjectValue::None.is_channel(), false);
+    }
+
+    #[test]
+    fn test_is_weak_reference() {
+        let value = ObjectValue::WeakReference(ObjectPointer::integer(5));
+
+        assert!(value.is_weak_reference());
+        assert_eq!(ObjectValue::None.is_weak_reference(), false);
+    }
+
     #[test]
     fn test_as_integer_without_integer() {
         assert!(ObjectValue::None.as_integer().is_err());
@@ -355,7 +453,7 @@ mod tests {
 
     #[test]
     fn test_as_string_with_string() {
-        let string = Box::new("test".to_string());
+        let string = Arc::new("test".to_string());
         let value = ObjectValue::String(string);
         let result = value.as_string();
 
@@ -438,6 +536,32 @@ mod tests {
         assert!(result.unwrap().self_object == pointer);
     }
 
+    #[test]
+    fn test_as_channel_without_channel() {
+        assert!(ObjectValue::None.as_channel().is_err());
+    }
+
+    #[test]
+    fn test_as_channel_with_channel() {
+        let result = ObjectValue::Channel(Channel::new(1, None)).as_channel();
+
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().owner, 1);
+    }
+
+    #[test]
+    fn test_as_weak_reference_without_weak_reference() {
+        assert!(ObjectValue::None.as_weak_reference().is_err());
+    }
+
+    #[test]
+    fn test_as_weak_reference_with_weak_reference() {
+        let pointer = ObjectPointer::integer(5);
+        let result = ObjectValue::WeakReference(pointer).as_weak_reference();
+
+        assert!(result.unwrap() == pointer);
+    }
+
     #[test]
     fn test_take() {
         let mut val1 = ObjectValue::Integer(5);
@@ -448,6 +572,28 @@ mod tests {
         assert_eq!(val2.as_integer().unwrap(), 5);
     }
 
+    #[test]
+    fn test_type_name() {
+        assert_eq!(ObjectValue::None.type_name(), "none");
+        assert_eq!(ObjectValue::Integer(5).type_name(), "integer");
+        assert_eq!(string("a".to_string()).type_name(), "string");
+        assert_eq!(array(Vec::new()).type_name(), "array");
+        assert_eq!(weak_reference(ObjectPointer::null()).type_name(),
+                   "weak_reference");
+    }
+
+    #[test]
+    fn test_off_heap_size() {
+        let pointer_size = mem::size_of::<ObjectPointer>();
+
+        assert_eq!(string("abc".to_string()).off_heap_size(), 3);
+        assert_eq!(array(Vec::with_capacity(2)).off_heap_size(),
+                   2 * pointer_size);
+
+        assert_eq!(ObjectValue::Integer(5).off_heap_size(), 0);
+        assert_eq!(ObjectValue::None.off_heap_size(), 0);
+    }
+
     #[test]
     fn test_none() {
         assert!(none().is_none());
@@ -502,4 +648,9 @@ mod tests {
 
         assert!(binding(b).is_binding());
     }
+
+    #[test]
+    fn test_weak_reference() {
+        assert!(weak_reference(ObjectPointer::null()).is_weak_reference());
+    }
 }
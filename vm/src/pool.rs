@@ -151,6 +151,24 @@ impl<T: Send + 'static> Pool<T> {
         self.inner.queues[queue_index].push(Job::Perform(value));
     }
 
+    /// Removes all jobs from the queues without performing them.
+    ///
+    /// Termination jobs are discarded. This allows the jobs of a pool to be
+    /// performed without starting any of its threads.
+    pub fn take_jobs(&self) -> Vec<T> {
+        let mut jobs = Vec::new();
+
+        for queue in self.inner.queues.iter() {
+            for job in queue.pop_all() {
+                if let Job::Perform(value) = job {
+                    jobs.push(value);
+                }
+            }
+        }
+
+        jobs
+    }
+
     /// Terminates all the schedulers, ignoring any remaining jobs
     pub fn terminate(&self) {
         self.inner.stop_processing();
@@ -285,6 +303,23 @@ mod tests {
         assert_eq!(pool.inner.queues[1].len(), 1);
     }
 
+    #[test]
+    fn test_pool_take_jobs() {
+        let pool = Pool::new(2);
+
+        pool.schedule(1);
+        pool.schedule(2);
+        pool.terminate();
+
+        let mut jobs = pool.take_jobs();
+
+        jobs.sort();
+
+        assert_eq!(jobs, vec![1, 2]);
+        assert_eq!(pool.inner.queues[0].len(), 0);
+        assert_eq!(pool.inner.queues[1].len(), 0);
+    }
+
     #[test]
     fn test_pool_terminate() {
         let pool: Pool<()> = Pool::new(2);
@@ -1,35 +1,121 @@
 //! Collections of multiple process pools.
+//!
+//! Every VM has at least two pools: the primary and the secondary pool.
+//! Additional pools can be defined using the VM configuration, each with a
+//! name and its own number of threads. Pools are identified by their index,
+//! and the ID of a pool can be looked up using its name.
+use std::collections::HashMap;
+
+use config::Config;
 use pool::Pool;
 use process::RcProcess;
 
-/// The number of process pools to create.
-const POOL_AMOUNT: usize = 2;
-
 /// The index of the primary process pool.
 pub const PRIMARY_POOL: usize = 0;
 
 /// The index of the secondary process pool.
 pub const SECONDARY_POOL: usize = 1;
 
+/// The name of the primary process pool.
+pub const PRIMARY_POOL_NAME: &'static str = "primary";
+
+/// The name of the secondary process pool.
+pub const SECONDARY_POOL_NAME: &'static str = "secondary";
+
 pub struct Pools {
-    pools: [Pool<RcProcess>; POOL_AMOUNT],
+    pools: Vec<Pool<RcProcess>>,
+
+    /// The names of all pools and the IDs they map to.
+    names: HashMap<String, usize>,
+
+    /// The ID of the pool to run blocking instructions on.
+    blocking_pool: usize,
 }
 
 impl Pools {
     pub fn new(primary: usize, secondary: usize) -> Self {
-        Pools { pools: [Pool::new(primary), Pool::new(secondary)] }
+        let mut pools = Pools {
+            pools: Vec::new(),
+            names: HashMap::new(),
+            blocking_pool: SECONDARY_POOL,
+        };
+
+        pools.add(PRIMARY_POOL_NAME.to_string(), primary);
+        pools.add(SECONDARY_POOL_NAME.to_string(), secondary);
+
+        pools
+    }
+
+    /// Returns a collection of pools using the pools defined in the given
+    /// configuration.
+    pub fn with_config(config: &Config) -> Self {
+        let mut pools = Pools::new(config.primary_threads,
+                                   config.secondary_threads);
+
+        for pool in config.pools.iter() {
+            pools.add(pool.name.clone(), pool.threads);
+        }
+
+        if let Some(id) = pools.id_for_name(&config.blocking_pool) {
+            pools.blocking_pool = id;
+        }
+
+        pools
+    }
+
+    /// Adds a new pool and returns its ID.
+    ///
+    /// If a pool with the given name already exists its ID is returned and no
+    /// new pool is created.
+    pub fn add(&mut self, name: String, threads: usize) -> usize {
+        if let Some(id) = self.id_for_name(&name) {
+            return id;
+        }
+
+        let id = self.pools.len();
+
+        self.pools.push(Pool::new(threads));
+        self.names.insert(name, id);
+
+        id
     }
 
     pub fn get(&self, index: usize) -> Option<&Pool<RcProcess>> {
         self.pools.get(index)
     }
 
+    /// Returns the ID of the pool with the given name.
+    pub fn id_for_name(&self, name: &str) -> Option<usize> {
+        self.names.get(name).cloned()
+    }
+
+    /// Returns the ID of the pool to use for running blocking instructions.
+    pub fn blocking_pool(&self) -> usize {
+        self.blocking_pool
+    }
+
+    /// Returns true if a pool exists for the given ID.
+    pub fn is_valid(&self, index: usize) -> bool {
+        index < self.pools.len()
+    }
+
+    /// Returns the number of pools.
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Schedules a process in the pool it is currently assigned to.
+    ///
+    /// The pool ID of a process is validated when the process is spawned, thus
+    /// an invalid ID at this point is a bug in the VM.
     pub fn schedule(&self, process: RcProcess) {
-        if let Some(pool) = self.get(process.pool_id) {
+        let pool_id = process.current_pool_id();
+
+        if let Some(pool) = self.get(pool_id) {
             pool.schedule(process);
         } else {
             panic!("The pool ID ({}) for process {} is invalid",
-                   process.pool_id,
+                   pool_id,
                    process.pid);
         }
     }
@@ -45,6 +131,7 @@ impl Pools {
 mod tests {
     use super::*;
     use compiled_code::CompiledCode;
+    use config::Config;
     use immix::global_allocator::GlobalAllocator;
     use object_pointer::ObjectPointer;
     use process::{Process, RcProcess};
@@ -68,6 +155,63 @@ mod tests {
         assert_eq!(pools.pools[1].inner.queues.len(), 1);
     }
 
+    #[test]
+    fn test_with_config() {
+        let mut config = Config::new();
+
+        config.set_primary_threads(1);
+        config.set_secondary_threads(1);
+        config.add_pool("io".to_string(), 2);
+
+        let pools = Pools::with_config(&config);
+
+        assert_eq!(pools.len(), 3);
+        assert_eq!(pools.pools[2].inner.queues.len(), 2);
+        assert_eq!(pools.id_for_name("io"), Some(2));
+        assert_eq!(pools.blocking_pool(), SECONDARY_POOL);
+    }
+
+    #[test]
+    fn test_with_config_blocking_pool() {
+        let mut config = Config::new();
+
+        config.set_primary_threads(1);
+        config.set_secondary_threads(1);
+        config.add_pool("io".to_string(), 1);
+        config.blocking_pool = "io".to_string();
+
+        let pools = Pools::with_config(&config);
+
+        assert_eq!(pools.blocking_pool(), 2);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut pools = Pools::new(1, 1);
+
+        assert_eq!(pools.add("io".to_string(), 1), 2);
+        assert_eq!(pools.add("io".to_string(), 1), 2);
+        assert_eq!(pools.len(), 3);
+    }
+
+    #[test]
+    fn test_id_for_name() {
+        let pools = Pools::new(1, 1);
+
+        assert_eq!(pools.id_for_name("primary"), Some(PRIMARY_POOL));
+        assert_eq!(pools.id_for_name("secondary"), Some(SECONDARY_POOL));
+        assert!(pools.id_for_name("foo").is_none());
+    }
+
+    #[test]
+    fn test_is_valid() {
+        let pools = Pools::new(1, 1);
+
+        assert!(pools.is_valid(0));
+        assert!(pools.is_valid(1));
+        assert_eq!(pools.is_valid(2), false);
+    }
+
     #[test]
     fn test_get_invalid() {
         let pools = Pools::new(1, 1);
@@ -92,6 +236,18 @@ mod tests {
         assert_eq!(pools.pools[0].inner.queues.len(), 1);
     }
 
+    #[test]
+    fn test_schedule_moved_process() {
+        let pools = Pools::new(1, 1);
+        let process = new_process(0);
+
+        process.move_to_pool(1);
+        pools.schedule(process);
+
+        assert_eq!(pools.pools[0].inner.queues[0].len(), 0);
+        assert_eq!(pools.pools[1].inner.queues[0].len(), 1);
+    }
+
     #[test]
     #[should_panic]
     fn test_schedule_invalid() {
@@ -2,16 +2,22 @@ use std::collections::HashSet;
 use std::mem;
 use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::cell::UnsafeCell;
 
+use immix::copy_object::{CopyObject, ObjectGraph};
 use immix::local_allocator::LocalAllocator;
 use immix::global_allocator::RcGlobalAllocator;
 
-use binding::RcBinding;
+use binding::{Binding, RcBinding};
 use call_frame::CallFrame;
+use channel::{Channel, RcChannel, SendResult};
 use compiled_code::RcCompiledCode;
 use config::Config;
 use execution_context::ExecutionContext;
+use gc::finalizer::Finalizer;
+use gc::statistics::Statistics;
+use heap_limits::HeapLimits;
 use mailbox::Mailbox;
 use object_pointer::ObjectPointer;
 use object_value;
@@ -36,6 +42,9 @@ pub enum ProcessStatus {
     /// The process has been suspended for garbage collection.
     SuspendedByGc,
 
+    /// The process has been suspended so a heap snapshot can be taken of it.
+    SuspendedForSnapshot,
+
     /// The process ran into some kind of error during execution.
     Failed,
 
@@ -50,6 +59,47 @@ impl ProcessStatus {
             _ => false,
         }
     }
+
+    /// Returns true if another thread may currently be mutating the process.
+    pub fn is_in_use(&self) -> bool {
+        match *self {
+            ProcessStatus::Running |
+            ProcessStatus::SuspendForGc |
+            ProcessStatus::SuspendedByGc => true,
+            _ => false,
+        }
+    }
+
+    /// Returns a human readable name of the status.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ProcessStatus::Scheduled => "scheduled",
+            ProcessStatus::Running => "running",
+            ProcessStatus::Suspended => "suspended",
+            ProcessStatus::SuspendForGc => "suspend_for_gc",
+            ProcessStatus::SuspendedByGc => "suspended_by_gc",
+            ProcessStatus::SuspendedForSnapshot => "suspended_for_snapshot",
+            ProcessStatus::Failed => "failed",
+            ProcessStatus::Finished => "finished",
+        }
+    }
+}
+
+/// The reason a process finished execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The process ran out of instructions to execute.
+    Normal,
+
+    /// The process exited itself using the given reason.
+    Exit(i64),
+
+    /// The process was killed by another process.
+    Killed,
+
+    /// The process exceeded one of its heap limits, even after a garbage
+    /// collection.
+    OutOfMemory,
 }
 
 pub enum GcState {
@@ -78,6 +128,22 @@ pub struct LocalData {
     /// suspended upon examining the remembered set.
     pub remembered_set: HashSet<ObjectPointer>,
 
+    /// The objects that have registered a finalizer.
+    pub finalizers: Vec<Finalizer>,
+
+    /// The finalizers of unreachable objects that have yet to be scheduled.
+    pub finalize_queue: Vec<Finalizer>,
+
+    /// The finalizers to run in this process, instead of in a new process.
+    pub local_finalizers: Vec<Finalizer>,
+
+    /// The receivers a broadcast has yet to send its message to, after the
+    /// process was suspended because a mailbox was full.
+    pub pending_broadcast: Vec<RcProcess>,
+
+    /// The maximum number of blocks this process can use.
+    pub heap_limits: HeapLimits,
+
     /// The mailbox for sending/receiving messages.
     ///
     /// The Mailbox is stored in LocalData as a Mailbox uses internal locking
@@ -92,8 +158,136 @@ pub struct LocalData {
     /// The number of mature garbage collections that have been performed.
     pub mature_collections: usize,
 
+    /// The number of times the process has hibernated.
+    pub hibernations: usize,
+
+    /// The number of mailbox collections that have been performed.
+    pub mailbox_collections: usize,
+}
+
+/// The event a waiting process is waiting for.
+#[derive(Clone)]
+pub enum WaitReason {
+    /// The process is waiting for a message to arrive in its mailbox.
+    Message,
+
+    /// The process is waiting for a message to arrive in one of its channels.
+    ChannelMessage(RcChannel),
+
+    /// The process is waiting for space to become available in a channel.
+    ChannelSpace(RcChannel),
+
+    /// The process is waiting for space to become available in the mailbox
+    /// of another process.
+    MailboxSpace(RcProcess),
+}
+
+/// Details about a process that is waiting for an event.
+#[derive(Clone)]
+pub struct WaitInfo {
+    /// A number that is unique for every time the process started waiting.
+    ///
+    /// This can be used to determine if a process stopped waiting and started
+    /// waiting again at the same location.
+    pub sequence: usize,
+
+    /// The event the process is waiting for.
+    pub reason: WaitReason,
+
+    /// The name of the method the process is waiting in.
+    pub method: String,
+
+    /// The file of the method the process is waiting in.
+    pub file: String,
+
+    /// The line number the process is waiting on.
+    pub line: u32,
+}
+
+impl WaitInfo {
+    /// Returns true if the event the process is waiting for has happened.
+    pub fn is_ready(&self, process: &Process) -> bool {
+        match self.reason {
+            WaitReason::Message => process.has_messages(),
+            WaitReason::ChannelMessage(ref channel) => {
+                channel.is_closed() || channel.len() > 0
+            }
+            WaitReason::ChannelSpace(ref channel) => {
+                if let Some(capacity) = channel.capacity {
+                    channel.is_closed() || channel.len() < capacity
+                } else {
+                    true
+                }
+            }
+            WaitReason::MailboxSpace(ref receiver) => {
+                !receiver.is_alive() || !receiver.mailbox_is_full()
+            }
+        }
+    }
+}
+
+/// A snapshot of various details of a process, used for introspection.
+pub struct ProcessInfo {
+    /// The name of the process' status.
+    pub status: &'static str,
+
+    /// The ID of the pool the process belongs to.
+    pub pool_id: usize,
+
+    /// The number of messages in the mailbox.
+    pub mailbox_size: usize,
+
+    /// The maximum number of messages the mailbox can hold, if any.
+    pub mailbox_capacity: Option<usize>,
+
+    /// The number of messages dropped because the mailbox was full.
+    pub mailbox_dropped: usize,
+
+    /// The number of times a sender was suspended because the mailbox was
+    /// full.
+    pub mailbox_blocked: usize,
+
+    /// Details about the heap of the process.
+    ///
+    /// Just like the method being executed this is only available if the
+    /// process is not in use by another thread.
+    pub heap: Option<HeapInfo>,
+
+    /// The name of the method being executed.
+    ///
+    /// This is only available if the call frame could be inspected safely,
+    /// which is not the case when the process is running on another thread.
+    pub method: Option<String>,
+
+    /// The file of the method being executed.
+    pub file: Option<String>,
+
+    /// The line number being executed.
+    pub line: Option<u32>,
+}
+
+/// A snapshot of the heap of a process, used for introspection.
+pub struct HeapInfo {
+    /// The number of young garbage collections that have been performed.
+    pub young_collections: usize,
+
+    /// The number of mature garbage collections that have been performed.
+    pub mature_collections: usize,
+
+    /// The number of times the process has hibernated.
+    pub hibernations: usize,
+
     /// The number of mailbox collections that have been performed.
     pub mailbox_collections: usize,
+
+    /// The number of blocks allocated for the young generation.
+    pub young_blocks: usize,
+
+    /// The number of blocks allocated for the mature generation.
+    pub mature_blocks: usize,
+
+    /// The number of blocks allocated for the mailbox.
+    pub mailbox_blocks: usize,
 }
 
 pub struct Process {
@@ -103,6 +297,11 @@ pub struct Process {
     /// The ID of the pool that this process belongs to.
     pub pool_id: usize,
 
+    /// The ID of the pool this process is currently scheduled on. This differs
+    /// from `pool_id` when the process has been moved to a different pool,
+    /// for example to run a blocking instruction.
+    pub current_pool_id: AtomicUsize,
+
     /// The status of this process.
     pub status: Mutex<ProcessStatus>,
 
@@ -110,6 +309,21 @@ pub struct Process {
     /// process' status to change.
     pub status_signaler: Condvar,
 
+    /// Set to true when the process should terminate at its next safepoint.
+    pub terminate: AtomicBool,
+
+    /// The reason the process finished, or will finish, execution.
+    pub exit_reason: Mutex<ExitReason>,
+
+    /// Details about the event the process is waiting for, if any.
+    pub waiting: Mutex<Option<WaitInfo>>,
+
+    /// The aggregated profiles of all garbage collections of this process.
+    pub gc_statistics: Mutex<Statistics>,
+
+    /// The number of times the process started waiting.
+    pub wait_count: AtomicUsize,
+
     /// Data stored in a process that should only be modified by a single thread
     /// at once.
     pub local_data: UnsafeCell<LocalData>,
@@ -132,17 +346,29 @@ impl Process {
             context: Box::new(context),
             gc_state: GcState::None,
             remembered_set: HashSet::new(),
+            finalizers: Vec::new(),
+            finalize_queue: Vec::new(),
+            local_finalizers: Vec::new(),
+            pending_broadcast: Vec::new(),
+            heap_limits: HeapLimits::unlimited(),
             mailbox: Mailbox::new(global_allocator),
             young_collections: 0,
             mature_collections: 0,
+            hibernations: 0,
             mailbox_collections: 0,
         };
 
         let process = Process {
             pid: pid,
             pool_id: pool_id,
+            current_pool_id: AtomicUsize::new(pool_id),
             status: Mutex::new(ProcessStatus::Scheduled),
             status_signaler: Condvar::new(),
+            terminate: AtomicBool::new(false),
+            exit_reason: Mutex::new(ExitReason::Normal),
+            waiting: Mutex::new(None),
+            gc_statistics: Mutex::new(Statistics::new()),
+            wait_count: AtomicUsize::new(0),
             local_data: UnsafeCell::new(local_data),
         };
 
@@ -262,15 +488,158 @@ impl Process {
         local_data.allocator.allocate_without_prototype(value)
     }
 
+    /// Allocates a weak reference to the given object.
+    pub fn allocate_weak_reference(&self,
+                                   target: ObjectPointer,
+                                   proto: ObjectPointer)
+                                   -> ObjectPointer {
+        let mut local_data = self.local_data_mut();
+
+        local_data.allocator.allocate_weak_reference(target, proto)
+    }
+
+    /// Allocates an integer.
+    ///
+    /// Integers that fit in a pointer are stored in the pointer itself, only
+    /// larger integers are allocated on the heap.
+    pub fn allocate_integer(&self,
+                            value: i64,
+                            proto: ObjectPointer)
+                            -> ObjectPointer {
+        if ObjectPointer::integer_fits(value) {
+            ObjectPointer::integer(value)
+        } else {
+            self.allocate(object_value::integer(value), proto)
+        }
+    }
+
     /// Sends a message to the current process.
-    pub fn send_message(&self, sender: &RcProcess, message: ObjectPointer) {
+    ///
+    /// Returns false if the message could not be sent because the mailbox is
+    /// full. Messages sent by a process to itself are always sent.
+    ///
+    /// Messages sent by other processes are copied, and thus must be
+    /// copyable.
+    pub fn send_message(&self,
+                        sender: &RcProcess,
+                        message: ObjectPointer)
+                        -> bool {
         if sender.pid == self.pid {
             self.local_data_mut().mailbox.send_from_self(message);
+
+            true
         } else {
-            self.local_data_mut().mailbox.send_from_external(message);
+            self.local_data_mut().mailbox.send_from_external(message)
         }
     }
 
+    /// Sends a message to this process using an object graph of the message.
+    ///
+    /// Returns false if the message could not be sent because the mailbox is
+    /// full.
+    pub fn send_graph(&self, graph: &ObjectGraph) -> bool {
+        self.local_data_mut().mailbox.send_graph(graph)
+    }
+
+    /// Sends a message serialized by another VM instance to this process.
+    ///
+    /// Returns false if the message could not be sent because the mailbox is
+    /// full.
+    pub fn send_serialized_message(&self,
+                                   bytes: &[u8],
+                                   permanent: &[ObjectPointer])
+                                   -> Result<bool, String> {
+        self.local_data_mut().mailbox.send_serialized(bytes, permanent)
+    }
+
+    /// Sets the maximum number of messages the mailbox can hold.
+    ///
+    /// A capacity of 0 means the mailbox is unbounded.
+    pub fn set_mailbox_capacity(&self, capacity: usize) {
+        self.local_data_mut().mailbox.capacity = if capacity > 0 {
+            Some(capacity)
+        } else {
+            None
+        };
+    }
+
+    /// Stores the receivers a suspended broadcast has yet to send its message
+    /// to.
+    pub fn set_pending_broadcast(&self, receivers: Vec<RcProcess>) {
+        self.local_data_mut().pending_broadcast = receivers;
+    }
+
+    /// Removes and returns the receivers a suspended broadcast has yet to send
+    /// its message to.
+    pub fn take_pending_broadcast(&self) -> Vec<RcProcess> {
+        mem::replace(&mut self.local_data_mut().pending_broadcast, Vec::new())
+    }
+
+    pub fn mailbox_is_full(&self) -> bool {
+        self.local_data().mailbox.is_full()
+    }
+
+    /// Records a message sent to this process being dropped because the
+    /// mailbox was full.
+    pub fn message_dropped(&self) {
+        self.local_data().mailbox.record_dropped();
+    }
+
+    /// Records a sender being suspended because the mailbox was full.
+    pub fn sender_blocked(&self) {
+        self.local_data().mailbox.record_blocked();
+    }
+
+    /// Creates a new channel owned by this process.
+    pub fn create_channel(&self, capacity: Option<usize>) -> RcChannel {
+        let channel = Channel::new(self.pid, capacity);
+
+        self.local_data_mut().mailbox.add_channel(&channel);
+
+        channel
+    }
+
+    /// Sends a message to a channel owned by this process.
+    ///
+    /// The message is copied, and thus must be copyable.
+    pub fn send_to_channel(&self,
+                           channel: &Channel,
+                           message: ObjectPointer)
+                           -> SendResult {
+        self.local_data_mut().mailbox.send_to_channel(channel, message)
+    }
+
+    /// Receives a message from a channel owned by this process.
+    ///
+    /// The message is copied from the mailbox heap into the heap of this
+    /// process.
+    pub fn receive_from_channel(&self,
+                                channel: &Channel)
+                                -> Option<ObjectPointer> {
+        if let Some(pointer) = channel.pop() {
+            // Messages are checked when they are sent, so we know they can be
+            // copied.
+            let allocator = &mut self.local_data_mut().allocator;
+
+            Some(allocator.copy_copyable_object(pointer))
+        } else {
+            None
+        }
+    }
+
+    /// Copies an object (allocated on another heap) into the heap of this
+    /// process.
+    pub fn copy_object(&self,
+                       pointer: ObjectPointer)
+                       -> Result<ObjectPointer, String> {
+        self.local_data_mut().allocator.copy_object(pointer)
+    }
+
+    /// Changes the object to use as "self" in the current execution context.
+    pub fn set_self_object(&self, object: ObjectPointer) {
+        self.local_data_mut().context.binding = Binding::new(object);
+    }
+
     /// Returns a message from the mailbox.
     pub fn receive_message(&self) -> Option<ObjectPointer> {
         self.local_data_mut().mailbox.receive()
@@ -338,8 +707,42 @@ impl Process {
         }
     }
 
+    /// Marks the process as running.
+    ///
+    /// If a heap snapshot is being taken of the process, this method blocks
+    /// until the snapshot has been written.
     pub fn running(&self) {
-        self.set_status(ProcessStatus::Running);
+        let mut status = lock!(self.status);
+
+        while let ProcessStatus::SuspendedForSnapshot = *status {
+            status = self.status_signaler.wait(status).unwrap();
+        }
+
+        *status = ProcessStatus::Running;
+
+        self.status_signaler.notify_all();
+    }
+
+    /// Prevents a suspended process from running while a heap snapshot is
+    /// taken of it.
+    ///
+    /// The previous status is returned if the process was suspended, and has
+    /// to be restored using `resume_after_snapshot`. None is returned if the
+    /// process is running, being garbage collected, or already finished.
+    pub fn suspend_for_snapshot(&self) -> Option<ProcessStatus> {
+        let mut status = lock!(self.status);
+
+        match *status {
+            ProcessStatus::Scheduled | ProcessStatus::Suspended => {}
+            _ => return None,
+        }
+
+        Some(mem::replace(&mut *status, ProcessStatus::SuspendedForSnapshot))
+    }
+
+    /// Restores the status of a process suspended for a heap snapshot.
+    pub fn resume_after_snapshot(&self, status: ProcessStatus) {
+        self.set_status(status);
     }
 
     pub fn set_status(&self, new_status: ProcessStatus) {
@@ -438,6 +841,65 @@ impl Process {
             .mature_block_allocation_threshold_exceeded()
     }
 
+    /// Returns the approximate number of bytes allocated outside of the heap
+    /// since the last collection.
+    pub fn off_heap_bytes(&self, mature: bool) -> usize {
+        self.local_data().allocator.off_heap_bytes(mature)
+    }
+
+    /// Records a value growing in place outside of the heap, such as an array
+    /// that had to be resized.
+    ///
+    /// The growth is counted towards the generation (or mailbox) the object
+    /// resides in, just like the values of newly allocated objects.
+    pub fn track_off_heap_growth(&self,
+                                 pointer: ObjectPointer,
+                                 old_size: usize,
+                                 new_size: usize) {
+        if new_size <= old_size {
+            return;
+        }
+
+        let growth = new_size - old_size;
+        let local_data = self.local_data_mut();
+
+        if pointer.is_young() {
+            local_data.allocator.young_off_heap_bytes += growth;
+        } else if pointer.is_mature() {
+            local_data.allocator.mature_off_heap_bytes += growth;
+        } else if pointer.is_mailbox() {
+            let ref mut mailbox = local_data.mailbox;
+            let _lock = mailbox.write_lock.lock();
+
+            mailbox.allocator.off_heap_bytes += growth;
+        }
+    }
+
+    pub fn set_heap_limits(&self, limits: HeapLimits) {
+        self.local_data_mut().heap_limits = limits;
+    }
+
+    /// Returns true if the young or mature generation uses more blocks than
+    /// allowed, or if the process allocated blocks while the VM exceeded its
+    /// global block limit.
+    pub fn heap_limit_exceeded(&self) -> bool {
+        let local_data = self.local_data();
+        let ref allocator = local_data.allocator;
+
+        allocator.global_limit_exceeded ||
+        local_data.heap_limits
+            .heap_exceeded(allocator.number_of_young_blocks(),
+                           allocator.number_of_mature_blocks())
+    }
+
+    /// Returns true if the mailbox uses more blocks than allowed.
+    pub fn mailbox_limit_exceeded(&self) -> bool {
+        let local_data = self.local_data();
+
+        local_data.heap_limits
+            .mailbox_exceeded(local_data.mailbox.allocator.number_of_blocks())
+    }
+
     pub fn should_collect_mailbox(&self) -> bool {
         self.local_data()
             .mailbox
@@ -477,18 +939,108 @@ impl Process {
         self.local_data_mut().allocator.reclaim_blocks(mature);
     }
 
+    pub fn prepare_for_full_evacuation(&self, mature: bool) {
+        self.local_data_mut().allocator.prepare_for_full_evacuation(mature);
+    }
+
+    /// Returns the total number of garbage collections performed.
+    pub fn collections(&self) -> usize {
+        let local_data = self.local_data();
+
+        local_data.young_collections + local_data.mature_collections +
+        local_data.mailbox_collections
+    }
+
+    pub fn prepare_for_compaction(&self) {
+        self.local_data_mut().allocator.prepare_for_compaction();
+    }
+
+    pub fn reclaim_compacted_blocks(&self) {
+        let mut local_data = self.local_data_mut();
+
+        local_data.allocator.reclaim_compacted_blocks();
+
+        // Compacting promotes all young objects, so there are no more
+        // references from mature to young objects to keep track of.
+        local_data.remembered_set.clear();
+        local_data.mature_collections += 1;
+    }
+
+    /// Releases as much memory as possible, in preparation of the process
+    /// being idle for a while.
+    ///
+    /// The heap is compacted separately, this method only takes care of
+    /// releasing unused register and binding storage.
+    pub fn prepare_for_hibernation(&self) {
+        let mut local_data = self.local_data_mut();
+
+        local_data.context.shrink();
+        local_data.hibernations += 1;
+    }
+
+    /// Registers a method to run once the given object becomes unreachable.
+    ///
+    /// Registering a finalizer for an object that already has one replaces
+    /// the existing finalizer.
+    pub fn register_finalizer(&self,
+                              object: ObjectPointer,
+                              code: RcCompiledCode) {
+        let ref mut finalizers = self.local_data_mut().finalizers;
+
+        finalizers.retain(|finalizer| finalizer.object != object);
+        finalizers.push(Finalizer::new(object, code));
+    }
+
+    /// Enters the finalizers that have to run in this process.
+    ///
+    /// Every finalizer runs in a new execution context on top of the current
+    /// one, without storing its return value. Once all finalizers have
+    /// finished the current context resumes where it left off, so the
+    /// instruction index of this context must be stored before calling this
+    /// method.
+    pub fn enter_local_finalizers(&self) {
+        let finalizers = {
+            let ref mut queue = self.local_data_mut().local_finalizers;
+
+            mem::replace(queue, Vec::new())
+        };
+
+        for finalizer in finalizers {
+            let context = ExecutionContext::with_object(finalizer.object,
+                                                        finalizer.code.clone(),
+                                                        None);
+
+            self.push_context(context);
+            self.push_call_frame(CallFrame::from_code(finalizer.code));
+        }
+    }
+
+    /// Returns the number of messages in the mailbox.
+    ///
+    /// Unlike most other methods this method can be used by any thread.
+    pub fn mailbox_size(&self) -> usize {
+        self.local_data().mailbox.len()
+    }
+
+    /// Returns true if the mailbox contains any messages.
+    pub fn has_messages(&self) -> bool {
+        self.local_data().mailbox.len() > 0
+    }
+
     pub fn update_collection_statistics(&self, config: &Config, mature: bool) {
         let mut local_data = self.local_data_mut();
 
         local_data.allocator.increment_young_ages();
 
         local_data.allocator.young_block_allocations = 0;
+        local_data.allocator.young_off_heap_bytes = 0;
 
         local_data.allocator
             .increment_young_threshold(config.young_growth_factor);
 
         if mature {
             local_data.allocator.mature_block_allocations = 0;
+            local_data.allocator.mature_off_heap_bytes = 0;
 
             local_data.allocator
                 .increment_mature_threshold(config.mature_growth_factor);
@@ -504,12 +1056,148 @@ impl Process {
 
         local_data.mailbox_collections += 1;
         local_data.mailbox.allocator.block_allocations = 0;
+        local_data.mailbox.allocator.off_heap_bytes = 0;
 
         local_data.mailbox
             .allocator
             .increment_threshold(config.mailbox_growth_factor);
     }
 
+    /// Schedules the termination of this process using the given reason.
+    ///
+    /// The process is terminated the next time it reaches a safepoint. If the
+    /// process is already being terminated the original reason is retained.
+    pub fn terminate_with_reason(&self, reason: ExitReason) {
+        let mut exit_reason = lock!(self.exit_reason);
+
+        if !self.should_terminate() {
+            *exit_reason = reason;
+
+            self.terminate.store(true, Ordering::Release);
+        }
+    }
+
+    /// Kills the process at its next safepoint.
+    pub fn kill(&self) {
+        self.terminate_with_reason(ExitReason::Killed);
+    }
+
+    /// Returns true if the process should terminate at its next safepoint.
+    pub fn should_terminate(&self) -> bool {
+        self.terminate.load(Ordering::Acquire)
+    }
+
+    pub fn exit_reason(&self) -> ExitReason {
+        *lock!(self.exit_reason)
+    }
+
+    /// Marks the process as waiting for the given event.
+    ///
+    /// This should be called by the thread running the process, right before
+    /// suspending it. Waiting for the same event again while already waiting
+    /// has no effect.
+    ///
+    /// Returns true if the process was not yet waiting.
+    pub fn start_waiting(&self, reason: WaitReason) -> bool {
+        let mut waiting = lock!(self.waiting);
+
+        if waiting.is_some() {
+            return false;
+        }
+
+        let frame = &self.local_data().call_frame;
+
+        *waiting = Some(WaitInfo {
+            sequence: self.wait_count.fetch_add(1, Ordering::AcqRel),
+            reason: reason,
+            method: frame.name().clone(),
+            file: frame.file().clone(),
+            line: frame.line,
+        });
+
+        true
+    }
+
+    /// Marks the process as no longer waiting.
+    pub fn stop_waiting(&self) {
+        *lock!(self.waiting) = None;
+    }
+
+    /// Returns details about the event the process is waiting for, if any.
+    pub fn wait_info(&self) -> Option<WaitInfo> {
+        lock!(self.waiting).clone()
+    }
+
+    /// Returns the ID of the pool this process is currently scheduled on.
+    pub fn current_pool_id(&self) -> usize {
+        self.current_pool_id.load(Ordering::Acquire)
+    }
+
+    /// Moves the process to a different pool.
+    ///
+    /// The process is only scheduled on the new pool the next time it is
+    /// scheduled.
+    pub fn move_to_pool(&self, pool_id: usize) {
+        self.current_pool_id.store(pool_id, Ordering::Release);
+    }
+
+    /// Returns true if the process is scheduled on a pool other than its own.
+    pub fn is_moved(&self) -> bool {
+        self.current_pool_id() != self.pool_id
+    }
+
+    /// Returns a snapshot of various details of this process.
+    ///
+    /// The `current` argument should be set to true when this method is called
+    /// by the thread executing the process. When set to false the current call
+    /// frame and heap details are only included if the process is not in use
+    /// by another thread. The mailbox details are always included, as these
+    /// are synchronized.
+    pub fn info(&self, current: bool) -> ProcessInfo {
+        // Holding on to the status lock prevents the process from being
+        // resumed while we inspect its call frame.
+        let status = lock!(self.status);
+        let local_data = self.local_data();
+        let inspect = current || !status.is_in_use();
+
+        let (method, file, line) = if inspect {
+            let frame = &local_data.call_frame;
+
+            (Some(frame.name().clone()),
+             Some(frame.file().clone()),
+             Some(frame.line))
+        } else {
+            (None, None, None)
+        };
+
+        let heap = if inspect {
+            Some(HeapInfo {
+                young_collections: local_data.young_collections,
+                mature_collections: local_data.mature_collections,
+                hibernations: local_data.hibernations,
+                mailbox_collections: local_data.mailbox_collections,
+                young_blocks: local_data.allocator.number_of_young_blocks(),
+                mature_blocks: local_data.allocator.number_of_mature_blocks(),
+                mailbox_blocks: local_data.mailbox.number_of_blocks(),
+            })
+        } else {
+            None
+        };
+
+        ProcessInfo {
+            status: status.name(),
+            pool_id: self.pool_id,
+            mailbox_size: local_data.mailbox.len(),
+            mailbox_capacity: local_data.mailbox.capacity,
+            mailbox_dropped: local_data.mailbox.dropped(),
+            mailbox_blocked: local_data.mailbox.blocked(),
+            heap: heap,
+            method: method,
+            file: file,
+            line: line,
+        }
+    }
+
     pub fn is_main(&self) -> bool {
         self.pid == 0
     }
@@ -533,6 +1221,7 @@ impl Hash for Process {
 mod tests {
     use super::*;
     use config::Config;
+    use immix::block::Block;
     use immix::global_allocator::GlobalAllocator;
     use compiled_code::CompiledCode;
     use object_pointer::ObjectPointer;
@@ -555,6 +1244,111 @@ mod tests {
         assert_eq!(process.contexts().len(), 1);
     }
 
+    #[test]
+    fn test_info() {
+        let process = new_process();
+        let info = process.info(false);
+
+        assert_eq!(info.status, "scheduled");
+        assert_eq!(info.pool_id, 0);
+        assert_eq!(info.mailbox_size, 0);
+        assert_eq!(info.heap.as_ref().unwrap().young_collections, 0);
+        assert_eq!(info.heap.as_ref().unwrap().young_blocks, 0);
+        assert_eq!(info.method, Some("a".to_string()));
+        assert_eq!(info.line, Some(1));
+    }
+
+    #[test]
+    fn test_info_running_process() {
+        let process = new_process();
+
+        process.running();
+
+        assert!(process.info(false).method.is_none());
+        assert!(process.info(false).heap.is_none());
+        assert!(process.info(true).method.is_some());
+        assert!(process.info(true).heap.is_some());
+    }
+
+    #[test]
+    fn test_channel_send_and_receive() {
+        let process = new_process();
+        let channel = process.create_channel(None);
+        let message =
+            process.allocate_without_prototype(object_value::integer(5));
+
+        assert_eq!(process.send_to_channel(&channel, message), SendResult::Sent);
+
+        let received = process.receive_from_channel(&channel).unwrap();
+
+        assert!(received != message);
+        assert_eq!(received.get().value.as_integer().unwrap(), 5);
+        assert!(process.receive_from_channel(&channel).is_none());
+    }
+
+    #[test]
+    fn test_allocate_integer() {
+        let process = new_process();
+        let proto = process.allocate_empty();
+        let small = process.allocate_integer(5, proto);
+        let large = process.allocate_integer(i64::max_value(), proto);
+
+        assert!(small.is_tagged_integer());
+        assert_eq!(small.integer_value().unwrap(), 5);
+
+        assert_eq!(large.is_tagged_integer(), false);
+        assert!(large.get().prototype().unwrap() == proto);
+        assert_eq!(large.integer_value().unwrap(), i64::max_value());
+    }
+
+    #[test]
+    fn test_allocate_weak_reference() {
+        let process = new_process();
+        let proto = process.allocate_empty();
+        let target = process.allocate_empty();
+        let weak = process.allocate_weak_reference(target, proto);
+
+        assert!(weak.get().value.as_weak_reference().unwrap() == target);
+        assert!(process.local_data().allocator.weak_references == vec![weak]);
+    }
+
+    #[test]
+    fn test_kill() {
+        let process = new_process();
+
+        assert_eq!(process.should_terminate(), false);
+        assert_eq!(process.exit_reason(), ExitReason::Normal);
+
+        process.kill();
+
+        assert!(process.should_terminate());
+        assert_eq!(process.exit_reason(), ExitReason::Killed);
+    }
+
+    #[test]
+    fn test_terminate_with_reason_retains_first_reason() {
+        let process = new_process();
+
+        process.terminate_with_reason(ExitReason::Exit(2));
+        process.kill();
+
+        assert_eq!(process.exit_reason(), ExitReason::Exit(2));
+    }
+
+    #[test]
+    fn test_move_to_pool() {
+        let process = new_process();
+
+        assert_eq!(process.current_pool_id(), 0);
+        assert_eq!(process.is_moved(), false);
+
+        process.move_to_pool(1);
+
+        assert_eq!(process.current_pool_id(), 1);
+        assert_eq!(process.pool_id, 0);
+        assert!(process.is_moved());
+    }
+
     #[test]
     fn test_update_collection_statistics_without_mature() {
         let process = new_process();
@@ -564,6 +1358,7 @@ mod tests {
             process.local_data().allocator.young_block_allocation_threshold;
 
         process.local_data_mut().allocator.young_block_allocations = 1;
+        process.local_data_mut().allocator.young_off_heap_bytes = 1;
 
         process.update_collection_statistics(&config, false);
 
@@ -571,6 +1366,7 @@ mod tests {
         let ref allocator = local_data.allocator;
 
         assert_eq!(allocator.young_block_allocations, 0);
+        assert_eq!(allocator.young_off_heap_bytes, 0);
         assert_eq!(local_data.young_collections, 1);
 
         assert!(allocator.young_block_allocation_threshold > old_threshold);
@@ -586,6 +1382,7 @@ mod tests {
 
             local_data.allocator.young_block_allocations = 1;
             local_data.allocator.mature_block_allocations = 1;
+            local_data.allocator.mature_off_heap_bytes = 1;
         }
 
         let old_young_threshold =
@@ -601,6 +1398,7 @@ mod tests {
 
         assert_eq!(allocator.young_block_allocations, 0);
         assert_eq!(allocator.mature_block_allocations, 0);
+        assert_eq!(allocator.mature_off_heap_bytes, 0);
 
         assert_eq!(local_data.young_collections, 0);
         assert_eq!(local_data.mature_collections, 1);
@@ -620,6 +1418,7 @@ mod tests {
             let mut local_data = process.local_data_mut();
 
             local_data.mailbox.allocator.block_allocations = 1;
+            local_data.mailbox.allocator.off_heap_bytes = 1;
         }
 
         let old_threshold =
@@ -632,7 +1431,255 @@ mod tests {
 
         assert_eq!(local_data.mailbox_collections, 1);
         assert_eq!(mailbox.allocator.block_allocations, 0);
+        assert_eq!(mailbox.allocator.off_heap_bytes, 0);
 
         assert!(mailbox.allocator.block_allocation_threshold > old_threshold);
     }
+
+    #[test]
+    fn test_reclaim_compacted_blocks() {
+        let process = new_process();
+        let pointer = process.allocate_empty();
+
+        process.local_data_mut().remembered_set.insert(pointer);
+        process.prepare_for_compaction();
+        process.reclaim_compacted_blocks();
+
+        assert_eq!(process.has_remembered_objects(), false);
+        assert_eq!(process.local_data().mature_collections, 1);
+    }
+
+    #[test]
+    fn test_prepare_for_hibernation() {
+        let process = new_process();
+
+        process.set_register(0, process.allocate_empty());
+        process.prepare_for_hibernation();
+
+        assert_eq!(process.context().register.values.capacity(), 1);
+        assert_eq!(process.local_data().hibernations, 1);
+    }
+
+    #[test]
+    fn test_has_messages() {
+        let process = new_process();
+        let message = process.allocate_empty();
+
+        assert_eq!(process.has_messages(), false);
+
+        process.send_message(&process, message);
+
+        assert!(process.has_messages());
+    }
+
+    #[test]
+    fn test_heap_limit_exceeded() {
+        let process = new_process();
+        let mut limits = HeapLimits::unlimited();
+
+        process.allocate_empty();
+
+        assert_eq!(process.heap_limit_exceeded(), false);
+
+        limits.young = 1;
+        process.set_heap_limits(limits);
+
+        assert_eq!(process.heap_limit_exceeded(), false);
+
+        limits.young = 0;
+        limits.mature = 1;
+        process.set_heap_limits(limits);
+
+        process.local_data_mut()
+            .allocator
+            .mature_generation
+            .add_block(Block::new());
+
+        process.local_data_mut()
+            .allocator
+            .mature_generation
+            .add_block(Block::new());
+
+        assert!(process.heap_limit_exceeded());
+    }
+
+    #[test]
+    fn test_heap_limit_exceeded_with_global_limit() {
+        let process = new_process();
+
+        process.local_data_mut().allocator.global_limit_exceeded = true;
+
+        assert!(process.heap_limit_exceeded());
+    }
+
+    #[test]
+    fn test_mailbox_limit_exceeded() {
+        let process = new_process();
+        let mut limits = HeapLimits::unlimited();
+
+        limits.mailbox = 1;
+        process.set_heap_limits(limits);
+
+        process.local_data_mut().mailbox.allocator.bucket.add_block(Block::new());
+
+        assert_eq!(process.mailbox_limit_exceeded(), false);
+
+        process.local_data_mut().mailbox.allocator.bucket.add_block(Block::new());
+
+        assert!(process.mailbox_limit_exceeded());
+    }
+
+    #[test]
+    fn test_register_finalizer() {
+        let process = new_process();
+        let pointer = process.allocate_empty();
+        let code = process.compiled_code();
+
+        process.register_finalizer(pointer, code.clone());
+        process.register_finalizer(pointer, code);
+
+        assert_eq!(process.local_data().finalizers.len(), 1);
+    }
+
+    #[test]
+    fn test_enter_local_finalizers() {
+        let process = new_process();
+        let pointer = process.allocate_empty();
+        let code = process.compiled_code();
+
+        process.local_data_mut()
+            .local_finalizers
+            .push(Finalizer::new(pointer, code));
+
+        process.enter_local_finalizers();
+
+        assert!(process.local_data().local_finalizers.is_empty());
+        assert!(process.self_object() == pointer);
+        assert_eq!(process.context().return_register, None);
+        assert!(!process.at_top_level());
+    }
+
+    #[test]
+    fn test_suspend_for_snapshot() {
+        let process = new_process();
+        let status = process.suspend_for_snapshot().unwrap();
+
+        assert_eq!(status.name(), "scheduled");
+        assert!(process.suspend_for_snapshot().is_none());
+        assert!(!process.available_for_execution());
+
+        process.resume_after_snapshot(status);
+
+        assert!(process.available_for_execution());
+    }
+
+    #[test]
+    fn test_suspend_for_snapshot_while_running() {
+        let process = new_process();
+
+        process.running();
+
+        assert!(process.suspend_for_snapshot().is_none());
+    }
+
+    #[test]
+    fn test_start_and_stop_waiting() {
+        let process = new_process();
+
+        assert!(process.wait_info().is_none());
+
+        process.start_waiting(WaitReason::Message);
+        process.start_waiting(WaitReason::Message);
+
+        let info = process.wait_info().unwrap();
+
+        assert_eq!(info.sequence, 0);
+        assert_eq!(info.method, "a".to_string());
+
+        process.stop_waiting();
+        process.start_waiting(WaitReason::Message);
+
+        assert!(process.wait_info().unwrap().sequence != info.sequence);
+
+        process.stop_waiting();
+
+        assert!(process.wait_info().is_none());
+    }
+
+    #[test]
+    fn test_wait_info_is_ready_for_message() {
+        let process = new_process();
+        let message = process.allocate_empty();
+
+        process.start_waiting(WaitReason::Message);
+
+        let info = process.wait_info().unwrap();
+
+        assert_eq!(info.is_ready(&process), false);
+
+        process.send_message(&process, message);
+
+        assert!(info.is_ready(&process));
+    }
+
+    #[test]
+    fn test_wait_info_is_ready_for_channel() {
+        let process = new_process();
+        let channel = process.create_channel(Some(1));
+        let message = process.allocate_empty();
+
+        process.start_waiting(WaitReason::ChannelMessage(channel.clone()));
+
+        let receive = process.wait_info().unwrap();
+
+        process.stop_waiting();
+        process.send_to_channel(&channel, message);
+        process.start_waiting(WaitReason::ChannelSpace(channel.clone()));
+
+        let send = process.wait_info().unwrap();
+
+        assert!(receive.is_ready(&process));
+        assert_eq!(send.is_ready(&process), false);
+
+        channel.close();
+
+        assert!(send.is_ready(&process));
+    }
+
+    #[test]
+    fn test_send_message_with_mailbox_capacity() {
+        let sender = new_process();
+        let receiver = new_process();
+        let message = sender.allocate_empty();
+
+        receiver.set_mailbox_capacity(1);
+
+        assert!(receiver.send_message(&sender, message));
+        assert!(receiver.mailbox_is_full());
+        assert_eq!(receiver.send_message(&sender, message), false);
+
+        // Messages sent to the process itself ignore the capacity.
+        let local = receiver.allocate_empty();
+
+        assert!(receiver.send_message(&receiver, local));
+    }
+
+    #[test]
+    fn test_wait_info_is_ready_for_mailbox_space() {
+        let sender = new_process();
+        let receiver = new_process();
+        let message = sender.allocate_empty();
+
+        receiver.set_mailbox_capacity(1);
+        receiver.send_message(&sender, message);
+        sender.start_waiting(WaitReason::MailboxSpace(receiver.clone()));
+
+        let info = sender.wait_info().unwrap();
+
+        assert_eq!(info.is_ready(&sender), false);
+
+        receiver.receive_message();
+
+        assert!(info.is_ready(&sender));
+    }
 }
@@ -0,0 +1,197 @@
+//! Named groups of processes.
+//!
+//! A process group is a named collection of PIDs, used for sending the same
+//! message to many processes at once (e.g. notifying all subscribers of an
+//! event). A process can be a member of any number of groups, and is removed
+//! from all its groups once it finishes.
+//!
+//! For example:
+//!
+//!     let mut groups = ProcessGroups::new();
+//!
+//!     groups.create("subscribers".to_string());
+//!     groups.join("subscribers", pid);
+//!     groups.members("subscribers"); // => Some(vec![pid])
+
+use std::collections::HashMap;
+
+use process_table::PID;
+
+pub struct ProcessGroups {
+    /// The groups and their members, in the order they joined.
+    groups: HashMap<String, Vec<PID>>,
+
+    /// The groups every PID is a member of, used for removing a process from
+    /// all its groups once it finishes.
+    pids: HashMap<PID, Vec<String>>,
+}
+
+impl ProcessGroups {
+    pub fn new() -> Self {
+        ProcessGroups {
+            groups: HashMap::new(),
+            pids: HashMap::new(),
+        }
+    }
+
+    /// Creates a new, empty group.
+    ///
+    /// Returns false if the group already exists.
+    pub fn create(&mut self, name: String) -> bool {
+        if self.groups.contains_key(&name) {
+            return false;
+        }
+
+        self.groups.insert(name, Vec::new());
+
+        true
+    }
+
+    /// Adds a PID to a group.
+    ///
+    /// Returns false if the group does not exist. Joining a group the PID is
+    /// already a member of has no effect.
+    pub fn join(&mut self, name: &str, pid: PID) -> bool {
+        let members = if let Some(members) = self.groups.get_mut(name) {
+            members
+        } else {
+            return false;
+        };
+
+        if !members.contains(&pid) {
+            members.push(pid);
+
+            self.pids.entry(pid).or_insert_with(Vec::new).push(name.to_string());
+        }
+
+        true
+    }
+
+    /// Removes a PID from a group.
+    ///
+    /// Returns false if the PID was not a member of the group.
+    pub fn leave(&mut self, name: &str, pid: &PID) -> bool {
+        let left = if let Some(members) = self.groups.get_mut(name) {
+            let before = members.len();
+
+            members.retain(|member| member != pid);
+            members.len() < before
+        } else {
+            false
+        };
+
+        if left {
+            let remove_pid = if let Some(names) = self.pids.get_mut(pid) {
+                names.retain(|joined| joined != name);
+                names.is_empty()
+            } else {
+                false
+            };
+
+            if remove_pid {
+                self.pids.remove(pid);
+            }
+        }
+
+        left
+    }
+
+    /// Removes a PID from all the groups it is a member of.
+    pub fn leave_all(&mut self, pid: &PID) {
+        if let Some(names) = self.pids.remove(pid) {
+            for name in names {
+                if let Some(members) = self.groups.get_mut(&name) {
+                    members.retain(|member| member != pid);
+                }
+            }
+        }
+    }
+
+    /// Returns the members of a group.
+    pub fn members(&self, name: &str) -> Option<Vec<PID>> {
+        self.groups.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let groups = ProcessGroups::new();
+
+        assert_eq!(groups.groups.len(), 0);
+        assert_eq!(groups.pids.len(), 0);
+    }
+
+    #[test]
+    fn test_create() {
+        let mut groups = ProcessGroups::new();
+
+        assert!(groups.create("a".to_string()));
+        assert_eq!(groups.create("a".to_string()), false);
+
+        assert_eq!(groups.members("a"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_join() {
+        let mut groups = ProcessGroups::new();
+
+        groups.create("a".to_string());
+
+        assert!(groups.join("a", 1));
+        assert!(groups.join("a", 2));
+        assert!(groups.join("a", 1));
+
+        assert_eq!(groups.members("a"), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_join_unknown_group() {
+        let mut groups = ProcessGroups::new();
+
+        assert_eq!(groups.join("a", 1), false);
+        assert_eq!(groups.pids.len(), 0);
+    }
+
+    #[test]
+    fn test_leave() {
+        let mut groups = ProcessGroups::new();
+
+        groups.create("a".to_string());
+        groups.join("a", 1);
+
+        assert!(groups.leave("a", &1));
+        assert_eq!(groups.leave("a", &1), false);
+        assert_eq!(groups.leave("b", &1), false);
+
+        assert_eq!(groups.members("a"), Some(Vec::new()));
+        assert_eq!(groups.pids.len(), 0);
+    }
+
+    #[test]
+    fn test_leave_all() {
+        let mut groups = ProcessGroups::new();
+
+        groups.create("a".to_string());
+        groups.create("b".to_string());
+        groups.join("a", 1);
+        groups.join("b", 1);
+        groups.join("b", 2);
+
+        groups.leave_all(&1);
+
+        assert_eq!(groups.members("a"), Some(Vec::new()));
+        assert_eq!(groups.members("b"), Some(vec![2]));
+        assert_eq!(groups.pids.len(), 1);
+    }
+
+    #[test]
+    fn test_members_unknown_group() {
+        let groups = ProcessGroups::new();
+
+        assert!(groups.members("a").is_none());
+    }
+}
@@ -0,0 +1,176 @@
+//! Registry for mapping names to PIDs.
+//!
+//! A ProcessRegistry can be used to give a process a name, allowing other
+//! processes to address it without having to pass its PID around. A name can
+//! only be used by a single process at a time, but a single process can be
+//! registered under multiple names.
+//!
+//! For example:
+//!
+//!     let mut registry = ProcessRegistry::new();
+//!
+//!     registry.register("logger".to_string(), pid);
+//!     registry.lookup("logger"); // => Some(pid)
+
+use std::collections::HashMap;
+
+use process_table::PID;
+
+pub struct ProcessRegistry {
+    /// The registered names and the PIDs they map to.
+    names: HashMap<String, PID>,
+
+    /// The names registered for every PID, used for removing all names of a
+    /// process once it finishes.
+    pids: HashMap<PID, Vec<String>>,
+}
+
+impl ProcessRegistry {
+    pub fn new() -> Self {
+        ProcessRegistry {
+            names: HashMap::new(),
+            pids: HashMap::new(),
+        }
+    }
+
+    /// Registers a name for the given PID.
+    ///
+    /// Returns false if the name is already in use.
+    pub fn register(&mut self, name: String, pid: PID) -> bool {
+        if self.names.contains_key(&name) {
+            return false;
+        }
+
+        self.pids.entry(pid).or_insert_with(Vec::new).push(name.clone());
+        self.names.insert(name, pid);
+
+        true
+    }
+
+    /// Removes a registered name.
+    ///
+    /// Returns the PID the name was mapped to, if any.
+    pub fn unregister(&mut self, name: &str) -> Option<PID> {
+        if let Some(pid) = self.names.remove(name) {
+            let remove_pid = if let Some(names) = self.pids.get_mut(&pid) {
+                names.retain(|registered| registered != name);
+                names.is_empty()
+            } else {
+                false
+            };
+
+            if remove_pid {
+                self.pids.remove(&pid);
+            }
+
+            Some(pid)
+        } else {
+            None
+        }
+    }
+
+    /// Removes all the names registered for the given PID.
+    pub fn unregister_pid(&mut self, pid: &PID) {
+        if let Some(names) = self.pids.remove(pid) {
+            for name in names {
+                self.names.remove(&name);
+            }
+        }
+    }
+
+    /// Returns the PID registered under the given name.
+    pub fn lookup(&self, name: &str) -> Option<PID> {
+        self.names.get(name).cloned()
+    }
+
+    /// Returns true if the given PID has one or more names.
+    pub fn is_registered(&self, pid: &PID) -> bool {
+        self.pids.contains_key(pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let registry = ProcessRegistry::new();
+
+        assert_eq!(registry.names.len(), 0);
+        assert_eq!(registry.pids.len(), 0);
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = ProcessRegistry::new();
+
+        assert!(registry.register("a".to_string(), 1));
+        assert!(registry.register("b".to_string(), 1));
+
+        assert_eq!(registry.lookup("a"), Some(1));
+        assert_eq!(registry.lookup("b"), Some(1));
+    }
+
+    #[test]
+    fn test_register_duplicate_name() {
+        let mut registry = ProcessRegistry::new();
+
+        assert!(registry.register("a".to_string(), 1));
+        assert_eq!(registry.register("a".to_string(), 2), false);
+
+        assert_eq!(registry.lookup("a"), Some(1));
+    }
+
+    #[test]
+    fn test_unregister() {
+        let mut registry = ProcessRegistry::new();
+
+        registry.register("a".to_string(), 1);
+
+        assert_eq!(registry.unregister("a"), Some(1));
+        assert_eq!(registry.unregister("a"), None);
+
+        assert!(registry.lookup("a").is_none());
+        assert_eq!(registry.is_registered(&1), false);
+    }
+
+    #[test]
+    fn test_unregister_keeps_other_names() {
+        let mut registry = ProcessRegistry::new();
+
+        registry.register("a".to_string(), 1);
+        registry.register("b".to_string(), 1);
+        registry.unregister("a");
+
+        assert!(registry.is_registered(&1));
+        assert_eq!(registry.lookup("b"), Some(1));
+    }
+
+    #[test]
+    fn test_unregister_pid() {
+        let mut registry = ProcessRegistry::new();
+
+        registry.register("a".to_string(), 1);
+        registry.register("b".to_string(), 1);
+        registry.register("c".to_string(), 2);
+
+        registry.unregister_pid(&1);
+
+        assert!(registry.lookup("a").is_none());
+        assert!(registry.lookup("b").is_none());
+        assert_eq!(registry.lookup("c"), Some(2));
+        assert_eq!(registry.is_registered(&1), false);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut registry = ProcessRegistry::new();
+
+        assert!(registry.lookup("a").is_none());
+
+        registry.register("a".to_string(), 1);
+
+        assert_eq!(registry.lookup("a"), Some(1));
+    }
+}
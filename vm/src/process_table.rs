@@ -20,46 +20,94 @@
 //!
 //! ## Recycling
 //!
-//! PIDs are recycled once a certain number of PIDs has been used. The exact
-//! amount of PIDs that can be used before recycling depends on the system
-//! architecture. In most cases PID recycling will only occur very rarely, if
-//! ever at all.
+//! A PID consists of two parts: the index of a slot in the table, and the
+//! generation of said slot. Slots are reused once a process is released, but
+//! every time a slot is released its generation is incremented. This means
+//! that a PID of a process that no longer exists will never refer to a newer
+//! process using the same slot, at least not until the generation of a slot
+//! wraps around.
+//!
+//! Looking up a PID that refers to a released slot or an older generation
+//! simply produces a None.
 //!
 //! ## PID Availability
 //!
 //! It's possible (though very unlikely) for a ProcessTable to run out of
-//! available PIDs. This can happen when many processes are added and kept
+//! available slots. This can happen when many processes are added and kept
 //! around. Callers should ensure they can handle such a scenario.
 
-use std::usize;
-use std::collections::HashMap;
+use std::collections::VecDeque;
 
 /// The type of a PID.
 pub type PID = usize;
 
-/// The maximum PID value.
-pub const MAX_PID: PID = usize::MAX;
+/// The number of bits of a PID used for storing the slot index.
+#[cfg(target_pointer_width = "64")]
+const INDEX_BITS: usize = 32;
 
-pub struct ProcessTable<T: Clone> {
-    /// The PID to use for the next process.
-    next_pid: PID,
+#[cfg(not(target_pointer_width = "64"))]
+const INDEX_BITS: usize = 16;
+
+/// The mask to apply to a PID to obtain its slot index.
+const INDEX_MASK: usize = (1 << INDEX_BITS) - 1;
+
+/// The maximum generation of a slot.
+///
+/// The most significant bit is never used so PIDs can be converted to signed
+/// integers without becoming negative.
+const MAX_GENERATION: usize = (1 << (INDEX_BITS - 1)) - 1;
+
+/// The maximum number of slots (and thus processes) a table can contain.
+pub const MAX_SLOTS: usize = INDEX_MASK + 1;
+
+/// Returns the slot index of a PID.
+pub fn pid_index(pid: PID) -> usize {
+    pid & INDEX_MASK
+}
 
-    /// When set to true, previously used PIDs may be recycled.
-    recycle: bool,
+/// Returns the generation of a PID.
+pub fn pid_generation(pid: PID) -> usize {
+    pid >> INDEX_BITS
+}
+
+fn make_pid(index: usize, generation: usize) -> PID {
+    (generation << INDEX_BITS) | index
+}
+
+struct Slot<T: Clone> {
+    /// The current generation of this slot.
+    generation: usize,
 
-    /// PIDs of existing processes, and their corresponding processes.
+    /// Set to true when the slot has been reserved.
+    reserved: bool,
+
+    /// The process stored in this slot, if any.
     ///
-    /// An entry's value may be set to None, indicating that the PID has been
+    /// This may be None for a reserved slot, indicating that the PID has been
     /// reserved but a process has yet to be inserted.
-    processes: HashMap<PID, Option<T>>,
+    process: Option<T>,
+}
+
+pub struct ProcessTable<T: Clone> {
+    /// All the slots of this table, both used and unused.
+    slots: Vec<Slot<T>>,
+
+    /// The indexes of slots that can be reused.
+    ///
+    /// Slots are reused in the order they are released, spreading generation
+    /// increments across slots.
+    free: VecDeque<usize>,
+
+    /// The number of reserved slots.
+    reserved: usize,
 }
 
 impl<T: Clone> ProcessTable<T> {
     pub fn new() -> Self {
         ProcessTable {
-            next_pid: 0,
-            recycle: false,
-            processes: HashMap::new(),
+            slots: Vec::new(),
+            free: VecDeque::new(),
+            reserved: 0,
         }
     }
 
@@ -67,56 +115,113 @@ impl<T: Clone> ProcessTable<T> {
     ///
     /// If no PID could be reserved a None value is returned.
     pub fn reserve(&mut self) -> Option<PID> {
-        let available = self.processes.len() < MAX_PID;
-
-        while available {
-            let pid = self.next_pid();
-
-            if self.recycle && self.processes.contains_key(&pid) {
-                continue;
-            }
+        let index = if let Some(index) = self.free.pop_front() {
+            index
+        } else if self.slots.len() < MAX_SLOTS {
+            self.slots.push(Slot {
+                generation: 0,
+                reserved: false,
+                process: None,
+            });
+
+            self.slots.len() - 1
+        } else {
+            return None;
+        };
 
-            self.processes.insert(pid, None);
+        let slot = &mut self.slots[index];
 
-            return Some(pid);
-        }
+        slot.reserved = true;
+        self.reserved += 1;
 
-        None
+        Some(make_pid(index, slot.generation))
     }
 
     /// Maps a process to the given PID.
+    ///
+    /// Nothing happens if the PID is not reserved.
     pub fn map(&mut self, pid: PID, process: T) {
-        self.processes.insert(pid, Some(process));
+        if let Some(slot) = self.slot_mut(pid) {
+            slot.process = Some(process);
+        }
     }
 
     /// Releases a PID.
+    ///
+    /// Releasing a PID that is not (or no longer) reserved has no effect.
     pub fn release(&mut self, pid: &PID) {
-        self.processes.remove(pid);
+        let released = if let Some(slot) = self.slot_mut(*pid) {
+            slot.reserved = false;
+            slot.process = None;
+
+            slot.generation = if slot.generation == MAX_GENERATION {
+                0
+            } else {
+                slot.generation + 1
+            };
+
+            true
+        } else {
+            false
+        };
+
+        if released {
+            self.reserved -= 1;
+            self.free.push_back(pid_index(*pid));
+        }
     }
 
     /// Returns the process for a given PID.
     pub fn get(&self, pid: &PID) -> Option<T> {
-        if let Some(slot) = self.processes.get(pid) {
-            match slot {
-                &Some(ref process) => Some(process.clone()),
-                &None => None,
-            }
+        if let Some(slot) = self.slot(*pid) {
+            slot.process.clone()
         } else {
             None
         }
     }
 
-    fn next_pid(&mut self) -> PID {
-        let pid = self.next_pid;
+    /// Returns true if the PID refers to a reserved slot of the current
+    /// generation.
+    pub fn contains(&self, pid: &PID) -> bool {
+        self.slot(*pid).is_some()
+    }
 
-        if pid == MAX_PID {
-            self.next_pid = 0;
-            self.recycle = true;
-        } else {
-            self.next_pid += 1;
+    /// Returns the number of reserved PIDs.
+    pub fn len(&self) -> usize {
+        self.reserved
+    }
+
+    /// Returns the PIDs of all processes stored in the table.
+    ///
+    /// PIDs that have been reserved but not yet mapped to a process are not
+    /// included.
+    pub fn pids(&self) -> Vec<PID> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|&(_, slot)| slot.reserved && slot.process.is_some())
+            .map(|(index, slot)| make_pid(index, slot.generation))
+            .collect()
+    }
+
+    fn slot(&self, pid: PID) -> Option<&Slot<T>> {
+        if let Some(slot) = self.slots.get(pid_index(pid)) {
+            if slot.reserved && slot.generation == pid_generation(pid) {
+                return Some(slot);
+            }
         }
 
-        pid
+        None
+    }
+
+    fn slot_mut(&mut self, pid: PID) -> Option<&mut Slot<T>> {
+        if let Some(slot) = self.slots.get_mut(pid_index(pid)) {
+            if slot.reserved && slot.generation == pid_generation(pid) {
+                return Some(slot);
+            }
+        }
+
+        None
     }
 }
 
@@ -128,9 +233,9 @@ mod tests {
     fn test_new() {
         let table = ProcessTable::<()>::new();
 
-        assert_eq!(table.next_pid, 0);
-        assert_eq!(table.recycle, false);
-        assert_eq!(table.processes.len(), 0);
+        assert_eq!(table.slots.len(), 0);
+        assert_eq!(table.free.len(), 0);
+        assert_eq!(table.len(), 0);
     }
 
     #[test]
@@ -145,19 +250,22 @@ mod tests {
 
         assert!(pid2.is_some());
         assert_eq!(pid2.unwrap(), 1);
+        assert_eq!(table.len(), 2);
     }
 
     #[test]
     fn test_reserve_with_recycle() {
         let mut table = ProcessTable::<()>::new();
+        let pid1 = table.reserve().unwrap();
 
-        table.reserve();
-        table.next_pid = 0;
-        table.recycle = true;
+        table.release(&pid1);
 
-        let pid2 = table.reserve();
+        let pid2 = table.reserve().unwrap();
 
-        assert_eq!(pid2.unwrap(), 1);
+        assert!(pid1 != pid2);
+        assert_eq!(pid_index(pid1), pid_index(pid2));
+        assert_eq!(pid_generation(pid2), pid_generation(pid1) + 1);
+        assert_eq!(table.slots.len(), 1);
     }
 
     #[test]
@@ -170,6 +278,17 @@ mod tests {
         assert_eq!(table.get(&pid).unwrap(), 10);
     }
 
+    #[test]
+    fn test_map_released_pid() {
+        let mut table = ProcessTable::new();
+        let pid = table.reserve().unwrap();
+
+        table.release(&pid);
+        table.map(pid, 10);
+
+        assert!(table.get(&pid).is_none());
+    }
+
     #[test]
     fn test_release() {
         let mut table = ProcessTable::new();
@@ -179,6 +298,19 @@ mod tests {
         table.release(&pid);
 
         assert!(table.get(&pid).is_none());
+        assert_eq!(table.len(), 0);
+    }
+
+    #[test]
+    fn test_release_twice() {
+        let mut table = ProcessTable::<()>::new();
+        let pid = table.reserve().unwrap();
+
+        table.release(&pid);
+        table.release(&pid);
+
+        assert_eq!(table.len(), 0);
+        assert_eq!(table.free.len(), 1);
     }
 
     #[test]
@@ -196,4 +328,68 @@ mod tests {
         assert!(table.get(&pid).is_some());
         assert_eq!(table.get(&pid).unwrap(), 10);
     }
+
+    #[test]
+    fn test_get_stale_pid() {
+        let mut table = ProcessTable::new();
+        let old_pid = table.reserve().unwrap();
+
+        table.map(old_pid, 10);
+        table.release(&old_pid);
+
+        let new_pid = table.reserve().unwrap();
+
+        table.map(new_pid, 20);
+
+        assert!(table.get(&old_pid).is_none());
+        assert_eq!(table.get(&new_pid).unwrap(), 20);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut table = ProcessTable::<()>::new();
+        let pid = table.reserve().unwrap();
+
+        assert!(table.contains(&pid));
+
+        table.release(&pid);
+
+        assert_eq!(table.contains(&pid), false);
+    }
+
+    #[test]
+    fn test_generation_wraps_around() {
+        let mut table = ProcessTable::<()>::new();
+        let pid = table.reserve().unwrap();
+
+        table.slots[0].generation = MAX_GENERATION;
+
+        table.release(&make_pid(0, MAX_GENERATION));
+
+        assert_eq!(table.reserve(), Some(pid));
+    }
+
+    #[test]
+    fn test_pid_fits_in_signed_integer() {
+        let pid = make_pid(INDEX_MASK, MAX_GENERATION);
+
+        assert!(pid as i64 >= 0);
+        assert_eq!(pid_index(pid), INDEX_MASK);
+        assert_eq!(pid_generation(pid), MAX_GENERATION);
+    }
+
+    #[test]
+    fn test_pids() {
+        let mut table = ProcessTable::new();
+        let pid1 = table.reserve().unwrap();
+        let pid2 = table.reserve().unwrap();
+
+        table.map(pid1, 10);
+
+        assert_eq!(table.pids(), vec![pid1]);
+
+        table.map(pid2, 20);
+
+        assert_eq!(table.pids(), vec![pid1, pid2]);
+    }
 }
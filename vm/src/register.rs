@@ -45,6 +45,15 @@ impl Register {
         None
     }
 
+    /// Releases the memory of any unused registers at the end.
+    pub fn shrink(&mut self) {
+        while self.values.last().map_or(false, |value| value.is_null()) {
+            self.values.pop();
+        }
+
+        self.values.shrink_to_fit();
+    }
+
     /// Pushes all pointers in this register into the supplied vector.
     pub fn push_pointers(&self, pointers: &mut Vec<ObjectPointerPointer>) {
         for pointer in self.pointers() {
@@ -99,6 +108,21 @@ mod tests {
         assert!(register.get(3).is_none());
     }
 
+    #[test]
+    fn test_shrink() {
+        let mut register = Register::new();
+        let pointer = ObjectPointer::new(0x4 as RawObjectPointer);
+
+        register.set(0, pointer);
+        register.set(2, pointer);
+        register.set(2, ObjectPointer::null());
+        register.shrink();
+
+        assert_eq!(register.values.len(), 1);
+        assert_eq!(register.values.capacity(), 1);
+        assert!(register.get(0).unwrap() == pointer);
+    }
+
     #[test]
     fn test_push_pointers() {
         let mut register = Register::new();
@@ -0,0 +1,893 @@
+//! Serialization of object graphs.
+//!
+//! Objects sent to a process running in a different VM instance can't be
+//! copied directly into the receiving mailbox. Instead the object graph is
+//! serialized into a sequence of bytes, which the receiving VM deserializes
+//! into the mailbox of the receiving process.
+//!
+//! Every regular object is serialized only once. Objects are numbered in the
+//! order they are written, and an object that is encountered again is written
+//! as a reference to this number. This means that objects referred to multiple
+//! times are still shared after deserializing, and cyclic object graphs can be
+//! serialized.
+//!
+//! Permanent objects are not copied. Instead a permanent object is serialized
+//! as an index into a list of permanent objects that exist in every VM (e.g.
+//! the prototype for integers). Other permanent objects, such as the prototypes
+//! of user defined objects, are serialized as a path of attribute and constant
+//! names starting at one of these objects. The receiving VM looks up the object
+//! using this path, meaning it must define the same objects under the same
+//! names. Permanent objects that can't be reached this way, objects with
+//! methods, and values such as files, bindings, and channels can not be
+//! serialized.
+//!
+//! All integers are stored in big-endian order. A serialized object graph
+//! looks as follows:
+//!
+//!     VERSION (u8)
+//!     OBJECT
+//!
+//! Here OBJECT is either a permanent object:
+//!
+//!     PERMANENT (u8)
+//!     INDEX (u64)
+//!
+//! A permanent object reached from one of the permanent objects in the list:
+//!
+//!     PERMANENT_PATH (u8)
+//!     INDEX (u64)
+//!     STEPS (u64, followed by a u8 step type and a name for every step)
+//!
+//! A tagged integer:
+//!
+//!     TAGGED_INTEGER (u8)
+//!     VALUE (u64)
+//!
+//! A reference to a regular object that was written before:
+//!
+//!     REFERENCE (u8)
+//!     INDEX (u64)
+//!
+//! Or a regular object:
+//!
+//!     REGULAR (u8)
+//!     PROTOTYPE (u8 set to 0 or 1, followed by an OBJECT if set to 1)
+//!     VALUE (u8 value type, followed by the value data)
+//!     HEADER (u8 set to 0 or 1, followed by the attributes and constants)
+
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+
+use immix::copy_object::CopyObject;
+use object::Object;
+use object_header::ObjectHeader;
+use object_pointer::ObjectPointer;
+use object_value;
+use object_value::ObjectValue;
+
+pub type SerializerResult<T> = Result<T, String>;
+
+const VERSION: u8 = 3;
+
+/// The maximum depth of an object graph.
+///
+/// Objects are serialized and deserialized recursively, this limit prevents
+/// deeply nested objects and malicious input from overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+const PERMANENT: u8 = 0;
+const REGULAR: u8 = 1;
+const TAGGED_INTEGER: u8 = 2;
+const REFERENCE: u8 = 3;
+const PERMANENT_PATH: u8 = 4;
+
+const STEP_ATTRIBUTE: u8 = 0;
+const STEP_CONSTANT: u8 = 1;
+
+const VALUE_NONE: u8 = 0;
+const VALUE_INTEGER: u8 = 1;
+const VALUE_FLOAT: u8 = 2;
+const VALUE_STRING: u8 = 3;
+const VALUE_ARRAY: u8 = 4;
+const VALUE_ERROR: u8 = 5;
+
+/// Serializes the object graph of the given pointer.
+///
+/// The `permanent` argument specifies the permanent objects that can be
+/// referred to by the serialized data.
+pub fn serialize(pointer: ObjectPointer,
+                 permanent: &[ObjectPointer])
+                 -> SerializerResult<Vec<u8>> {
+    let mut writer = Writer {
+        output: vec![VERSION],
+        permanent: permanent,
+        paths: None,
+        objects: HashMap::new(),
+    };
+
+    writer.write_object(pointer, 0)?;
+
+    Ok(writer.output)
+}
+
+/// Deserializes an object graph, allocating the objects using the given
+/// allocator.
+pub fn deserialize<A: CopyObject>(bytes: &[u8],
+                                  permanent: &[ObjectPointer],
+                                  allocator: &mut A)
+                                  -> SerializerResult<ObjectPointer> {
+    let mut reader = Reader {
+        bytes: bytes,
+        position: 0,
+        objects: Vec::new(),
+    };
+
+    if reader.read_u8()? != VERSION {
+        return Err("The serialized data uses an unsupported version"
+            .to_string());
+    }
+
+    let pointer = read_object(&mut reader, permanent, allocator, 0)?;
+
+    if reader.position != bytes.len() {
+        return Err("The serialized data contains trailing bytes".to_string());
+    }
+
+    Ok(pointer)
+}
+
+/// A buffer for writing an object graph, keeping track of the objects
+/// written so far.
+struct Writer<'a> {
+    output: Vec<u8>,
+    permanent: &'a [ObjectPointer],
+
+    /// The paths of the permanent objects reachable from `permanent`, only
+    /// built when such an object is written.
+    paths: Option<HashMap<ObjectPointer, PermanentPath>>,
+
+    /// The regular objects written so far, mapped to the order in which they
+    /// were written.
+    objects: HashMap<ObjectPointer, u64>,
+}
+
+impl<'a> Writer<'a> {
+    fn write_object(&mut self,
+                    pointer: ObjectPointer,
+                    depth: usize)
+                    -> SerializerResult<()> {
+        if depth > MAX_DEPTH {
+            return Err("The object graph is too deep to be serialized"
+                .to_string());
+        }
+
+        if pointer.is_tagged_integer() {
+            self.output.push(TAGGED_INTEGER);
+            write_u64(&mut self.output, pointer.integer_value()? as u64);
+
+            return Ok(());
+        }
+
+        if pointer.is_permanent() {
+            return self.write_permanent(pointer);
+        }
+
+        if let Some(index) = self.objects.get(&pointer).cloned() {
+            self.output.push(REFERENCE);
+            write_u64(&mut self.output, index);
+
+            return Ok(());
+        }
+
+        let index = self.objects.len() as u64;
+
+        self.objects.insert(pointer, index);
+
+        let object = pointer.get();
+
+        self.output.push(REGULAR);
+
+        if let Some(proto) = object.prototype() {
+            self.output.push(1);
+            self.write_object(proto, depth + 1)?;
+        } else {
+            self.output.push(0);
+        }
+
+        match object.value {
+            ObjectValue::None => self.output.push(VALUE_NONE),
+            ObjectValue::Integer(num) => {
+                self.output.push(VALUE_INTEGER);
+                write_u64(&mut self.output, num as u64);
+            }
+            ObjectValue::Float(num) => {
+                let bits = unsafe { mem::transmute::<f64, u64>(num) };
+
+                self.output.push(VALUE_FLOAT);
+                write_u64(&mut self.output, bits);
+            }
+            ObjectValue::String(ref string) => {
+                self.output.push(VALUE_STRING);
+                write_string(&mut self.output, string);
+            }
+            ObjectValue::Array(ref values) => {
+                self.output.push(VALUE_ARRAY);
+                write_u64(&mut self.output, values.len() as u64);
+
+                for value in values.iter() {
+                    self.write_object(*value, depth + 1)?;
+                }
+            }
+            ObjectValue::Error(num) => {
+                self.output.push(VALUE_ERROR);
+                write_u64(&mut self.output, num as u64);
+            }
+            ObjectValue::File(_) => {
+                return Err("Files can not be serialized".to_string());
+            }
+            ObjectValue::CompiledCode(_) => {
+                return Err("Compiled code can not be serialized".to_string());
+            }
+            ObjectValue::Binding(_) => {
+                return Err("Bindings can not be serialized".to_string());
+            }
+            ObjectValue::Channel(_) => {
+                return Err("Channels can not be serialized".to_string());
+            }
+            ObjectValue::WeakReference(_) => {
+                return Err("Weak references can not be serialized"
+                    .to_string());
+            }
+        }
+
+        if let Some(header) = object.header() {
+            if !header.methods.is_empty() {
+                return Err("Objects with methods can not be serialized"
+                    .to_string());
+            }
+
+            self.output.push(1);
+
+            write_u64(&mut self.output, header.attributes.len() as u64);
+
+            for (name, value) in header.attributes.iter() {
+                write_string(&mut self.output, name);
+                self.write_object(*value, depth + 1)?;
+            }
+
+            write_u64(&mut self.output, header.constants.len() as u64);
+
+            for (name, value) in header.constants.iter() {
+                write_string(&mut self.output, name);
+                self.write_object(*value, depth + 1)?;
+            }
+
+            if let Some(scope) = header.outer_scope {
+                self.output.push(1);
+                self.write_object(scope, depth + 1)?;
+            } else {
+                self.output.push(0);
+            }
+        } else {
+            self.output.push(0);
+        }
+
+        Ok(())
+    }
+
+    fn write_permanent(&mut self,
+                       pointer: ObjectPointer)
+                       -> SerializerResult<()> {
+        if let Some(index) = self.permanent
+            .iter()
+            .position(|perm| *perm == pointer) {
+            self.output.push(PERMANENT);
+            write_u64(&mut self.output, index as u64);
+
+            return Ok(());
+        }
+
+        if self.paths.is_none() {
+            self.paths = Some(permanent_paths(self.permanent));
+        }
+
+        let path = self.paths
+            .as_ref()
+            .and_then(|paths| paths.get(&pointer))
+            .cloned()
+            .ok_or_else(|| {
+                "Permanent objects that can not be reached by name can not be \
+                 serialized"
+                    .to_string()
+            })?;
+
+        self.output.push(PERMANENT_PATH);
+        write_u64(&mut self.output, path.root);
+        write_u64(&mut self.output, path.steps.len() as u64);
+
+        for &(kind, ref name) in path.steps.iter() {
+            self.output.push(kind);
+            write_string(&mut self.output, name);
+        }
+
+        Ok(())
+    }
+}
+
+/// The location of a permanent object, relative to one of the permanent
+/// objects shared by all VMs.
+#[derive(Clone)]
+struct PermanentPath {
+    /// The index of the permanent object to start at.
+    root: u64,
+
+    /// The attributes and constants to look up, starting at the root.
+    steps: Vec<(u8, String)>,
+}
+
+/// Returns the paths of all permanent objects reachable from the given
+/// permanent objects.
+///
+/// Objects are visited breadth first, so every object gets the shortest path
+/// that leads to it.
+fn permanent_paths(permanent: &[ObjectPointer])
+                   -> HashMap<ObjectPointer, PermanentPath> {
+    let mut paths = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    for (index, pointer) in permanent.iter().enumerate() {
+        if !paths.contains_key(pointer) {
+            let path = PermanentPath { root: index as u64, steps: Vec::new() };
+
+            paths.insert(*pointer, path);
+            queue.push_back(*pointer);
+        }
+    }
+
+    while let Some(pointer) = queue.pop_front() {
+        let path = paths.get(&pointer).unwrap().clone();
+
+        let header = if let Some(header) = pointer.get().header() {
+            header
+        } else {
+            continue;
+        };
+
+        let attributes = header.attributes
+            .iter()
+            .map(|(name, value)| (STEP_ATTRIBUTE, name, *value));
+
+        let constants = header.constants
+            .iter()
+            .map(|(name, value)| (STEP_CONSTANT, name, *value));
+
+        for (kind, name, child) in attributes.chain(constants) {
+            if child.is_tagged_integer() || !child.is_permanent() ||
+               paths.contains_key(&child) {
+                continue;
+            }
+
+            let mut steps = path.steps.clone();
+
+            steps.push((kind, name.clone()));
+
+            paths.insert(child, PermanentPath { root: path.root, steps: steps });
+            queue.push_back(child);
+        }
+    }
+
+    paths
+}
+
+/// Looks up the attribute or constant of a permanent object.
+fn lookup_permanent(pointer: ObjectPointer,
+                    kind: u8,
+                    name: &str)
+                    -> SerializerResult<ObjectPointer> {
+    let found = if pointer.is_tagged_integer() {
+        None
+    } else if let Some(header) = pointer.get().header() {
+        match kind {
+            STEP_ATTRIBUTE => header.get_attribute(name),
+            STEP_CONSTANT => header.get_constant(name),
+            _ => return Err(format!("Invalid path step type {}", kind)),
+        }
+    } else {
+        None
+    };
+
+    found.ok_or_else(|| format!("Undefined permanent object {}", name))
+}
+
+fn read_object<A: CopyObject>(reader: &mut Reader,
+                              permanent: &[ObjectPointer],
+                              allocator: &mut A,
+                              depth: usize)
+                              -> SerializerResult<ObjectPointer> {
+    if depth > MAX_DEPTH {
+        return Err("The serialized object graph is too deep".to_string());
+    }
+
+    match reader.read_u8()? {
+        PERMANENT => {
+            let index = reader.read_u64()? as usize;
+
+            return permanent.get(index)
+                .cloned()
+                .ok_or_else(|| format!("Undefined permanent object {}", index));
+        }
+        PERMANENT_PATH => {
+            let index = reader.read_u64()? as usize;
+
+            let mut pointer = permanent.get(index)
+                .cloned()
+                .ok_or_else(|| format!("Undefined permanent object {}", index))?;
+
+            for _ in 0..reader.read_u64()? {
+                let kind = reader.read_u8()?;
+                let name = reader.read_string()?;
+
+                pointer = lookup_permanent(pointer, kind, &name)?;
+            }
+
+            return Ok(pointer);
+        }
+        TAGGED_INTEGER => {
+            let value = reader.read_u64()? as i64;
+
+            if !ObjectPointer::integer_fits(value) {
+                return Err(format!("The integer {} can not be tagged", value));
+            }
+
+            return Ok(ObjectPointer::integer(value));
+        }
+        REFERENCE => {
+            let index = reader.read_u64()? as usize;
+
+            return reader.objects
+                .get(index)
+                .cloned()
+                .ok_or_else(|| format!("Undefined object reference {}", index));
+        }
+        REGULAR => {}
+        kind => return Err(format!("Invalid object type {}", kind)),
+    };
+
+    // The object is allocated before reading its contents so that objects
+    // further down the graph can refer back to it.
+    let pointer = allocator.allocate_copy(Object::new(object_value::none()));
+
+    reader.objects.push(pointer);
+
+    let proto_opt = if reader.read_bool()? {
+        Some(read_object(reader, permanent, allocator, depth + 1)?)
+    } else {
+        None
+    };
+
+    let value = match reader.read_u8()? {
+        VALUE_NONE => object_value::none(),
+        VALUE_INTEGER => object_value::integer(reader.read_u64()? as i64),
+        VALUE_FLOAT => {
+            let bits = reader.read_u64()?;
+
+            object_value::float(unsafe { mem::transmute::<u64, f64>(bits) })
+        }
+        VALUE_STRING => object_value::string(reader.read_string()?),
+        VALUE_ARRAY => {
+            let amount = reader.read_u64()?;
+            let mut values = Vec::new();
+
+            for _ in 0..amount {
+                let value = read_object(reader, permanent, allocator, depth + 1)?;
+
+                values.push(value);
+            }
+
+            object_value::array(values)
+        }
+        VALUE_ERROR => object_value::error(reader.read_u64()? as u16),
+        kind => return Err(format!("Invalid value type {}", kind)),
+    };
+
+    let header_opt = if reader.read_bool()? {
+        let mut header = ObjectHeader::new();
+
+        for _ in 0..reader.read_u64()? {
+            let name = reader.read_string()?;
+            let value = read_object(reader, permanent, allocator, depth + 1)?;
+
+            header.add_attribute(name, value);
+        }
+
+        for _ in 0..reader.read_u64()? {
+            let name = reader.read_string()?;
+            let value = read_object(reader, permanent, allocator, depth + 1)?;
+
+            header.add_constant(name, value);
+        }
+
+        if reader.read_bool()? {
+            let scope = read_object(reader, permanent, allocator, depth + 1)?;
+
+            header.outer_scope = Some(scope);
+        }
+
+        Some(header)
+    } else {
+        None
+    };
+
+    {
+        let object = pointer.get_mut();
+
+        object.value = value;
+
+        if let Some(proto) = proto_opt {
+            object.set_prototype(proto);
+        }
+
+        if let Some(header) = header_opt {
+            object.set_header(header);
+        }
+    }
+
+    if pointer.is_finalizable() {
+        pointer.mark_for_finalization();
+    }
+
+    Ok(pointer)
+}
+
+fn write_u64(output: &mut Vec<u8>, value: u64) {
+    let bytes: [u8; 8] = unsafe { mem::transmute(value.to_be()) };
+
+    output.extend_from_slice(&bytes);
+}
+
+fn write_string(output: &mut Vec<u8>, string: &String) {
+    write_u64(output, string.len() as u64);
+
+    output.extend_from_slice(string.as_bytes());
+}
+
+/// A cursor for reading values from a slice of bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+
+    /// The regular objects read so far, in the order they were read.
+    objects: Vec<ObjectPointer>,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, amount: usize) -> SerializerResult<&'a [u8]> {
+        if amount > self.bytes.len() - self.position {
+            return Err("The serialized data is incomplete".to_string());
+        }
+
+        let slice = &self.bytes[self.position..(self.position + amount)];
+
+        self.position += amount;
+
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> SerializerResult<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_bool(&mut self) -> SerializerResult<bool> {
+        Ok(self.read_u8()? == 1)
+    }
+
+    fn read_u64(&mut self) -> SerializerResult<u64> {
+        let mut buff: [u8; 8] = [0; 8];
+
+        buff.copy_from_slice(self.read_bytes(8)?);
+
+        let value: u64 = unsafe { mem::transmute(buff) };
+
+        Ok(u64::from_be(value))
+    }
+
+    fn read_string(&mut self) -> SerializerResult<String> {
+        let size = self.read_u64()? as usize;
+        let bytes = self.read_bytes(size)?;
+
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| "The serialized data contains an invalid string"
+                .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use immix::global_allocator::GlobalAllocator;
+    use immix::local_allocator::LocalAllocator;
+    use immix::permanent_allocator::PermanentAllocator;
+    use compiled_code::CompiledCode;
+    use object_value;
+
+    fn local_allocator() -> LocalAllocator {
+        LocalAllocator::new(GlobalAllocator::without_preallocated_blocks())
+    }
+
+    fn roundtrip(pointer: ObjectPointer,
+                 permanent: &[ObjectPointer])
+                 -> ObjectPointer {
+        let bytes = serialize(pointer, permanent).unwrap();
+
+        deserialize(&bytes, permanent, &mut local_allocator()).unwrap()
+    }
+
+    #[test]
+    fn test_serialize_none() {
+        let mut alloc = local_allocator();
+        let copy = roundtrip(alloc.allocate_empty(), &[]);
+
+        assert!(copy.get().value.is_none());
+        assert!(copy.get().prototype().is_none());
+    }
+
+    #[test]
+    fn test_serialize_integer() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_without_prototype(object_value::integer(-5));
+        let copy = roundtrip(ptr, &[]);
+
+        assert_eq!(copy.get().value.as_integer().unwrap(), -5);
+    }
+
+    #[test]
+    fn test_serialize_tagged_integer() {
+        let copy = roundtrip(ObjectPointer::integer(-5), &[]);
+
+        assert!(copy.is_tagged_integer());
+        assert_eq!(copy.integer_value().unwrap(), -5);
+    }
+
+    #[test]
+    fn test_deserialize_tagged_integer_out_of_range() {
+        let mut bytes = vec![VERSION, TAGGED_INTEGER];
+
+        write_u64(&mut bytes, i64::max_value() as u64);
+
+        assert!(deserialize(&bytes, &[], &mut local_allocator()).is_err());
+    }
+
+    #[test]
+    fn test_serialize_float() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_without_prototype(object_value::float(2.5));
+        let copy = roundtrip(ptr, &[]);
+
+        assert_eq!(copy.get().value.as_float().unwrap(), 2.5);
+    }
+
+    #[test]
+    fn test_serialize_string() {
+        let mut alloc = local_allocator();
+        let ptr = alloc
+            .allocate_without_prototype(object_value::string("a".to_string()));
+
+        let copy = roundtrip(ptr, &[]);
+
+        assert_eq!(copy.get().value.as_string().unwrap(), &"a".to_string());
+    }
+
+    #[test]
+    fn test_serialize_array() {
+        let mut alloc = local_allocator();
+        let ptr1 = alloc.allocate_without_prototype(object_value::integer(1));
+        let ptr2 = alloc.allocate_without_prototype(object_value::integer(2));
+        let array = alloc
+            .allocate_without_prototype(object_value::array(vec![ptr1, ptr2]));
+
+        let copy = roundtrip(array, &[]);
+        let values = copy.get().value.as_array().unwrap();
+
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[1].get().value.as_integer().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_serialize_error() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_without_prototype(object_value::error(3));
+        let copy = roundtrip(ptr, &[]);
+
+        assert_eq!(copy.get().value.as_error().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_serialize_header() {
+        let mut alloc = local_allocator();
+        let ptr1 = alloc.allocate_empty();
+        let ptr2 = alloc.allocate_without_prototype(object_value::integer(1));
+
+        ptr1.get_mut().add_attribute("a".to_string(), ptr2);
+        ptr1.get_mut().add_constant("B".to_string(), ptr2);
+
+        let copy = roundtrip(ptr1, &[]);
+        let header = copy.get().header().unwrap();
+
+        assert!(header.get_attribute("a").is_some());
+        assert!(header.get_constant("B").is_some());
+    }
+
+    #[test]
+    fn test_serialize_permanent_prototype() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+
+        let proto = perm_alloc.allocate_empty();
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_with_prototype(object_value::integer(1), proto);
+
+        let copy = roundtrip(ptr, &[proto]);
+
+        assert!(copy.get().prototype().unwrap() == proto);
+    }
+
+    #[test]
+    fn test_serialize_unknown_permanent_object() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+
+        let ptr = perm_alloc.allocate_empty();
+
+        assert!(serialize(ptr, &[]).is_err());
+    }
+
+    #[test]
+    fn test_serialize_permanent_prototype_by_path() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+
+        let top_level = perm_alloc.allocate_empty();
+        let class = perm_alloc.allocate_empty();
+        let proto = perm_alloc.allocate_empty();
+
+        top_level.get_mut().add_constant("Foo".to_string(), class);
+        class.get_mut().add_attribute("@proto".to_string(), proto);
+
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_with_prototype(object_value::integer(1), proto);
+
+        let copy = roundtrip(ptr, &[top_level]);
+
+        assert!(copy.get().prototype().unwrap() == proto);
+    }
+
+    #[test]
+    fn test_deserialize_undefined_permanent_path() {
+        let global_alloc = GlobalAllocator::without_preallocated_blocks();
+        let mut perm_alloc = PermanentAllocator::new(global_alloc);
+
+        let top_level = perm_alloc.allocate_empty();
+        let class = perm_alloc.allocate_empty();
+
+        top_level.get_mut().add_constant("Foo".to_string(), class);
+
+        let bytes = serialize(class, &[top_level]).unwrap();
+        let other_top_level = perm_alloc.allocate_empty();
+
+        assert!(deserialize(&bytes, &[other_top_level], &mut local_allocator())
+            .is_err());
+    }
+
+    #[test]
+    fn test_serialize_object_with_methods() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_empty();
+        let method = alloc.allocate_empty();
+
+        ptr.get_mut().add_method("foo".to_string(), method);
+
+        assert!(serialize(ptr, &[]).is_err());
+    }
+
+    #[test]
+    fn test_serialize_compiled_code() {
+        let mut alloc = local_allocator();
+        let code = CompiledCode::with_rc("a".to_string(),
+                                         "a".to_string(),
+                                         1,
+                                         Vec::new());
+
+        let ptr = alloc
+            .allocate_without_prototype(object_value::compiled_code(code));
+
+        assert!(serialize(ptr, &[]).is_err());
+    }
+
+    #[test]
+    fn test_serialize_cyclic_object() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_empty();
+
+        ptr.get_mut().add_attribute("self".to_string(), ptr);
+
+        let copy = roundtrip(ptr, &[]);
+        let attr = copy.get().header().unwrap().get_attribute("self").unwrap();
+
+        assert!(copy != ptr);
+        assert!(attr == copy);
+    }
+
+    #[test]
+    fn test_serialize_shared_object() {
+        let mut alloc = local_allocator();
+        let shared = alloc.allocate_without_prototype(object_value::integer(1));
+        let array = alloc.allocate_without_prototype(object_value::array(vec![
+            shared, shared,
+        ]));
+
+        let bytes = serialize(array, &[]).unwrap();
+        let copy = deserialize(&bytes, &[], &mut local_allocator()).unwrap();
+        let values = copy.get().value.as_array().unwrap();
+
+        assert!(values[0] == values[1]);
+        assert!(values[0] != shared);
+        assert_eq!(values[0].get().value.as_integer().unwrap(), 1);
+
+        // The shared object is written once, followed by a reference to it.
+        assert_eq!(bytes[bytes.len() - 10], REFERENCE);
+    }
+
+    #[test]
+    fn test_serialize_deeply_shared_objects() {
+        let mut alloc = local_allocator();
+        let mut pointer = alloc.allocate_empty();
+
+        // Without tracking written objects this would produce 2^64 objects.
+        for _ in 0..64 {
+            pointer = alloc.allocate_without_prototype(object_value::array(vec![
+                pointer, pointer,
+            ]));
+        }
+
+        let bytes = serialize(pointer, &[]).unwrap();
+
+        assert!(deserialize(&bytes, &[], &mut local_allocator()).is_ok());
+    }
+
+    #[test]
+    fn test_deserialize_undefined_reference() {
+        let mut bytes = vec![VERSION, REFERENCE];
+
+        write_u64(&mut bytes, 0);
+
+        assert!(deserialize(&bytes, &[], &mut local_allocator()).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_invalid_version() {
+        assert!(deserialize(&[0], &[], &mut local_allocator()).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_incomplete_data() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_without_prototype(object_value::integer(5));
+        let bytes = serialize(ptr, &[]).unwrap();
+
+        assert!(deserialize(&bytes[0..bytes.len() - 1],
+                            &[],
+                            &mut local_allocator())
+            .is_err());
+    }
+
+    #[test]
+    fn test_deserialize_trailing_data() {
+        let mut alloc = local_allocator();
+        let ptr = alloc.allocate_empty();
+        let mut bytes = serialize(ptr, &[]).unwrap();
+
+        bytes.push(0);
+
+        assert!(deserialize(&bytes, &[], &mut local_allocator()).is_err());
+    }
+}
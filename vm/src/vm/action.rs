@@ -19,4 +19,7 @@ pub enum Action {
 
     /// The VM should suspend the current process.
     Suspend,
+
+    /// The VM should terminate the current process.
+    Terminate,
 }
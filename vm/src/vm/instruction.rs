@@ -2,23 +2,28 @@
 use vm::instructions::array;
 use vm::instructions::binding;
 use vm::instructions::boolean;
+use vm::instructions::channel;
 use vm::instructions::code_execution;
 use vm::instructions::compiled_code;
 use vm::instructions::constant;
+use vm::instructions::distribution;
 use vm::instructions::error;
 use vm::instructions::file;
 use vm::instructions::float;
 use vm::instructions::flow_control;
+use vm::instructions::gc;
 use vm::instructions::integer;
 use vm::instructions::local_variable;
 use vm::instructions::method;
 use vm::instructions::object;
 use vm::instructions::process;
+use vm::instructions::process_group;
 use vm::instructions::prototype;
 use vm::instructions::stderr;
 use vm::instructions::stdin;
 use vm::instructions::stdout;
 use vm::instructions::string;
+use vm::instructions::weak_reference;
 use vm::machine::Machine;
 use vm::instructions::result::InstructionResult;
 
@@ -141,13 +146,41 @@ pub enum InstructionType {
     FileReadExact,
     StdinReadExact,
     ObjectEquals,
+    RegisterProcess,
+    UnregisterProcess,
+    LookupProcess,
+    SendRegisteredProcessMessage,
+    LookupProcessPool,
+    ExitProcess,
+    KillProcess,
+    ProcessList,
+    ProcessInfo,
+    SendRemoteProcessMessage,
+    NodeName,
+    GetChannelPrototype,
+    ChannelNew,
+    ChannelSend,
+    ChannelReceive,
+    ChannelClose,
+    ProcessGroupCreate,
+    ProcessGroupJoin,
+    ProcessGroupLeave,
+    ProcessGroupBroadcast,
+    HibernateProcess,
+    GcStatistics,
+    SetFinalizer,
+    FileClose,
+    HeapSnapshot,
+    GetWeakReferencePrototype,
+    WeakReferenceNew,
+    WeakReferenceGet,
 }
 
 pub const INSTRUCTION_MAPPING: [fn(&Machine,
     &RcProcess,
     &RcCompiledCode,
     &Instruction)
-    -> InstructionResult; 112] = [integer::set_integer,
+    -> InstructionResult; 140] = [integer::set_integer,
                                   float::set_float,
                                   string::set_string,
                                   object::set_object,
@@ -258,7 +291,60 @@ pub const INSTRUCTION_MAPPING: [fn(&Machine,
                                   error::error_to_integer,
                                   file::file_read_exact,
                                   stdin::stdin_read_exact,
-                                  object::object_equals];
+                                  object::object_equals,
+                                  process::register_process,
+                                  process::unregister_process,
+                                  process::lookup_process,
+                                  process::send_registered_process_message,
+                                  process::lookup_process_pool,
+                                  process::exit_process,
+                                  process::kill_process,
+                                  process::process_list,
+                                  process::process_info,
+                                  distribution::send_remote_process_message,
+                                  distribution::node_name,
+                                  prototype::get_channel_prototype,
+                                  channel::channel_new,
+                                  channel::channel_send,
+                                  channel::channel_receive,
+                                  channel::channel_close,
+                                  process_group::process_group_create,
+                                  process_group::process_group_join,
+                                  process_group::process_group_leave,
+                                  process_group::process_group_broadcast,
+                                  process::hibernate_process,
+                                  gc::gc_statistics,
+                                  gc::set_finalizer,
+                                  file::file_close,
+                                  gc::heap_snapshot,
+                                  prototype::get_weak_reference_prototype,
+                                  weak_reference::weak_reference_new,
+                                  weak_reference::weak_reference_get];
+
+impl InstructionType {
+    /// Returns true if the instruction may block the OS thread running it.
+    pub fn is_blocking(&self) -> bool {
+        match *self {
+            InstructionType::StdoutWrite |
+            InstructionType::StderrWrite |
+            InstructionType::StdinRead |
+            InstructionType::StdinReadLine |
+            InstructionType::StdinReadExact |
+            InstructionType::FileOpen |
+            InstructionType::FileWrite |
+            InstructionType::FileRead |
+            InstructionType::FileReadLine |
+            InstructionType::FileReadExact |
+            InstructionType::FileFlush |
+            InstructionType::FileSize |
+            InstructionType::FileSeek |
+            InstructionType::FileClose |
+            InstructionType::HeapSnapshot |
+            InstructionType::SendRemoteProcessMessage => true,
+            _ => false,
+        }
+    }
+}
 
 /// Struct for storing information about a single instruction.
 #[derive(Clone, Debug)]
@@ -325,6 +411,14 @@ mod tests {
         assert_eq!(ins.column, 4);
     }
 
+    #[test]
+    fn test_instruction_type_is_blocking() {
+        assert!(InstructionType::FileRead.is_blocking());
+        assert!(InstructionType::StdinReadLine.is_blocking());
+        assert!(InstructionType::SendRemoteProcessMessage.is_blocking());
+        assert_eq!(InstructionType::SetInteger.is_blocking(), false);
+    }
+
     #[test]
     fn test_arg_invalid() {
         let ins = new_instruction();
@@ -1,4 +1,6 @@
 //! VM instruction handlers for array operations.
+use std::mem;
+
 use immix::copy_object::CopyObject;
 
 use vm::action::Action;
@@ -7,6 +9,7 @@ use vm::instructions::result::InstructionResult;
 use vm::machine::Machine;
 
 use compiled_code::RcCompiledCode;
+use object_pointer::ObjectPointer;
 use object_value;
 use process::RcProcess;
 
@@ -77,13 +80,13 @@ pub fn array_insert(machine: &Machine,
     let index_ptr = process.get_register(instruction.arg(2)?)?;
     let value_ptr = process.get_register(instruction.arg(3)?)?;
 
-    let mut array = array_ptr.get_mut();
-    let index_obj = index_ptr.get();
-
-    let mut vector = array.value.as_array_mut()?;
-    let index = int_to_vector_index!(vector, index_obj.value.as_integer()?);
+    let vector = array_ptr.array_value_mut()?;
+    let index = int_to_vector_index!(vector, index_ptr.integer_value()?);
 
-    ensure_array_within_bounds!(vector, index);
+    // Inserting right after the last value grows the array.
+    if index > vector.len() {
+        return Err(format!("array index {} is out of bounds", index));
+    }
 
     let value = copy_if_permanent!(machine.state.permanent_allocator,
                                    value_ptr,
@@ -92,7 +95,14 @@ pub fn array_insert(machine: &Machine,
     if vector.get(index).is_some() {
         vector[index] = value;
     } else {
+        let pointer_size = mem::size_of::<ObjectPointer>();
+        let old_size = vector.capacity() * pointer_size;
+
         vector.insert(index, value);
+
+        process.track_off_heap_growth(array_ptr,
+                                      old_size,
+                                      vector.capacity() * pointer_size);
     }
 
     process.set_register(register, value);
@@ -119,11 +129,8 @@ pub fn array_at(_: &Machine,
     let register = instruction.arg(0)?;
     let array_ptr = process.get_register(instruction.arg(1)?)?;
     let index_ptr = process.get_register(instruction.arg(2)?)?;
-    let array = array_ptr.get();
-
-    let index_obj = index_ptr.get();
-    let vector = array.value.as_array()?;
-    let index = int_to_vector_index!(vector, index_obj.value.as_integer()?);
+    let vector = array_ptr.array_value()?;
+    let index = int_to_vector_index!(vector, index_ptr.integer_value()?);
 
     ensure_array_within_bounds!(vector, index);
 
@@ -154,10 +161,8 @@ pub fn array_remove(_: &Machine,
     let array_ptr = process.get_register(instruction.arg(1)?)?;
     let index_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let mut array = array_ptr.get_mut();
-    let index_obj = index_ptr.get();
-    let mut vector = array.value.as_array_mut()?;
-    let index = int_to_vector_index!(vector, index_obj.value.as_integer()?);
+    let vector = array_ptr.array_value_mut()?;
+    let index = int_to_vector_index!(vector, index_ptr.integer_value()?);
 
     ensure_array_within_bounds!(vector, index);
 
@@ -181,12 +186,11 @@ pub fn array_length(machine: &Machine,
                     -> InstructionResult {
     let register = instruction.arg(0)?;
     let array_ptr = process.get_register(instruction.arg(1)?)?;
-    let array = array_ptr.get();
-    let vector = array.value.as_array()?;
+    let vector = array_ptr.array_value()?;
     let length = vector.len() as i64;
 
-    let obj = process.allocate(object_value::integer(length),
-                               machine.state.integer_prototype.clone());
+    let obj = process.allocate_integer(length,
+                                       machine.state.integer_prototype.clone());
 
     process.set_register(register, obj);
 
@@ -202,8 +206,7 @@ pub fn array_clear(_: &Machine,
                    instruction: &Instruction)
                    -> InstructionResult {
     let array_ptr = process.get_register(instruction.arg(0)?)?;
-    let mut array = array_ptr.get_mut();
-    let mut vector = array.value.as_array_mut()?;
+    let vector = array_ptr.array_value_mut()?;
 
     vector.clear();
 
@@ -213,6 +216,9 @@ pub fn array_clear(_: &Machine,
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::mem;
+    use immix::block::BLOCK_SIZE;
+    use object_pointer::ObjectPointer;
     use object_value;
     use vm::instructions::test::*;
     use vm::instruction::InstructionType;
@@ -336,6 +342,51 @@ mod tests {
             assert!(result.is_err());
         }
 
+        #[test]
+        fn test_with_integer_instead_of_array() {
+            let (machine, code, process) = setup();
+            let instruction = new_instruction(InstructionType::ArrayInsert,
+                                              vec![3, 0, 1, 2]);
+
+            process.set_register(0, ObjectPointer::integer(5));
+            process.set_register(1, ObjectPointer::integer(0));
+            process.set_register(2, ObjectPointer::integer(1));
+
+            let result = array_insert(&machine, &process, &code, &instruction);
+
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_growing_array_across_collection_threshold() {
+            let (machine, code, process) = setup();
+            let instruction = new_instruction(InstructionType::ArrayInsert,
+                                              vec![3, 0, 1, 2]);
+
+            let length = BLOCK_SIZE / mem::size_of::<ObjectPointer>();
+            let values = vec![ObjectPointer::integer(1); length];
+            let array =
+                process.allocate_without_prototype(object_value::array(values));
+
+            {
+                let ref mut allocator = process.local_data_mut().allocator;
+
+                allocator.young_off_heap_bytes = 0;
+                allocator.young_block_allocation_threshold =
+                    allocator.young_block_allocations + 1;
+            }
+
+            process.set_register(0, array);
+            process.set_register(1, ObjectPointer::integer(length as i64));
+            process.set_register(2, ObjectPointer::integer(2));
+
+            assert!(!process.should_collect_young_generation());
+
+            let result = array_insert(&machine, &process, &code, &instruction);
+
+            assert!(result.is_ok());
+            assert!(process.should_collect_young_generation());
+        }
 
         #[test]
         fn test_with_valid_arguments() {
@@ -518,7 +569,7 @@ mod tests {
             assert!(removed_object.value.is_integer());
             assert_eq!(removed_object.value.as_integer().unwrap(), 5);
 
-            assert_eq!(array.get().value.as_array().unwrap().len(), 0);
+            assert_eq!(array.array_value().unwrap().len(), 0);
         }
     }
 
@@ -576,10 +627,9 @@ mod tests {
             assert!(result.is_ok());
 
             let pointer = process.get_register(1).unwrap();
-            let object = pointer.get();
 
-            assert!(object.value.is_integer());
-            assert_eq!(object.value.as_integer().unwrap(), 1);
+            assert!(pointer.is_integer());
+            assert_eq!(pointer.integer_value().unwrap(), 1);
         }
     }
 
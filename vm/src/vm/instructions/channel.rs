@@ -0,0 +1,221 @@
+//! VM instruction handlers for channel operations.
+use vm::action::Action;
+use vm::instruction::Instruction;
+use vm::instructions::result::InstructionResult;
+use vm::machine::Machine;
+
+use channel::SendResult;
+use compiled_code::RcCompiledCode;
+use errors;
+use immix::copy_object;
+use object_value;
+use process::{RcProcess, WaitReason};
+
+/// Creates a new channel owned by the current process.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the channel in.
+/// 2. The register containing the maximum number of messages (as an integer)
+///    the channel can hold. If omitted the channel is unbounded. The capacity
+///    must be greater than 0.
+pub fn channel_new(machine: &Machine,
+                   process: &RcProcess,
+                   _: &RcCompiledCode,
+                   instruction: &Instruction)
+                   -> InstructionResult {
+    let register = instruction.arg(0)?;
+
+    let capacity = if let Ok(cap_reg) = instruction.arg(1) {
+        let cap_ptr = process.get_register(cap_reg)?;
+        let capacity = cap_ptr.integer_value()?;
+
+        if capacity < 1 {
+            return Err(format!("channel capacities must be greater than 0, \
+                                {} was given",
+                               capacity));
+        }
+
+        Some(capacity as usize)
+    } else {
+        None
+    };
+
+    let channel = process.create_channel(capacity);
+    let obj = process.allocate(object_value::channel(channel),
+                               machine.state.channel_prototype);
+
+    process.set_register(register, obj);
+
+    Ok(Action::None)
+}
+
+/// Sends a message to a channel.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the message in.
+/// 2. The register containing the channel.
+/// 3. The register containing the message to send.
+///
+/// If the channel is full the current process is suspended, and the
+/// instruction will be retried the next time the process is executed.
+///
+/// An error is stored instead of the message if the channel has been closed.
+/// A channel is closed automatically once its owner no longer exists. An
+/// error is also stored if the message contains values that can't be copied,
+/// such as files.
+pub fn channel_send(machine: &Machine,
+                    process: &RcProcess,
+                    _: &RcCompiledCode,
+                    instruction: &Instruction)
+                    -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let chan_ptr = process.get_register(instruction.arg(1)?)?;
+    let msg_ptr = process.get_register(instruction.arg(2)?)?;
+    let channel = chan_ptr.channel_value()?;
+
+    if copy_object::ensure_copyable(msg_ptr).is_err() {
+        let code = errors::channel::unsendable();
+        let error = process.allocate_without_prototype(object_value::error(code));
+
+        process.set_register(register, error);
+
+        return Ok(Action::None);
+    }
+
+    let owner_opt = if channel.owner == process.pid {
+        Some(process.clone())
+    } else {
+        read_lock!(machine.state.process_table).get(&channel.owner)
+    };
+
+    let send_result = if let Some(owner) = owner_opt {
+        owner.send_to_channel(&channel, msg_ptr)
+    } else {
+        channel.close();
+
+        SendResult::Closed
+    };
+
+    let result = match send_result {
+        SendResult::Sent => msg_ptr,
+        SendResult::Full => {
+            process.start_waiting(WaitReason::ChannelSpace(channel));
+
+            return Ok(Action::Suspend);
+        }
+        SendResult::Closed => {
+            let code = errors::channel::closed();
+
+            process.allocate_without_prototype(object_value::error(code))
+        }
+    };
+
+    process.stop_waiting();
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Receives a message from a channel.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the message in.
+/// 2. The register containing the channel.
+///
+/// If no messages are available the current process is suspended, and the
+/// instruction will be retried the next time the process is executed.
+///
+/// An error is stored instead of the message if the current process does not
+/// own the channel, or if the channel is closed and empty.
+pub fn channel_receive(_: &Machine,
+                       process: &RcProcess,
+                       _: &RcCompiledCode,
+                       instruction: &Instruction)
+                       -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let chan_ptr = process.get_register(instruction.arg(1)?)?;
+    let channel = chan_ptr.channel_value()?;
+
+    let result = if channel.owner != process.pid {
+        let code = errors::channel::not_owner();
+
+        process.allocate_without_prototype(object_value::error(code))
+    } else if let Some(msg_ptr) = process.receive_from_channel(&channel) {
+        msg_ptr
+    } else if channel.is_closed() {
+        let code = errors::channel::closed();
+
+        process.allocate_without_prototype(object_value::error(code))
+    } else {
+        process.start_waiting(WaitReason::ChannelMessage(channel));
+
+        return Ok(Action::Suspend);
+    };
+
+    process.stop_waiting();
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Closes a channel.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the result (true) in.
+/// 2. The register containing the channel.
+///
+/// Messages already in the channel can still be received after closing it.
+pub fn channel_close(machine: &Machine,
+                     process: &RcProcess,
+                     _: &RcCompiledCode,
+                     instruction: &Instruction)
+                     -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let chan_ptr = process.get_register(instruction.arg(1)?)?;
+    let channel = chan_ptr.channel_value()?;
+
+    channel.close();
+
+    process.set_register(register, machine.state.true_object.clone());
+
+    Ok(Action::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_pointer::ObjectPointer;
+    use vm::instructions::test::*;
+    use vm::instruction::InstructionType;
+
+    #[test]
+    fn test_channel_new_with_capacity() {
+        let (machine, code, process) = setup();
+
+        process.set_register(1, ObjectPointer::integer(2));
+
+        let instruction = new_instruction(InstructionType::ChannelNew,
+                                          vec![0, 1]);
+
+        assert!(channel_new(&machine, &process, &code, &instruction).is_ok());
+        assert!(process.get_register(0).unwrap().channel_value().is_ok());
+    }
+
+    #[test]
+    fn test_channel_new_with_invalid_capacity() {
+        let (machine, code, process) = setup();
+        let instruction = new_instruction(InstructionType::ChannelNew,
+                                          vec![0, 1]);
+
+        for capacity in vec![0, -1] {
+            process.set_register(1, ObjectPointer::integer(capacity));
+
+            assert!(channel_new(&machine, &process, &code, &instruction)
+                .is_err());
+        }
+    }
+}
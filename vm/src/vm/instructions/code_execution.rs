@@ -27,18 +27,16 @@ pub fn run_code(machine: &Machine,
     let cc_ptr = process.get_register(instruction.arg(1)?)?;
     let args_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let code_obj = cc_ptr.get().value.as_compiled_code()?;
-    let args_obj = args_ptr.get();
-    let arguments = args_obj.value.as_array()?;
+    let code_obj = cc_ptr.compiled_code_value()?;
+    let arguments = args_ptr.array_value()?;
     let arg_count = arguments.len();
 
     let binding_idx = 3 + arg_count;
 
     let binding = if instruction.arg(binding_idx).is_ok() {
         let obj_ptr = process.get_register(binding_idx)?;
-        let obj = obj_ptr.get();
 
-        Some(obj.value.as_binding()?)
+        Some(obj_ptr.binding_value()?)
     } else {
         None
     };
@@ -128,7 +126,7 @@ pub fn run_file(machine: &Machine,
                 -> InstructionResult {
     let register = instruction.arg(0)?;
     let path_ptr = process.get_register(instruction.arg(1)?)?;
-    let path = path_ptr.get();
+    let path = path_ptr.string_value()?;
 
-    machine.run_file(path.value.as_string()?, process, instruction, register)
+    machine.run_file(path, process, instruction, register)
 }
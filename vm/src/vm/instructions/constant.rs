@@ -26,6 +26,8 @@ pub fn set_literal_const(machine: &Machine,
     let source_ptr = process.get_register(instruction.arg(2)?)?;
     let name = code.string(name_index)?;
 
+    target_ptr.ensure_mutable()?;
+
     let source = copy_if_permanent!(machine.state.permanent_allocator,
                                     source_ptr,
                                     target_ptr);
@@ -48,8 +50,9 @@ pub fn set_const(machine: &Machine,
     let target_ptr = process.get_register(instruction.arg(0)?)?;
     let name = process.get_register(instruction.arg(1)?)?;
     let source_ptr = process.get_register(instruction.arg(2)?)?;
-    let name_obj = name.get();
-    let name_str = name_obj.value.as_string()?.clone();
+    let name_str = name.string_value()?.clone();
+
+    target_ptr.ensure_mutable()?;
 
     let source = copy_if_permanent!(machine.state.permanent_allocator,
                                     source_ptr,
@@ -68,7 +71,7 @@ pub fn set_const(machine: &Machine,
 /// 2. The register pointing to an object in which to look for the
 ///    constant.
 /// 3. The string literal index containing the name of the constant.
-pub fn get_literal_const(_: &Machine,
+pub fn get_literal_const(machine: &Machine,
                          process: &RcProcess,
                          code: &RcCompiledCode,
                          instruction: &Instruction)
@@ -78,8 +81,7 @@ pub fn get_literal_const(_: &Machine,
     let name_index = instruction.arg(2)?;
     let name = code.string(name_index)?;
 
-    let object = src.get()
-        .lookup_constant(name)
+    let object = src.lookup_constant(&machine.state, name)
         .ok_or_else(|| constant_error!(instruction.arguments[1], name))?;
 
     process.set_register(register, object);
@@ -92,7 +94,7 @@ pub fn get_literal_const(_: &Machine,
 /// This instruction requires the same arguments as the "get_literal_const"
 /// instruction except the last argument should point to a register
 /// containing a String to use for the name.
-pub fn get_const(_: &Machine,
+pub fn get_const(machine: &Machine,
                  process: &RcProcess,
                  _: &RcCompiledCode,
                  instruction: &Instruction)
@@ -101,11 +103,9 @@ pub fn get_const(_: &Machine,
     let src = process.get_register(instruction.arg(1)?)?;
     let name = process.get_register(instruction.arg(2)?)?;
 
-    let name_obj = name.get();
-    let name_str = name_obj.value.as_string()?;
+    let name_str = name.string_value()?;
 
-    let object = src.get()
-        .lookup_constant(name_str)
+    let object = src.lookup_constant(&machine.state, name_str)
         .ok_or_else(|| constant_error!(instruction.arguments[1], name_str))?;
 
     process.set_register(register, object);
@@ -130,7 +130,7 @@ pub fn literal_const_exists(machine: &Machine,
     let name_index = instruction.arg(2)?;
     let name = code.string(name_index)?;
 
-    if source.get().lookup_constant(name).is_some() {
+    if source.lookup_constant(&machine.state, name).is_some() {
         process.set_register(register, machine.state.true_object.clone());
     } else {
         process.set_register(register, machine.state.false_object.clone());
@@ -0,0 +1,129 @@
+//! VM instruction handlers for exchanging messages with other VM instances.
+use vm::action::Action;
+use vm::instruction::Instruction;
+use vm::instructions::result::InstructionResult;
+use vm::machine::Machine;
+
+use compiled_code::RcCompiledCode;
+use distribution::Delivery;
+use errors;
+use mailbox::OverflowPolicy;
+use object_value;
+use process::RcProcess;
+use serializer;
+
+/// Sends a message to a process running on another node.
+///
+/// This instruction takes 4 arguments:
+///
+/// 1. The register to store the message in.
+/// 2. The register containing the name of the node to send the message to.
+/// 3. The register containing the PID of the receiving process.
+/// 4. The register containing the message (an object) to send to the
+///    process.
+///
+/// An error is stored instead of the message if distribution is disabled, the
+/// message can not be serialized, or the node can not be reached (including
+/// when the node name is invalid). Sending a message to the current node is the
+/// same as sending a local message.
+///
+/// Permanent objects, such as the prototypes of user defined objects, are sent
+/// by their name. Messages referring to permanent objects that can't be
+/// reached by name from the top-level object, or to objects with methods, can
+/// not be serialized.
+///
+/// The other node replies to every message, allowing the mailbox policy to be
+/// applied when the mailbox of the receiving process is full. With the
+/// "suspend" policy the process is rescheduled and tries to send the message
+/// again, as it can't wait for space in the mailbox of a process on another
+/// node.
+pub fn send_remote_process_message(machine: &Machine,
+                                   process: &RcProcess,
+                                   _: &RcCompiledCode,
+                                   instruction: &Instruction)
+                                   -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let node_ptr = process.get_register(instruction.arg(1)?)?;
+    let pid_ptr = process.get_register(instruction.arg(2)?)?;
+    let msg_ptr = process.get_register(instruction.arg(3)?)?;
+    let node_name = node_ptr.string_value()?;
+    let pid = pid_ptr.integer_value()? as usize;
+
+    let error_opt = if let Some(node) = machine.state.node.as_ref() {
+        if node_name == &node.name {
+            let receiver_opt = read_lock!(machine.state.process_table).get(&pid);
+
+            if let Some(receiver) = receiver_opt {
+                if let Some(result) =
+                    machine.send_process_message(process, &receiver, msg_ptr) {
+                    process.set_register(register, result);
+
+                    return Ok(Action::None);
+                } else {
+                    return Ok(Action::Suspend);
+                }
+            }
+
+            None
+        } else {
+            match serializer::serialize(msg_ptr, &machine.state.shared_objects) {
+                Ok(message) => {
+                    match node.send(node_name, pid, &message) {
+                        Ok(Delivery::Delivered) => None,
+                        Ok(Delivery::MailboxFull) => {
+                            match machine.state.config.mailbox_policy {
+                                OverflowPolicy::Suspend => {
+                                    return Ok(Action::Suspend);
+                                }
+                                OverflowPolicy::Error => {
+                                    Some(errors::process::mailbox_full())
+                                }
+                                OverflowPolicy::Drop => None,
+                            }
+                        }
+                        Err(_) => Some(errors::distribution::node_unavailable()),
+                    }
+                }
+                Err(_) => Some(errors::distribution::not_serializable()),
+            }
+        }
+    } else {
+        Some(errors::distribution::disabled())
+    };
+
+    let result = if let Some(code) = error_opt {
+        process.allocate_without_prototype(object_value::error(code))
+    } else {
+        msg_ptr
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Gets the name of the current node.
+///
+/// This instruction takes 1 argument: the register to store the name in.
+///
+/// An error is stored instead of the name if distribution is disabled.
+pub fn node_name(machine: &Machine,
+                 process: &RcProcess,
+                 _: &RcCompiledCode,
+                 instruction: &Instruction)
+                 -> InstructionResult {
+    let register = instruction.arg(0)?;
+
+    let result = if let Some(node) = machine.state.node.as_ref() {
+        process.allocate(object_value::string(node.name.clone()),
+                         machine.state.string_prototype)
+    } else {
+        let code = errors::distribution::disabled();
+
+        process.allocate_without_prototype(object_value::error(code))
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
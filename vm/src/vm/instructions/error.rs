@@ -5,7 +5,6 @@ use vm::instructions::result::InstructionResult;
 use vm::machine::Machine;
 
 use compiled_code::RcCompiledCode;
-use object_value;
 use process::RcProcess;
 
 /// Checks if a given object is an error object.
@@ -22,9 +21,9 @@ pub fn is_error(machine: &Machine,
     let register = instruction.arg(0)?;
     let obj_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let obj = obj_ptr.get();
+    let is_error = !obj_ptr.is_tagged_integer() && obj_ptr.get().value.is_error();
 
-    let result = if obj.value.is_error() {
+    let result = if is_error {
         machine.state.true_object.clone()
     } else {
         machine.state.false_object.clone()
@@ -48,12 +47,10 @@ pub fn error_to_integer(machine: &Machine,
                         -> InstructionResult {
     let register = instruction.arg(0)?;
     let error_ptr = process.get_register(instruction.arg(1)?)?;
-    let error = error_ptr.get();
-
     let proto = machine.state.integer_prototype.clone();
-    let integer = error.value.as_error()? as i64;
+    let integer = error_ptr.error_value()? as i64;
 
-    let result = process.allocate(object_value::integer(integer), proto);
+    let result = process.allocate_integer(integer, proto);
 
     process.set_register(register, result);
 
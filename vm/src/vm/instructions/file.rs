@@ -57,8 +57,8 @@ pub fn file_open(machine: &Machine,
     let path_ptr = process.get_register(instruction.arg(1)?)?;
     let mode_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let path = path_ptr.get().value.as_string()?;
-    let mode = mode_ptr.get().value.as_integer()?;
+    let path = path_ptr.string_value()?;
+    let mode = mode_ptr.integer_value()?;
 
     let mut open_opts = OpenOptions::new();
 
@@ -113,16 +113,13 @@ pub fn file_write(machine: &Machine,
     let file_ptr = process.get_register(instruction.arg(1)?)?;
     let string_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let mut file = file_ptr.get_mut();
-    let string = string_ptr.get();
-
-    let mut file = file.value.as_file_mut()?;
-    let bytes = string.value.as_string()?.as_bytes();
+    let file = file_ptr.file_value_mut()?;
+    let bytes = string_ptr.string_value()?.as_bytes();
 
     let obj = match file.write(bytes) {
         Ok(num_bytes) => {
-            process.allocate(object_value::integer(num_bytes as i64),
-                             machine.state.integer_prototype)
+            process.allocate_integer(num_bytes as i64,
+                                     machine.state.integer_prototype)
         }
         Err(error) => io_error_code!(process, error),
     };
@@ -149,8 +146,7 @@ pub fn file_read(machine: &Machine,
     let register = instruction.arg(0)?;
     let file_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let mut file_obj = file_ptr.get_mut();
-    let mut file = file_obj.value.as_file_mut()?;
+    let file = file_ptr.file_value_mut()?;
     let mut buffer = String::new();
 
     let obj = match file.read_to_string(&mut buffer) {
@@ -186,10 +182,9 @@ pub fn file_read_exact(machine: &Machine,
     let file_ptr = process.get_register(instruction.arg(1)?)?;
     let size_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let mut file_obj = file_ptr.get_mut();
-    let mut file = file_obj.value.as_file_mut()?;
+    let file = file_ptr.file_value_mut()?;
 
-    let size = size_ptr.get().value.as_integer()? as usize;
+    let size = size_ptr.integer_value()? as usize;
     let mut buffer = String::with_capacity(size);
 
     let obj = match file.take(size as u64).read_to_string(&mut buffer) {
@@ -222,8 +217,7 @@ pub fn file_read_line(machine: &Machine,
     let register = instruction.arg(0)?;
     let file_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let mut file_obj = file_ptr.get_mut();
-    let mut file = file_obj.value.as_file_mut()?;
+    let file = file_ptr.file_value_mut()?;
     let mut buffer = Vec::new();
 
     for result in file.bytes() {
@@ -277,8 +271,7 @@ pub fn file_flush(_: &Machine,
     let register = instruction.arg(0)?;
     let file_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let mut file_obj = file_ptr.get_mut();
-    let mut file = file_obj.value.as_file_mut()?;
+    let file = file_ptr.file_value_mut()?;
 
     let obj = match file.flush() {
         Ok(_) => file_ptr,
@@ -307,13 +300,12 @@ pub fn file_size(machine: &Machine,
     let register = instruction.arg(0)?;
     let file_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let file_obj = file_ptr.get();
-    let file = file_obj.value.as_file()?;
+    let file = file_ptr.file_value()?;
 
     let obj = match file.metadata() {
         Ok(meta) => {
-            process.allocate(object_value::integer(meta.len() as i64),
-                             machine.state.integer_prototype)
+            process.allocate_integer(meta.len() as i64,
+                                     machine.state.integer_prototype)
         }
         Err(error) => io_error_code!(process, error),
     };
@@ -342,14 +334,13 @@ pub fn file_seek(machine: &Machine,
     let file_ptr = process.get_register(instruction.arg(1)?)?;
     let offset_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let mut file_obj = file_ptr.get_mut();
-    let mut file = file_obj.value.as_file_mut()?;
-    let offset = offset_ptr.get().value.as_integer()?;
+    let file = file_ptr.file_value_mut()?;
+    let offset = offset_ptr.integer_value()?;
 
     let obj = match file.seek(SeekFrom::Start(offset as u64)) {
         Ok(new_offset) => {
-            process.allocate(object_value::integer(new_offset as i64),
-                             machine.state.integer_prototype)
+            process.allocate_integer(new_offset as i64,
+                                     machine.state.integer_prototype)
         }
         Err(error) => io_error_code!(process, error),
     };
@@ -358,3 +349,40 @@ pub fn file_seek(machine: &Machine,
 
     Ok(Action::None)
 }
+
+/// Closes a file.
+///
+/// This instruction requires two arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the file to close.
+///
+/// The file handle is released right away, instead of when the file object is
+/// garbage collected. The resulting object is the file itself. Closing a file
+/// that is already closed has no effect, but using a closed file for any other
+/// file operation results in an error.
+pub fn file_close(_: &Machine,
+                  process: &RcProcess,
+                  _: &RcCompiledCode,
+                  instruction: &Instruction)
+                  -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let file_ptr = process.get_register(instruction.arg(1)?)?;
+
+    file_ptr.ensure_mutable()?;
+
+    {
+        let mut file_obj = file_ptr.get_mut();
+
+        if !file_obj.value.is_none() {
+            file_obj.value.as_file()?;
+
+            // Dropping the handle closes the underlying file descriptor.
+            drop(file_obj.value.take());
+        }
+    }
+
+    process.set_register(register, file_ptr);
+
+    Ok(Action::None)
+}
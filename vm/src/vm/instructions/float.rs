@@ -121,11 +121,10 @@ pub fn float_to_integer(machine: &Machine,
                         -> InstructionResult {
     let register = instruction.arg(0)?;
     let float_ptr = process.get_register(instruction.arg(1)?)?;
-    let float = float_ptr.get();
-    let result = float.value.as_float()? as i64;
+    let result = float_ptr.float_value()? as i64;
 
-    let obj = process.allocate(object_value::integer(result),
-                               machine.state.integer_prototype.clone());
+    let obj = process.allocate_integer(result,
+                                       machine.state.integer_prototype.clone());
 
     process.set_register(register, obj);
 
@@ -145,8 +144,7 @@ pub fn float_to_string(machine: &Machine,
                        -> InstructionResult {
     let register = instruction.arg(0)?;
     let float_ptr = process.get_register(instruction.arg(1)?)?;
-    let float = float_ptr.get();
-    let result = float.value.as_float()?.to_string();
+    let result = float_ptr.float_value()?.to_string();
 
     let obj = process.allocate(object_value::string(result),
                                machine.state.string_prototype.clone());
@@ -353,7 +351,8 @@ mod tests {
     }
 
     macro_rules! test_cast_op {
-        ($ins_type: ident, $ins_func: ident, $target_type: ident, $target_val: expr) => (
+        ($ins_type: ident, $ins_func: ident, $pointer: ident => $value: expr,
+         $target_val: expr) => (
             mod $ins_func {
                 use super::*;
 
@@ -404,10 +403,9 @@ mod tests {
 
                     assert!(result.is_ok());
 
-                    let pointer = process.get_register(1).unwrap();
-                    let object = pointer.get();
+                    let $pointer = process.get_register(1).unwrap();
 
-                    assert!(object.value.$target_type().unwrap() == $target_val);
+                    assert!($value.unwrap() == $target_val);
                 }
             }
         );
@@ -467,6 +465,13 @@ mod tests {
     test_bool_op!(FloatGreater, float_greater, true_object);
     test_bool_op!(FloatEquals, float_equals, false_object);
 
-    test_cast_op!(FloatToInteger, float_to_integer, as_integer, 5);
-    test_cast_op!(FloatToString, float_to_string, as_string, &"5.5".to_string());
+    test_cast_op!(FloatToInteger,
+                  float_to_integer,
+                  pointer => pointer.integer_value(),
+                  5);
+
+    test_cast_op!(FloatToString,
+                  float_to_string,
+                  pointer => pointer.string_value(),
+                  &"5.5".to_string());
 }
@@ -0,0 +1,232 @@
+//! VM instruction handlers for inspecting the garbage collector.
+use vm::action::Action;
+use vm::instruction::Instruction;
+use vm::instructions::result::InstructionResult;
+use vm::machine::Machine;
+
+use compiled_code::RcCompiledCode;
+use errors;
+use gc::statistics::Statistics;
+use heap_snapshot::writer;
+use object_pointer::ObjectPointer;
+use object_value;
+use process::RcProcess;
+
+/// Returns an object containing the garbage collection statistics of a
+/// process, or of the entire VM.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the resulting object in.
+/// 2. The register containing the PID of the process to inspect. If omitted
+///    the statistics of all processes combined are returned.
+///
+/// The resulting object has the attributes "young", "full", "mailbox", and
+/// "compact", one for every type of collection. Each of these attributes is
+/// an object with the following attributes:
+///
+/// * collections: the number of collections performed.
+/// * marked: the total number of marked objects.
+/// * evacuated: the total number of evacuated objects.
+/// * promoted: the total number of promoted objects.
+/// * off_heap_bytes: the approximate number of bytes allocated outside of the
+///   heap (e.g. for the contents of strings) before these collections.
+/// * total, prepare, trace, reclaim: the total time spent collecting, and the
+///   time spent in every phase of a collection, in milliseconds (as floats).
+///
+/// The statistics of the entire VM also include the attribute "blocks", an
+/// object with the attributes "held", "in_use", and "released": the number of
+/// free blocks retained for re-use, the number of blocks in use, and the
+/// total number of blocks released to the operating system.
+///
+/// If the process does not exist an error is stored instead.
+pub fn gc_statistics(machine: &Machine,
+                     process: &RcProcess,
+                     _: &RcCompiledCode,
+                     instruction: &Instruction)
+                     -> InstructionResult {
+    let register = instruction.arg(0)?;
+
+    let vm_wide = instruction.arg(1).is_err();
+
+    let stats_opt = if let Ok(pid_reg) = instruction.arg(1) {
+        let pid_ptr = process.get_register(pid_reg)?;
+        let pid = pid_ptr.integer_value()? as usize;
+
+        if pid == process.pid {
+            Some(lock!(process.gc_statistics).clone())
+        } else {
+            read_lock!(machine.state.process_table)
+                .get(&pid)
+                .map(|receiver| lock!(receiver.gc_statistics).clone())
+        }
+    } else {
+        Some(machine.state.gc_statistics.lock().clone())
+    };
+
+    let result = if let Some(stats) = stats_opt {
+        let object = statistics_object(machine, process, &stats);
+
+        if vm_wide {
+            let blocks = block_statistics_object(machine, process);
+
+            object.add_attribute(process, "blocks".to_string(), blocks);
+        }
+
+        object
+    } else {
+        process.allocate_without_prototype(
+            object_value::error(errors::process::does_not_exist()))
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Registers a method to run once an object becomes unreachable.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register containing the object to register the finalizer for.
+/// 2. The register containing the name of the method to run, as a string.
+///
+/// The method is looked up when registering the finalizer, and must not
+/// require any arguments. It runs at most once, in a new process, using a
+/// copy of the object as "self". Registering a finalizer for an object that
+/// already has one replaces the existing finalizer.
+///
+/// Finalizers can only be registered for objects allocated on the heap of the
+/// current process.
+pub fn set_finalizer(machine: &Machine,
+                     process: &RcProcess,
+                     _: &RcCompiledCode,
+                     instruction: &Instruction)
+                     -> InstructionResult {
+    let object_ptr = process.get_register(instruction.arg(0)?)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?.clone();
+
+    if !object_ptr.is_young() && !object_ptr.is_mature() {
+        return Err("finalizers can only be registered for objects allocated \
+                    on the heap of the current process"
+            .to_string());
+    }
+
+    let method_ptr = object_ptr.lookup_method(&machine.state, &name)
+        .ok_or_else(|| format!("undefined method \"{}\"", name))?;
+
+    let code = method_ptr.compiled_code_value()?;
+
+    if code.required_arguments > 0 {
+        return Err(format!("the finalizer \"{}\" can not require any \
+                            arguments",
+                           name));
+    }
+
+    process.register_finalizer(object_ptr, code);
+
+    Ok(Action::None)
+}
+
+/// Writes a heap snapshot to a file.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the path of the file to write the snapshot to.
+/// 3. The register containing a boolean indicating if all processes should be
+///    included, instead of only the current process.
+///
+/// The result is `true` if the snapshot was written, or an error object if the
+/// file could not be written.
+///
+/// When including all processes, only processes that are suspended are
+/// included. These processes can't run until the snapshot has been written.
+pub fn heap_snapshot(machine: &Machine,
+                     process: &RcProcess,
+                     _: &RcCompiledCode,
+                     instruction: &Instruction)
+                     -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let path_ptr = process.get_register(instruction.arg(1)?)?;
+    let all_ptr = process.get_register(instruction.arg(2)?)?;
+    let path = path_ptr.string_value()?;
+    let processes = vec![process.clone()];
+
+    let result = if all_ptr != machine.state.false_object {
+        writer::write_file_with_suspended(&machine.state, processes, path)
+    } else {
+        writer::write_file(&machine.state, &processes, path)
+    };
+
+    let result = match result {
+        Ok(_) => machine.state.true_object,
+        Err(error) => io_error_code!(process, error),
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+fn statistics_object(machine: &Machine,
+                     process: &RcProcess,
+                     stats: &Statistics)
+                     -> ObjectPointer {
+    let object = process.allocate_empty();
+
+    for &(name, totals) in stats.all().iter() {
+        let totals_obj = process.allocate_empty();
+
+        let integers = [("collections", totals.collections),
+                        ("marked", totals.marked),
+                        ("evacuated", totals.evacuated),
+                        ("promoted", totals.promoted),
+                        ("off_heap_bytes", totals.off_heap_bytes)];
+
+        for &(attr, value) in integers.iter() {
+            let value = process.allocate_integer(value as i64,
+                                                 machine.state.integer_prototype);
+
+            totals_obj.add_attribute(process, attr.to_string(), value);
+        }
+
+        let timings = [("total", totals.total),
+                       ("prepare", totals.prepare),
+                       ("trace", totals.trace),
+                       ("reclaim", totals.reclaim)];
+
+        for &(attr, nanosec) in timings.iter() {
+            let msec = nanosec as f64 / 1000000.0;
+            let value = process.allocate(object_value::float(msec),
+                                         machine.state.float_prototype);
+
+            totals_obj.add_attribute(process, attr.to_string(), value);
+        }
+
+        object.add_attribute(process, name.to_string(), totals_obj);
+    }
+
+    object
+}
+
+fn block_statistics_object(machine: &Machine,
+                           process: &RcProcess)
+                           -> ObjectPointer {
+    let object = process.allocate_empty();
+    let stats = machine.state.global_allocator.statistics();
+
+    let integers = [("held", stats.held),
+                    ("in_use", stats.in_use),
+                    ("released", stats.released)];
+
+    for &(attr, value) in integers.iter() {
+        let value = process.allocate_integer(value as i64,
+                                             machine.state.integer_prototype);
+
+        object.add_attribute(process, attr.to_string(), value);
+    }
+
+    object
+}
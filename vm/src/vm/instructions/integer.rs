@@ -25,8 +25,8 @@ pub fn set_integer(machine: &Machine,
     let index = instruction.arg(1)?;
     let value = *code.integer(index)?;
 
-    let obj = process.allocate(object_value::integer(value),
-                               machine.state.integer_prototype.clone());
+    let obj = process.allocate_integer(value,
+                                       machine.state.integer_prototype.clone());
 
     process.set_register(register, obj);
 
@@ -121,8 +121,7 @@ pub fn integer_to_float(machine: &Machine,
                         -> InstructionResult {
     let register = instruction.arg(0)?;
     let integer_ptr = process.get_register(instruction.arg(1)?)?;
-    let integer = integer_ptr.get();
-    let result = integer.value.as_integer()? as f64;
+    let result = integer_ptr.integer_value()? as f64;
 
     let obj = process.allocate(object_value::float(result),
                                machine.state.float_prototype.clone());
@@ -145,8 +144,7 @@ pub fn integer_to_string(machine: &Machine,
                          -> InstructionResult {
     let register = instruction.arg(0)?;
     let integer_ptr = process.get_register(instruction.arg(1)?)?;
-    let integer = integer_ptr.get();
-    let result = integer.value.as_integer()?.to_string();
+    let result = integer_ptr.integer_value()?.to_string();
 
     let obj = process.allocate(object_value::string(result),
                                machine.state.string_prototype.clone());
@@ -350,9 +348,8 @@ mod tests {
                     assert!(result.is_ok());
 
                     let pointer = process.get_register(2).unwrap();
-                    let object = pointer.get();
 
-                    assert_eq!(object.value.as_integer().unwrap(), $expected);
+                    assert_eq!(pointer.integer_value().unwrap(), $expected);
                 }
             }
         );
@@ -526,9 +523,25 @@ mod tests {
             assert!(result.is_ok());
 
             let pointer = process.get_register(0).unwrap();
-            let object = pointer.get();
 
-            assert_eq!(object.value.as_integer().unwrap(), 10);
+            assert!(pointer.is_tagged_integer());
+            assert_eq!(pointer.integer_value().unwrap(), 10);
+        }
+
+        #[test]
+        fn test_with_large_integer() {
+            let (machine, code, process) = setup();
+            let instruction = new_instruction(InstructionType::SetInteger,
+                                              vec![0, 0]);
+
+            arc_mut(&code).integer_literals.push(i64::max_value());
+
+            set_integer(&machine, &process, &code, &instruction).unwrap();
+
+            let pointer = process.get_register(0).unwrap();
+
+            assert_eq!(pointer.is_tagged_integer(), false);
+            assert_eq!(pointer.integer_value().unwrap(), i64::max_value());
         }
     }
 
@@ -26,11 +26,10 @@ pub fn def_method(machine: &Machine,
     let receiver_ptr = process.get_register(instruction.arg(1)?)?;
     let name_ptr = process.get_register(instruction.arg(2)?)?;
     let cc_ptr = process.get_register(instruction.arg(3)?)?;
-    let name_obj = name_ptr.get();
+    let name = name_ptr.string_value()?;
+    let cc = cc_ptr.compiled_code_value()?;
 
-    let cc_obj = cc_ptr.get();
-    let name = name_obj.value.as_string()?;
-    let cc = cc_obj.value.as_compiled_code()?;
+    receiver_ptr.ensure_mutable()?;
 
     let method = machine.allocate_method(&process, &receiver_ptr, cc);
 
@@ -67,6 +66,9 @@ pub fn def_literal_method(machine: &Machine,
 
     let name = code.string(name_index)?;
     let cc = code.code_object(cc_index)?;
+
+    receiver_ptr.ensure_mutable()?;
+
     let method = machine.allocate_method(&process, &receiver_ptr, cc);
 
     receiver_ptr.add_method(&process, name.clone(), method);
@@ -110,9 +112,9 @@ pub fn send(machine: &Machine,
             instruction: &Instruction)
             -> InstructionResult {
     let string = process.get_register(instruction.arg(2)?)?;
-    let string_obj = string.get();
+    let name = string.string_value()?;
 
-    machine.send_message(string_obj.value.as_string()?, process, instruction)
+    machine.send_message(name, process, instruction)
 }
 
 /// Checks if an object responds to a message
@@ -132,9 +134,7 @@ pub fn literal_responds_to(machine: &Machine,
     let name_index = instruction.arg(2)?;
     let name = code.string(name_index)?;
 
-    let source_obj = source.get();
-
-    let result = if source_obj.responds_to(name) {
+    let result = if source.lookup_method(&machine.state, name).is_some() {
         machine.state.true_object.clone()
     } else {
         machine.state.false_object.clone()
@@ -160,10 +160,9 @@ pub fn responds_to(machine: &Machine,
     let source = process.get_register(instruction.arg(1)?)?;
     let name = process.get_register(instruction.arg(2)?)?;
 
-    let name_obj = name.get();
-    let source_obj = source.get();
+    let name_str = name.string_value()?;
 
-    let result = if source_obj.responds_to(name_obj.value.as_string()?) {
+    let result = if source.lookup_method(&machine.state, name_str).is_some() {
         machine.state.true_object.clone()
     } else {
         machine.state.false_object.clone()
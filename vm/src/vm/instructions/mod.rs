@@ -1,18 +1,22 @@
 pub mod array;
 pub mod binding;
 pub mod boolean;
+pub mod channel;
 pub mod code_execution;
 pub mod compiled_code;
 pub mod constant;
+pub mod distribution;
 pub mod error;
 pub mod file;
 pub mod float;
 pub mod flow_control;
+pub mod gc;
 pub mod integer;
 pub mod local_variable;
 pub mod method;
 pub mod object;
 pub mod process;
+pub mod process_group;
 pub mod prototype;
 pub mod result;
 pub mod stderr;
@@ -20,3 +24,4 @@ pub mod stdin;
 pub mod stdout;
 pub mod string;
 pub mod test;
+pub mod weak_reference;
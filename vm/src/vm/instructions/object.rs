@@ -49,7 +49,10 @@ pub fn get_self(_: &Machine,
 /// 1. The register to store the object in.
 /// 2. A register containing a truthy/falsy object. When the register
 ///    contains a truthy object the new object will be a global object.
-/// 3. An optional register containing the prototype for the object.
+/// 3. An optional register containing the prototype for the object. The
+///    prototype is copied into the permanent space if it's not permanent
+///    already, and the register is updated to refer to the copy. If the
+///    prototype is an integer, the integer prototype is used instead.
 pub fn set_object(machine: &Machine,
                   process: &RcProcess,
                   _: &RcCompiledCode,
@@ -66,16 +69,11 @@ pub fn set_object(machine: &Machine,
     };
 
     if let Ok(proto_index) = instruction.arg(2) {
-        let mut proto = process.get_register(proto_index)?;
-
-        if is_permanent && !proto.is_permanent() {
-            proto = machine.state
-                .permanent_allocator
-                .lock()
-                .copy_object(proto);
-        }
+        let proto =
+            machine.shared_prototype(process.get_register(proto_index)?)?;
 
         obj.get_mut().set_prototype(proto);
+        process.set_register(proto_index, proto);
     }
 
     process.set_register(register, obj);
@@ -103,6 +101,8 @@ pub fn set_literal_attr(machine: &Machine,
     let value_ptr = process.get_register(instruction.arg(2)?)?;
     let name = code.string(name_index)?;
 
+    target_ptr.ensure_mutable()?;
+
     let value = copy_if_permanent!(machine.state.permanent_allocator,
                                    value_ptr,
                                    target_ptr);
@@ -126,8 +126,9 @@ pub fn set_attr(machine: &Machine,
     let name_ptr = process.get_register(instruction.arg(1)?)?;
     let value_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let name_obj = name_ptr.get();
-    let name = name_obj.value.as_string()?;
+    let name = name_ptr.string_value()?;
+
+    target_ptr.ensure_mutable()?;
 
     let value = copy_if_permanent!(machine.state.permanent_allocator,
                                    value_ptr,
@@ -158,8 +159,7 @@ pub fn get_literal_attr(_: &Machine,
 
     let name = code.string(name_index)?;
 
-    let attr = source.get()
-        .lookup_attribute(name)
+    let attr = source.lookup_attribute(name)
         .ok_or_else(|| attribute_error!(instruction.arguments[1], name))?;
 
     process.set_register(register, attr);
@@ -181,11 +181,9 @@ pub fn get_attr(_: &Machine,
     let source = process.get_register(instruction.arg(1)?)?;
     let name = process.get_register(instruction.arg(2)?)?;
 
-    let name_obj = name.get();
-    let name = name_obj.value.as_string()?;
+    let name = name.string_value()?;
 
-    let attr = source.get()
-        .lookup_attribute(name)
+    let attr = source.lookup_attribute(name)
         .ok_or_else(|| attribute_error!(instruction.arguments[1], name))?;
 
     process.set_register(register, attr);
@@ -210,9 +208,7 @@ pub fn literal_attr_exists(machine: &Machine,
     let name_index = instruction.arg(2)?;
     let name = code.string(name_index)?;
 
-    let source = source_ptr.get();
-
-    let obj = if source.has_attribute(name) {
+    let obj = if source_ptr.lookup_attribute(name).is_some() {
         machine.state.true_object.clone()
     } else {
         machine.state.false_object.clone()
@@ -237,6 +233,8 @@ pub fn set_outer_scope(machine: &Machine,
     let target_ptr = process.get_register(instruction.arg(0)?)?;
     let scope_ptr = process.get_register(instruction.arg(1)?)?;
 
+    target_ptr.ensure_mutable()?;
+
     let scope = copy_if_permanent!(machine.state.permanent_allocator,
                                    scope_ptr,
                                    target_ptr);
@@ -277,3 +275,29 @@ pub fn object_equals(machine: &Machine,
 
     Ok(Action::None)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_pointer::ObjectPointer;
+    use vm::instructions::test::*;
+    use vm::instruction::InstructionType;
+
+    #[test]
+    fn test_set_object_with_integer_prototype() {
+        let (machine, code, process) = setup();
+
+        process.set_register(1, machine.state.false_object);
+        process.set_register(2, ObjectPointer::integer(5));
+
+        let instruction = new_instruction(InstructionType::SetObject,
+                                          vec![0, 1, 2]);
+
+        set_object(&machine, &process, &code, &instruction).unwrap();
+
+        let object = process.get_register(0).unwrap();
+
+        assert!(object.get().prototype == machine.state.integer_prototype);
+        assert!(object.get().lookup_method(&"foo".to_string()).is_none());
+    }
+}
@@ -5,18 +5,49 @@ use vm::instructions::result::InstructionResult;
 use vm::machine::Machine;
 
 use compiled_code::RcCompiledCode;
+use errors;
+use gc::finalizer;
+use gc::heap_collector;
+use gc::statistics;
+use heap_limits::HeapLimits;
+use object_pointer::ObjectPointer;
 use object_value;
 use pools::PRIMARY_POOL;
-use process::RcProcess;
+use process::{ExitReason, RcProcess, WaitReason};
 
 /// Runs a CompiledCode in a new process.
 ///
-/// This instruction takes 3 arguments:
+/// This instruction takes at least 2 arguments:
 ///
 /// 1. The register to store the PID in.
 /// 2. A code objects index pointing to the CompiledCode object to run.
 /// 3. The ID of the process pool to schedule the process on. Defaults to the ID
 ///    of the primary pool.
+/// 4. A set of bit flags specifying which of the optional registers described
+///    below are given. Defaults to 0.
+/// 5. The registers containing the arguments to pass to the new process,
+///    preceded by the optional registers.
+///
+/// The following flags and optional registers are supported, in this order:
+///
+/// * 1 (SPAWN_SELF): a register containing the object to use as "self" in the
+///   new process. The top-level object is used if not given.
+/// * 2 (SPAWN_MAILBOX_CAPACITY): a register containing the maximum number of
+///   messages (as an integer) the mailbox of the new process can hold, 0
+///   meaning unbounded. The configured capacity is used if not given.
+/// * 4 (SPAWN_YOUNG_LIMIT): a register containing the maximum number of blocks
+///   (as an integer) the young generation of the new process can use, 0
+///   meaning unlimited. The configured limit is used if not given.
+/// * 8 (SPAWN_MATURE_LIMIT): the same as SPAWN_YOUNG_LIMIT, but for the
+///   mature generation.
+/// * 16 (SPAWN_MAILBOX_LIMIT): the same as SPAWN_YOUNG_LIMIT, but for the
+///   mailbox.
+///
+/// The self object and the arguments are copied into the heap of the new
+/// process. The arguments are available as local variables, in the order they
+/// are given.
+///
+/// If the pool ID is invalid an error object is stored instead of the PID.
 pub fn spawn_literal_process(machine: &Machine,
                              process: &RcProcess,
                              code: &RcCompiledCode,
@@ -26,8 +57,16 @@ pub fn spawn_literal_process(machine: &Machine,
     let code_index = instruction.arg(1)?;
     let pool_id = instruction.arg(2).unwrap_or(PRIMARY_POOL);
     let code_obj = code.code_object(code_index)?;
+    let options = spawn_options(machine, process, instruction)?;
 
-    machine.spawn_process(process, pool_id, code_obj, register)?;
+    machine.spawn_process(process,
+                          pool_id,
+                          code_obj,
+                          options.self_obj,
+                          options.mailbox_capacity,
+                          options.heap_limits,
+                          &options.arguments,
+                          register)?;
 
     Ok(Action::None)
 }
@@ -37,7 +76,8 @@ pub fn spawn_literal_process(machine: &Machine,
 ///
 /// This instruction takes the same arguments as the "spawn_literal_process"
 /// instruction except instead of a code object index the 2nd argument
-/// should point to a register containing a CompiledCode object.
+/// should point to a register containing a CompiledCode object, and the 3rd
+/// argument should point to a register containing the pool ID.
 pub fn spawn_process(machine: &Machine,
                      process: &RcProcess,
                      _: &RcCompiledCode,
@@ -49,14 +89,22 @@ pub fn spawn_process(machine: &Machine,
     let pool_id = if let Ok(pool_reg) = instruction.arg(2) {
         let ptr = process.get_register(pool_reg)?;
 
-        ptr.get().value.as_integer()? as usize
+        ptr.integer_value()? as usize
     } else {
         PRIMARY_POOL
     };
 
-    let code_obj = code_ptr.get().value.as_compiled_code()?;
+    let code_obj = code_ptr.compiled_code_value()?;
+    let options = spawn_options(machine, process, instruction)?;
 
-    machine.spawn_process(process, pool_id, code_obj, register)?;
+    machine.spawn_process(process,
+                          pool_id,
+                          code_obj,
+                          options.self_obj,
+                          options.mailbox_capacity,
+                          options.heap_limits,
+                          &options.arguments,
+                          register)?;
 
     Ok(Action::None)
 }
@@ -69,6 +117,19 @@ pub fn spawn_process(machine: &Machine,
 /// 2. The register containing the PID to send the message to.
 /// 3. The register containing the message (an object) to send to the
 ///    process.
+///
+/// Messages sent to a process that no longer exists are dropped. This also
+/// applies to PIDs of finished processes of which the PID slot has since been
+/// reused by a new process.
+///
+/// If the mailbox of the receiver is full the configured mailbox policy is
+/// applied: the current process is either suspended (retrying the instruction
+/// when resumed), an error is stored instead of the message, or the message
+/// is dropped.
+///
+/// Messages are copied into the mailbox of the receiver, except for messages
+/// a process sends to itself. If the message contains values that can't be
+/// copied, such as files, an error is stored instead of the message.
 pub fn send_process_message(machine: &Machine,
                             process: &RcProcess,
                             _: &RcCompiledCode,
@@ -77,13 +138,21 @@ pub fn send_process_message(machine: &Machine,
     let register = instruction.arg(0)?;
     let pid_ptr = process.get_register(instruction.arg(1)?)?;
     let msg_ptr = process.get_register(instruction.arg(2)?)?;
-    let pid = pid_ptr.get().value.as_integer()? as usize;
+    let pid = pid_ptr.integer_value()? as usize;
+    let receiver_opt = read_lock!(machine.state.process_table).get(&pid);
 
-    if let Some(receiver) = read_lock!(machine.state.process_table).get(&pid) {
-        receiver.send_message(&process, msg_ptr);
-    }
+    let result = if let Some(receiver) = receiver_opt {
+        if let Some(result) =
+            machine.send_process_message(process, &receiver, msg_ptr) {
+            result
+        } else {
+            return Ok(Action::Suspend);
+        }
+    } else {
+        msg_ptr
+    };
 
-    process.set_register(register, msg_ptr);
+    process.set_register(register, result);
 
     Ok(Action::None)
 }
@@ -102,16 +171,59 @@ pub fn receive_process_message(_: &Machine,
                                -> InstructionResult {
     let register = instruction.arg(0)?;
     let result = if let Some(msg_ptr) = process.receive_message() {
+        process.stop_waiting();
         process.set_register(register, msg_ptr);
 
         Action::None
     } else {
+        process.start_waiting(WaitReason::Message);
+
         Action::Suspend
     };
 
     Ok(result)
 }
 
+/// Hibernates the current process until it receives a message.
+///
+/// This instruction does not take any arguments.
+///
+/// Hibernating releases unused register and binding storage, and performs a
+/// full collection that compacts the heap of the process. Any blocks no longer
+/// needed are returned to the global allocator. Finalizers of objects found to
+/// be unreachable are scheduled as well. Once done the process is suspended
+/// until a message arrives, without receiving said message.
+///
+/// If a message is already available the process continues right away.
+pub fn hibernate_process(machine: &Machine,
+                         process: &RcProcess,
+                         _: &RcCompiledCode,
+                         _: &Instruction)
+                         -> InstructionResult {
+    // A waiting process has already hibernated and is only checking if a
+    // message arrived, so there's no need to compact the heap again.
+    if process.wait_info().is_none() {
+        process.prepare_for_hibernation();
+
+        let profile = heap_collector::compact(process);
+
+        statistics::record(&machine.state, process, &profile);
+        finalizer::schedule(&machine.state, process);
+
+        machine.state.global_allocator.release_blocks();
+    }
+
+    if process.has_messages() {
+        process.stop_waiting();
+
+        Ok(Action::None)
+    } else {
+        process.start_waiting(WaitReason::Message);
+
+        Ok(Action::Suspend)
+    }
+}
+
 /// Gets the PID of the currently running process.
 ///
 /// This instruction requires one argument: the register to store the PID
@@ -124,10 +236,530 @@ pub fn get_current_pid(machine: &Machine,
     let register = instruction.arg(0)?;
     let pid = process.pid;
 
-    let pid_obj = process.allocate(object_value::integer(pid as i64),
-                                   machine.state.integer_prototype.clone());
+    let proto = machine.state.integer_prototype.clone();
+    let pid_obj = process.allocate_integer(pid as i64, proto);
 
     process.set_register(register, pid_obj);
 
     Ok(Action::None)
 }
+
+/// Registers a process under a name.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the name (a string) to register.
+/// 3. The register containing the PID to register. Defaults to the PID of the
+///    current process.
+///
+/// The result of this instruction is either the "true" object or an error
+/// object if the name is already in use or the process does not exist.
+pub fn register_process(machine: &Machine,
+                        process: &RcProcess,
+                        _: &RcCompiledCode,
+                        instruction: &Instruction)
+                        -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let pid = if let Ok(pid_reg) = instruction.arg(2) {
+        let ptr = process.get_register(pid_reg)?;
+
+        ptr.integer_value()? as usize
+    } else {
+        process.pid
+    };
+
+    // The process table is locked while registering the name so the process
+    // can't finish (and have its names removed) in the mean time.
+    let error_opt = {
+        let process_table = read_lock!(machine.state.process_table);
+
+        if process_table.contains(&pid) {
+            let registered = write_lock!(machine.state.process_registry)
+                .register(name.clone(), pid);
+
+            if registered {
+                None
+            } else {
+                Some(errors::process::already_registered())
+            }
+        } else {
+            Some(errors::process::does_not_exist())
+        }
+    };
+
+    let result = if let Some(code) = error_opt {
+        process.allocate_without_prototype(object_value::error(code))
+    } else {
+        machine.state.true_object.clone()
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Removes a registered process name.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the name to remove.
+///
+/// The result of this instruction is the "true" object if the name was
+/// registered, or the "false" object if it was not.
+pub fn unregister_process(machine: &Machine,
+                          process: &RcProcess,
+                          _: &RcCompiledCode,
+                          instruction: &Instruction)
+                          -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let removed = write_lock!(machine.state.process_registry)
+        .unregister(name)
+        .is_some();
+
+    let result = if removed {
+        machine.state.true_object.clone()
+    } else {
+        machine.state.false_object.clone()
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Looks up the PID of a registered process.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the PID in.
+/// 2. The register containing the name to look up.
+///
+/// The result of this instruction is either the PID (as an integer) or an
+/// error object if no process is registered under the name.
+pub fn lookup_process(machine: &Machine,
+                      process: &RcProcess,
+                      _: &RcCompiledCode,
+                      instruction: &Instruction)
+                      -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let pid_opt = read_lock!(machine.state.process_registry).lookup(name);
+
+    let result = if let Some(pid) = pid_opt {
+        process.allocate_integer(pid as i64,
+                                 machine.state.integer_prototype.clone())
+    } else {
+        let code = errors::process::not_registered();
+
+        process.allocate_without_prototype(object_value::error(code))
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Sends a message to a registered process.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the message in.
+/// 2. The register containing the name of the process to send the message
+///    to.
+/// 3. The register containing the message (an object) to send to the
+///    process.
+///
+/// If no process is registered under the name an error object is stored
+/// instead of the message. Full mailboxes and messages that can't be copied
+/// are handled the same way as the "send_process_message" instruction.
+pub fn send_registered_process_message(machine: &Machine,
+                                       process: &RcProcess,
+                                       _: &RcCompiledCode,
+                                       instruction: &Instruction)
+                                       -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let msg_ptr = process.get_register(instruction.arg(2)?)?;
+    let name = name_ptr.string_value()?;
+
+    let pid_opt = read_lock!(machine.state.process_registry).lookup(name);
+
+    let result = if let Some(pid) = pid_opt {
+        let receiver_opt = read_lock!(machine.state.process_table).get(&pid);
+
+        if let Some(receiver) = receiver_opt {
+            if let Some(result) =
+                machine.send_process_message(process, &receiver, msg_ptr) {
+                result
+            } else {
+                return Ok(Action::Suspend);
+            }
+        } else {
+            msg_ptr
+        }
+    } else {
+        let code = errors::process::not_registered();
+
+        process.allocate_without_prototype(object_value::error(code))
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Looks up the ID of a process pool by its name.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the pool ID in.
+/// 2. The register containing the name of the pool.
+///
+/// The result of this instruction is either the pool ID (as an integer) or
+/// an error object if no pool exists for the name.
+pub fn lookup_process_pool(machine: &Machine,
+                           process: &RcProcess,
+                           _: &RcCompiledCode,
+                           instruction: &Instruction)
+                           -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let result =
+        if let Some(pool_id) = machine.state.process_pools.id_for_name(name) {
+            process.allocate_integer(pool_id as i64,
+                                     machine.state.integer_prototype.clone())
+        } else {
+            let code = errors::process::invalid_pool();
+
+            process.allocate_without_prototype(object_value::error(code))
+        };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Terminates the current process.
+///
+/// This instruction takes 1 argument: the register containing the exit
+/// reason, as an integer.
+///
+/// When the main process exits with a non-zero reason the VM will exit with
+/// an error.
+pub fn exit_process(_: &Machine,
+                    process: &RcProcess,
+                    _: &RcCompiledCode,
+                    instruction: &Instruction)
+                    -> InstructionResult {
+    let reason_ptr = process.get_register(instruction.arg(0)?)?;
+    let reason = reason_ptr.integer_value()?;
+
+    process.terminate_with_reason(ExitReason::Exit(reason));
+
+    Ok(Action::Terminate)
+}
+
+/// Kills a process.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the PID of the process to kill.
+///
+/// The process is terminated the next time it reaches a safepoint. The
+/// result of this instruction is the "true" object if the process existed,
+/// or the "false" object if it did not.
+pub fn kill_process(machine: &Machine,
+                    process: &RcProcess,
+                    _: &RcCompiledCode,
+                    instruction: &Instruction)
+                    -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let pid_ptr = process.get_register(instruction.arg(1)?)?;
+    let pid = pid_ptr.integer_value()? as usize;
+
+    if pid == process.pid {
+        process.kill();
+
+        return Ok(Action::Terminate);
+    }
+
+    let found = if let Some(receiver) = read_lock!(machine.state.process_table)
+        .get(&pid) {
+        receiver.kill();
+        true
+    } else {
+        false
+    };
+
+    let result = if found {
+        machine.state.true_object.clone()
+    } else {
+        machine.state.false_object.clone()
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Returns the PIDs of all live processes.
+///
+/// This instruction takes 1 argument: the register to store the resulting
+/// array of PIDs in.
+pub fn process_list(machine: &Machine,
+                    process: &RcProcess,
+                    _: &RcCompiledCode,
+                    instruction: &Instruction)
+                    -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let mut pids = read_lock!(machine.state.process_table).pids();
+
+    pids.sort();
+
+    let values = pids.into_iter()
+        .map(|pid| {
+            process.allocate_integer(pid as i64,
+                                     machine.state.integer_prototype)
+        })
+        .collect();
+
+    let array = process.allocate(object_value::array(values),
+                                 machine.state.array_prototype);
+
+    process.set_register(register, array);
+
+    Ok(Action::None)
+}
+
+/// Returns an object containing details about a process.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the resulting object in.
+/// 2. The register containing the PID of the process to inspect.
+///
+/// The resulting object has the following attributes:
+///
+/// * status: the status of the process, as a string.
+/// * pool: the ID of the pool the process belongs to.
+/// * mailbox_size: the number of messages in the mailbox.
+/// * mailbox_capacity: the maximum number of messages the mailbox can hold.
+///   This attribute is only set if the mailbox is bounded.
+/// * mailbox_dropped: the number of messages dropped because the mailbox was
+///   full.
+/// * mailbox_blocked: the number of times a sender was suspended because the
+///   mailbox was full.
+/// * young_collections: the number of young generation collections.
+/// * mature_collections: the number of mature generation collections.
+/// * hibernations: the number of times the process hibernated.
+/// * mailbox_collections: the number of mailbox collections.
+/// * young_blocks: the number of blocks allocated for the young generation.
+/// * mature_blocks: the number of blocks allocated for the mature generation.
+/// * mailbox_blocks: the number of blocks allocated for the mailbox.
+/// * method, file, line: the method and line being executed.
+///
+/// The collection, block, method, file, and line attributes are only set when
+/// the process is not in use by another thread.
+///
+/// If the process does not exist an error is stored instead.
+pub fn process_info(machine: &Machine,
+                    process: &RcProcess,
+                    _: &RcCompiledCode,
+                    instruction: &Instruction)
+                    -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let pid_ptr = process.get_register(instruction.arg(1)?)?;
+    let pid = pid_ptr.integer_value()? as usize;
+
+    let info = if pid == process.pid {
+        Some(process.info(true))
+    } else {
+        read_lock!(machine.state.process_table)
+            .get(&pid)
+            .map(|receiver| receiver.info(false))
+    };
+
+    let result = if let Some(info) = info {
+        let object = process.allocate_empty();
+
+        let status_value = object_value::string(info.status.to_string());
+        let status = process.allocate(status_value,
+                                      machine.state.string_prototype);
+
+        object.add_attribute(process, "status".to_string(), status);
+
+        let mut integers = vec![("pool", info.pool_id),
+                                ("mailbox_size", info.mailbox_size),
+                                ("mailbox_dropped", info.mailbox_dropped),
+                                ("mailbox_blocked", info.mailbox_blocked)];
+
+        if let Some(heap) = info.heap {
+            integers.push(("young_collections", heap.young_collections));
+            integers.push(("mature_collections", heap.mature_collections));
+            integers.push(("hibernations", heap.hibernations));
+            integers.push(("mailbox_collections", heap.mailbox_collections));
+            integers.push(("young_blocks", heap.young_blocks));
+            integers.push(("mature_blocks", heap.mature_blocks));
+            integers.push(("mailbox_blocks", heap.mailbox_blocks));
+        }
+
+        for &(name, value) in integers.iter() {
+            let value = process.allocate_integer(value as i64,
+                                                 machine.state.integer_prototype);
+
+            object.add_attribute(process, name.to_string(), value);
+        }
+
+        if let Some(capacity) = info.mailbox_capacity {
+            let value = process.allocate_integer(capacity as i64,
+                                                 machine.state.integer_prototype);
+
+            object.add_attribute(process, "mailbox_capacity".to_string(), value);
+        }
+
+        if let Some(method) = info.method {
+            let value = process.allocate(object_value::string(method),
+                                         machine.state.string_prototype);
+
+            object.add_attribute(process, "method".to_string(), value);
+        }
+
+        if let Some(file) = info.file {
+            let value = process.allocate(object_value::string(file),
+                                         machine.state.string_prototype);
+
+            object.add_attribute(process, "file".to_string(), value);
+        }
+
+        if let Some(line) = info.line {
+            let value = process.allocate_integer(line as i64,
+                                                 machine.state.integer_prototype);
+
+            object.add_attribute(process, "line".to_string(), value);
+        }
+
+        object
+    } else {
+        process.allocate_without_prototype(
+            object_value::error(errors::process::does_not_exist()))
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// The flag indicating a spawn instruction specifies a self object.
+const SPAWN_SELF: usize = 1;
+
+/// The flag indicating a spawn instruction specifies a mailbox capacity.
+const SPAWN_MAILBOX_CAPACITY: usize = 2;
+
+/// The flag indicating a spawn instruction specifies a young generation limit.
+const SPAWN_YOUNG_LIMIT: usize = 4;
+
+/// The flag indicating a spawn instruction specifies a mature generation
+/// limit.
+const SPAWN_MATURE_LIMIT: usize = 8;
+
+/// The flag indicating a spawn instruction specifies a mailbox limit.
+const SPAWN_MAILBOX_LIMIT: usize = 16;
+
+/// The optional settings of a new process.
+struct SpawnOptions {
+    self_obj: Option<ObjectPointer>,
+    mailbox_capacity: Option<usize>,
+    heap_limits: HeapLimits,
+    arguments: Vec<ObjectPointer>,
+}
+
+/// Returns the optional settings of a new process.
+///
+/// The flags are expected to be the 4th argument of the instruction, see
+/// `spawn_literal_process` for more information.
+fn spawn_options(machine: &Machine,
+                 process: &RcProcess,
+                 instruction: &Instruction)
+                 -> Result<SpawnOptions, String> {
+    let mut options = SpawnOptions {
+        self_obj: None,
+        mailbox_capacity: None,
+        heap_limits: HeapLimits::from_config(&machine.state.config),
+        arguments: Vec::new(),
+    };
+
+    let mut offset = 3;
+
+    if instruction.arguments.len() <= offset {
+        return Ok(options);
+    }
+
+    let flags = instruction.arg(offset)?;
+
+    offset += 1;
+
+    if flags & SPAWN_SELF == SPAWN_SELF {
+        options.self_obj = Some(process.get_register(instruction.arg(offset)?)?);
+        offset += 1;
+    }
+
+    if flags & SPAWN_MAILBOX_CAPACITY == SPAWN_MAILBOX_CAPACITY {
+        let capacity_ptr = process.get_register(instruction.arg(offset)?)?;
+
+        options.mailbox_capacity =
+            Some(capacity_ptr.integer_value()? as usize);
+
+        offset += 1;
+    }
+
+    if flags & SPAWN_YOUNG_LIMIT == SPAWN_YOUNG_LIMIT {
+        options.heap_limits.young = block_limit(process, instruction, offset)?;
+        offset += 1;
+    }
+
+    if flags & SPAWN_MATURE_LIMIT == SPAWN_MATURE_LIMIT {
+        options.heap_limits.mature = block_limit(process, instruction, offset)?;
+        offset += 1;
+    }
+
+    if flags & SPAWN_MAILBOX_LIMIT == SPAWN_MAILBOX_LIMIT {
+        options.heap_limits.mailbox = block_limit(process, instruction, offset)?;
+        offset += 1;
+    }
+
+    let amount = instruction.arguments.len() - offset;
+
+    options.arguments =
+        machine.collect_arguments(process.clone(), instruction, offset, amount)?;
+
+    Ok(options)
+}
+
+/// Returns the block limit stored in the register of the given argument.
+fn block_limit(process: &RcProcess,
+               instruction: &Instruction,
+               index: usize)
+               -> Result<usize, String> {
+    let limit_ptr = process.get_register(instruction.arg(index)?)?;
+    let limit = limit_ptr.integer_value()?;
+
+    if limit < 0 {
+        return Err(format!("block limits can not be negative, {} was given",
+                           limit));
+    }
+
+    Ok(limit as usize)
+}
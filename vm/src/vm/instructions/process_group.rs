@@ -0,0 +1,401 @@
+//! VM instruction handlers for process groups.
+use vm::action::Action;
+use vm::instruction::Instruction;
+use vm::instructions::result::InstructionResult;
+use vm::machine::Machine;
+
+use compiled_code::RcCompiledCode;
+use errors;
+use immix::copy_object::ObjectGraph;
+use mailbox::OverflowPolicy;
+use object_value;
+use process::{RcProcess, WaitReason};
+
+/// Creates a new process group.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the name (a string) of the group.
+///
+/// The result of this instruction is either the "true" object or an error
+/// object if the group already exists.
+pub fn process_group_create(machine: &Machine,
+                            process: &RcProcess,
+                            _: &RcCompiledCode,
+                            instruction: &Instruction)
+                            -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let created = write_lock!(machine.state.process_groups)
+        .create(name.clone());
+
+    let result = if created {
+        machine.state.true_object.clone()
+    } else {
+        let code = errors::process::group_exists();
+
+        process.allocate_without_prototype(object_value::error(code))
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Adds a process to a process group.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the name of the group.
+/// 3. The register containing the PID of the process to add. Defaults to the
+///    PID of the current process.
+///
+/// The result of this instruction is either the "true" object or an error
+/// object if the group or the process does not exist.
+pub fn process_group_join(machine: &Machine,
+                          process: &RcProcess,
+                          _: &RcCompiledCode,
+                          instruction: &Instruction)
+                          -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let pid = if let Ok(pid_reg) = instruction.arg(2) {
+        let ptr = process.get_register(pid_reg)?;
+
+        ptr.integer_value()? as usize
+    } else {
+        process.pid
+    };
+
+    // The process table is locked while joining the group so the process
+    // can't finish (and leave its groups) in the mean time.
+    let error_opt = {
+        let process_table = read_lock!(machine.state.process_table);
+
+        if process_table.contains(&pid) {
+            let joined =
+                write_lock!(machine.state.process_groups).join(name, pid);
+
+            if joined {
+                None
+            } else {
+                Some(errors::process::group_does_not_exist())
+            }
+        } else {
+            Some(errors::process::does_not_exist())
+        }
+    };
+
+    let result = if let Some(code) = error_opt {
+        process.allocate_without_prototype(object_value::error(code))
+    } else {
+        machine.state.true_object.clone()
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Removes a process from a process group.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the result in.
+/// 2. The register containing the name of the group.
+/// 3. The register containing the PID of the process to remove. Defaults to
+///    the PID of the current process.
+///
+/// The result of this instruction is the "true" object if the process was a
+/// member of the group, or the "false" object if it was not.
+pub fn process_group_leave(machine: &Machine,
+                           process: &RcProcess,
+                           _: &RcCompiledCode,
+                           instruction: &Instruction)
+                           -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let name = name_ptr.string_value()?;
+
+    let pid = if let Ok(pid_reg) = instruction.arg(2) {
+        let ptr = process.get_register(pid_reg)?;
+
+        ptr.integer_value()? as usize
+    } else {
+        process.pid
+    };
+
+    let left = write_lock!(machine.state.process_groups).leave(name, &pid);
+
+    let result = if left {
+        machine.state.true_object.clone()
+    } else {
+        machine.state.false_object.clone()
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+/// Sends a message to every member of a process group.
+///
+/// This instruction takes 3 arguments:
+///
+/// 1. The register to store the message in.
+/// 2. The register containing the name of the group.
+/// 3. The register containing the message (an object) to send.
+///
+/// The object graph of the message is traversed once, then copied directly
+/// into the mailbox of every receiver. This way the message doesn't have to be
+/// traversed again for every receiver. Messages sent to the current process
+/// are not copied at all.
+///
+/// If the mailbox of a receiver is full the configured mailbox policy is
+/// applied, just like the "send_process_message" instruction:
+///
+/// * suspend: the current process is suspended until there is room in the
+///   mailbox. Once resumed the message is only sent to the receivers it was
+///   not yet sent to.
+/// * error: the message is still sent to all other receivers, but an error is
+///   stored instead of the message.
+/// * drop: the message is dropped for this receiver.
+///
+/// If the group does not exist, or the message contains values that can't be
+/// copied (such as files), an error object is stored instead of the message.
+pub fn process_group_broadcast(machine: &Machine,
+                               process: &RcProcess,
+                               _: &RcCompiledCode,
+                               instruction: &Instruction)
+                               -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let name_ptr = process.get_register(instruction.arg(1)?)?;
+    let msg_ptr = process.get_register(instruction.arg(2)?)?;
+    let name = name_ptr.string_value()?;
+
+    let graph = match ObjectGraph::new(msg_ptr) {
+        Ok(graph) => graph,
+        Err(_) => {
+            let code = errors::process::unsendable();
+            let error =
+                process.allocate_without_prototype(object_value::error(code));
+
+            process.set_register(register, error);
+
+            return Ok(Action::None);
+        }
+    };
+
+    // A process suspended because of a full mailbox retries this instruction
+    // once resumed, sending the message to the remaining receivers only.
+    let pending = process.take_pending_broadcast();
+    let retrying = !pending.is_empty();
+
+    let members_opt = if retrying {
+        None
+    } else {
+        read_lock!(machine.state.process_groups).members(name)
+    };
+
+    let receivers = if retrying {
+        pending
+    } else if let Some(members) = members_opt {
+        let process_table = read_lock!(machine.state.process_table);
+
+        members.iter()
+            .filter_map(|pid| process_table.get(pid))
+            .collect::<Vec<_>>()
+    } else {
+        let code = errors::process::group_does_not_exist();
+        let error =
+            process.allocate_without_prototype(object_value::error(code));
+
+        process.set_register(register, error);
+
+        return Ok(Action::None);
+    };
+
+    let mut mailbox_full = false;
+
+    for (index, receiver) in receivers.iter().enumerate() {
+        let sent = if receiver.pid == process.pid {
+            receiver.send_message(&process, msg_ptr)
+        } else {
+            receiver.send_graph(&graph)
+        };
+
+        if sent {
+            continue;
+        }
+
+        match machine.state.config.mailbox_policy {
+            OverflowPolicy::Suspend => {
+                // If no message was sent yet we are still waiting for the same
+                // receiver, otherwise we have to wait for a different one.
+                if index > 0 {
+                    process.stop_waiting();
+                }
+
+                let reason = WaitReason::MailboxSpace(receiver.clone());
+
+                if process.start_waiting(reason) {
+                    receiver.sender_blocked();
+                }
+
+                process.set_pending_broadcast(receivers[index..].to_vec());
+
+                return Ok(Action::Suspend);
+            }
+            OverflowPolicy::Error => mailbox_full = true,
+            OverflowPolicy::Drop => receiver.message_dropped(),
+        }
+    }
+
+    process.stop_waiting();
+
+    let result = if mailbox_full {
+        let code = errors::process::mailbox_full();
+
+        process.allocate_without_prototype(object_value::error(code))
+    } else {
+        msg_ptr
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compiled_code::{CompiledCode, RcCompiledCode};
+    use config::Config;
+    use errors;
+    use mailbox::OverflowPolicy;
+    use object_pointer::ObjectPointer;
+    use object_value;
+    use process::RcProcess;
+    use vm::action::Action;
+    use vm::instruction::InstructionType;
+    use vm::instructions::result::InstructionResult;
+    use vm::instructions::test::new_instruction;
+    use vm::machine::Machine;
+    use vm::state::State;
+
+    /// Sets up a VM with a sender, and a group containing two receivers. The
+    /// mailbox of the first receiver is full.
+    fn setup(policy: OverflowPolicy)
+             -> (Machine, RcCompiledCode, RcProcess, RcProcess, RcProcess) {
+        let mut config = Config::new();
+
+        config.mailbox_policy = policy;
+
+        let machine = Machine::new(State::new(config));
+        let code = CompiledCode::with_rc("a".to_string(),
+                                         "a".to_string(),
+                                         1,
+                                         Vec::new());
+
+        let (sender, full, other) = {
+            let top_level = machine.state.top_level;
+            let new_process = || {
+                machine.allocate_process(0, code.clone(), top_level).unwrap()
+            };
+
+            (new_process(), new_process(), new_process())
+        };
+
+        full.set_mailbox_capacity(1);
+        full.send_message(&sender, ObjectPointer::integer(1));
+
+        {
+            let mut groups = write_lock!(machine.state.process_groups);
+
+            groups.create("a".to_string());
+            groups.join("a", full.pid);
+            groups.join("a", other.pid);
+        }
+
+        let name = sender.allocate(object_value::string("a".to_string()),
+                                   machine.state.string_prototype);
+
+        sender.set_register(0, name);
+        sender.set_register(1, ObjectPointer::integer(2));
+
+        (machine, code, sender, full, other)
+    }
+
+    fn broadcast(machine: &Machine,
+                 code: &RcCompiledCode,
+                 sender: &RcProcess)
+                 -> InstructionResult {
+        let instruction =
+            new_instruction(InstructionType::ProcessGroupBroadcast,
+                            vec![2, 0, 1]);
+
+        process_group_broadcast(machine, sender, code, &instruction)
+    }
+
+    #[test]
+    fn test_broadcast_with_full_mailbox_and_drop_policy() {
+        let (machine, code, sender, full, other) =
+            setup(OverflowPolicy::Drop);
+
+        assert!(broadcast(&machine, &code, &sender).is_ok());
+
+        assert!(sender.get_register(2).unwrap() ==
+                ObjectPointer::integer(2));
+
+        assert_eq!(full.mailbox_size(), 1);
+        assert_eq!(full.local_data().mailbox.dropped(), 1);
+        assert_eq!(other.mailbox_size(), 1);
+    }
+
+    #[test]
+    fn test_broadcast_with_full_mailbox_and_error_policy() {
+        let (machine, code, sender, full, other) =
+            setup(OverflowPolicy::Error);
+
+        assert!(broadcast(&machine, &code, &sender).is_ok());
+
+        let result = sender.get_register(2).unwrap();
+
+        assert!(result.error_value().unwrap() ==
+                errors::process::mailbox_full());
+
+        assert_eq!(full.mailbox_size(), 1);
+        assert_eq!(other.mailbox_size(), 1);
+    }
+
+    #[test]
+    fn test_broadcast_with_full_mailbox_and_suspend_policy() {
+        let (machine, code, sender, full, other) =
+            setup(OverflowPolicy::Suspend);
+
+        match broadcast(&machine, &code, &sender) {
+            Ok(Action::Suspend) => {}
+            _ => panic!("the sender should be suspended"),
+        }
+
+        assert!(sender.wait_info().is_some());
+        assert_eq!(full.local_data().mailbox.blocked(), 1);
+        assert_eq!(other.mailbox_size(), 0);
+
+        full.local_data_mut().mailbox.receive();
+
+        // Retrying sends the message to the remaining receivers only.
+        assert!(broadcast(&machine, &code, &sender).is_ok());
+
+        assert!(sender.wait_info().is_none());
+        assert!(sender.take_pending_broadcast().is_empty());
+        assert_eq!(full.mailbox_size(), 1);
+        assert_eq!(other.mailbox_size(), 1);
+    }
+}
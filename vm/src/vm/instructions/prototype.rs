@@ -13,15 +13,25 @@ use process::RcProcess;
 ///
 /// 1. The register containing the object for which to set the prototype.
 /// 2. The register containing the object to use as the prototype.
-pub fn set_prototype(_: &Machine,
+///
+/// The prototype is copied into the permanent space if it's not permanent
+/// already, and the register is updated to refer to the copy. If the prototype
+/// is an integer, the integer prototype is used instead.
+pub fn set_prototype(machine: &Machine,
                      process: &RcProcess,
                      _: &RcCompiledCode,
                      instruction: &Instruction)
                      -> InstructionResult {
     let source = process.get_register(instruction.arg(0)?)?;
-    let proto = process.get_register(instruction.arg(1)?)?;
+    let proto_register = instruction.arg(1)?;
+
+    source.ensure_mutable()?;
+
+    let proto =
+        machine.shared_prototype(process.get_register(proto_register)?)?;
 
     source.get_mut().set_prototype(proto);
+    process.set_register(proto_register, proto);
 
     Ok(Action::None)
 }
@@ -32,7 +42,7 @@ pub fn set_prototype(_: &Machine,
 ///
 /// 1. The register to store the prototype in.
 /// 2. The register containing the object to get the prototype from.
-pub fn get_prototype(_: &Machine,
+pub fn get_prototype(machine: &Machine,
                      process: &RcProcess,
                      _: &RcCompiledCode,
                      instruction: &Instruction)
@@ -40,9 +50,7 @@ pub fn get_prototype(_: &Machine,
     let register = instruction.arg(0)?;
     let source = process.get_register(instruction.arg(1)?)?;
 
-    let source_obj = source.get();
-
-    let proto = source_obj.prototype()
+    let proto = source.prototype(&machine.state)
         .ok_or_else(|| {
             format!("The object in register {} does not have a prototype",
                     instruction.arguments[1])
@@ -196,3 +204,86 @@ pub fn get_compiled_code_prototype(machine: &Machine,
 
     Ok(Action::None)
 }
+
+/// Gets the prototype to use for channel objects.
+///
+/// This instruction requires one argument: the register to store the
+/// prototype in.
+pub fn get_channel_prototype(machine: &Machine,
+                             process: &RcProcess,
+                             _: &RcCompiledCode,
+                             instruction: &Instruction)
+                             -> InstructionResult {
+    let register = instruction.arg(0)?;
+
+    process.set_register(register, machine.state.channel_prototype.clone());
+
+    Ok(Action::None)
+}
+
+/// Gets the prototype to use for weak reference objects.
+///
+/// This instruction requires one argument: the register to store the
+/// prototype in.
+pub fn get_weak_reference_prototype(machine: &Machine,
+                                    process: &RcProcess,
+                                    _: &RcCompiledCode,
+                                    instruction: &Instruction)
+                                    -> InstructionResult {
+    let register = instruction.arg(0)?;
+
+    process.set_register(register,
+                         machine.state.weak_reference_prototype.clone());
+
+    Ok(Action::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_pointer::ObjectPointer;
+    use vm::instructions::test::*;
+    use vm::instruction::InstructionType;
+
+    #[test]
+    fn test_set_prototype_with_integer_prototype() {
+        let (machine, code, process) = setup();
+        let object = process.allocate_empty();
+
+        process.set_register(0, object);
+        process.set_register(1, ObjectPointer::integer(5));
+
+        let instruction = new_instruction(InstructionType::SetPrototype,
+                                          vec![0, 1]);
+
+        set_prototype(&machine, &process, &code, &instruction).unwrap();
+
+        assert!(object.get().prototype == machine.state.integer_prototype);
+        assert!(object.get().lookup_method(&"foo".to_string()).is_none());
+        assert!(object.get().lookup_constant(&"Foo".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_set_prototype_shares_the_prototype() {
+        let (machine, code, process) = setup();
+        let object = process.allocate_empty();
+        let proto = process.allocate_empty();
+
+        process.set_register(0, object);
+        process.set_register(1, proto);
+
+        let instruction = new_instruction(InstructionType::SetPrototype,
+                                          vec![0, 1]);
+
+        set_prototype(&machine, &process, &code, &instruction).unwrap();
+
+        let shared = process.get_register(1).unwrap();
+
+        assert!(shared.is_permanent());
+        assert!(object.get().prototype == shared);
+
+        let copy = process.copy_object(object).unwrap();
+
+        assert!(copy.get().prototype == shared);
+    }
+}
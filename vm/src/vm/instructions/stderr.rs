@@ -7,7 +7,6 @@ use vm::instructions::result::InstructionResult;
 use vm::machine::Machine;
 
 use compiled_code::RcCompiledCode;
-use object_value;
 use process::RcProcess;
 
 /// Writes a string to STDERR and returns the amount of written bytes.
@@ -26,15 +25,15 @@ pub fn stderr_write(machine: &Machine,
                     -> InstructionResult {
     let register = instruction.arg(0)?;
     let string_ptr = process.get_register(instruction.arg(1)?)?;
-    let string = string_ptr.get().value.as_string()?;
+    let string = string_ptr.string_value()?;
     let mut stderr = io::stderr();
 
     let obj = match stderr.write(string.as_bytes()) {
         Ok(num_bytes) => {
             match stderr.flush() {
                 Ok(_) => {
-                    process.allocate(object_value::integer(num_bytes as i64),
-                                     machine.state.integer_prototype)
+                    process.allocate_integer(num_bytes as i64,
+                                             machine.state.integer_prototype)
                 }
                 Err(error) => io_error_code!(process, error),
             }
@@ -47,8 +47,7 @@ pub fn string_to_lower(machine: &Machine,
                        -> InstructionResult {
     let register = instruction.arg(0)?;
     let source_ptr = process.get_register(instruction.arg(1)?)?;
-    let source = source_ptr.get();
-    let lower = source.value.as_string()?.to_lowercase();
+    let lower = source_ptr.string_value()?.to_lowercase();
 
     let obj = process.allocate(object_value::string(lower),
                                machine.state.string_prototype.clone());
@@ -71,8 +70,7 @@ pub fn string_to_upper(machine: &Machine,
                        -> InstructionResult {
     let register = instruction.arg(0)?;
     let source_ptr = process.get_register(instruction.arg(1)?)?;
-    let source = source_ptr.get();
-    let upper = source.value.as_string()?.to_uppercase();
+    let upper = source_ptr.string_value()?.to_uppercase();
 
     let obj = process.allocate(object_value::string(upper),
                                machine.state.string_prototype.clone());
@@ -98,9 +96,7 @@ pub fn string_equals(machine: &Machine,
     let receiver_ptr = process.get_register(instruction.arg(1)?)?;
     let arg_ptr = process.get_register(instruction.arg(2)?)?;
 
-    let receiver = receiver_ptr.get();
-    let arg = arg_ptr.get();
-    let result = receiver.value.as_string()? == arg.value.as_string()?;
+    let result = receiver_ptr.string_value()? == arg_ptr.string_value()?;
 
     let boolean = if result {
         machine.state.true_object.clone()
@@ -126,15 +122,12 @@ pub fn string_to_bytes(machine: &Machine,
                        -> InstructionResult {
     let register = instruction.arg(0)?;
     let string_ptr = process.get_register(instruction.arg(1)?)?;
-    let string_obj = string_ptr.get();
-
-    let array = string_obj.value
-        .as_string()?
+    let array = string_ptr.string_value()?
         .as_bytes()
         .iter()
         .map(|&b| {
-            process.allocate(object_value::integer(b as i64),
-                             machine.state.integer_prototype)
+            process.allocate_integer(b as i64,
+                                     machine.state.integer_prototype)
         })
         .collect::<Vec<_>>();
 
@@ -163,12 +156,11 @@ pub fn string_from_bytes(machine: &Machine,
     let register = instruction.arg(0)?;
     let arg_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let arg = arg_ptr.get();
-    let array = arg.value.as_array()?;
+    let array = arg_ptr.array_value()?;
     let mut bytes = Vec::with_capacity(array.len());
 
     for ptr in array.iter() {
-        let integer = ptr.get().value.as_integer()?;
+        let integer = ptr.integer_value()?;
 
         bytes.push(integer as u8);
     }
@@ -204,11 +196,10 @@ pub fn string_length(machine: &Machine,
     let register = instruction.arg(0)?;
     let arg_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let arg = arg_ptr.get();
     let int_proto = machine.state.integer_prototype.clone();
-    let length = arg.value.as_string()?.chars().count() as i64;
+    let length = arg_ptr.string_value()?.chars().count() as i64;
 
-    let obj = process.allocate(object_value::integer(length), int_proto);
+    let obj = process.allocate_integer(length, int_proto);
 
     process.set_register(register, obj);
 
@@ -229,11 +220,10 @@ pub fn string_size(machine: &Machine,
     let register = instruction.arg(0)?;
     let arg_ptr = process.get_register(instruction.arg(1)?)?;
 
-    let arg = arg_ptr.get();
     let int_proto = machine.state.integer_prototype.clone();
-    let size = arg.value.as_string()?.len() as i64;
+    let size = arg_ptr.string_value()?.len() as i64;
 
-    let obj = process.allocate(object_value::integer(size), int_proto);
+    let obj = process.allocate_integer(size, int_proto);
 
     process.set_register(register, obj);
 
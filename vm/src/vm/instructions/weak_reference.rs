@@ -0,0 +1,61 @@
+//! VM instruction handlers for weak references.
+use vm::action::Action;
+use vm::instruction::Instruction;
+use vm::instructions::result::InstructionResult;
+use vm::machine::Machine;
+
+use compiled_code::RcCompiledCode;
+use process::RcProcess;
+
+/// Creates a weak reference to an object.
+///
+/// A weak reference doesn't keep its target alive. Once the target has been
+/// garbage collected the weak reference is cleared.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the weak reference in.
+/// 2. The register containing the object to refer to.
+pub fn weak_reference_new(machine: &Machine,
+                          process: &RcProcess,
+                          _: &RcCompiledCode,
+                          instruction: &Instruction)
+                          -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let target = process.get_register(instruction.arg(1)?)?;
+    let proto = machine.state.weak_reference_prototype;
+    let obj = process.allocate_weak_reference(target, proto);
+
+    process.set_register(register, obj);
+
+    Ok(Action::None)
+}
+
+/// Gets the object a weak reference refers to.
+///
+/// This instruction takes 2 arguments:
+///
+/// 1. The register to store the object in.
+/// 2. The register containing the weak reference.
+///
+/// If the object has been garbage collected the "false" object is stored
+/// instead.
+pub fn weak_reference_get(machine: &Machine,
+                          process: &RcProcess,
+                          _: &RcCompiledCode,
+                          instruction: &Instruction)
+                          -> InstructionResult {
+    let register = instruction.arg(0)?;
+    let weak_ptr = process.get_register(instruction.arg(1)?)?;
+    let target = weak_ptr.weak_reference_value()?;
+
+    let result = if target.is_null() {
+        machine.state.false_object
+    } else {
+        target
+    };
+
+    process.set_register(register, result);
+
+    Ok(Action::None)
+}
@@ -1,23 +1,40 @@
 //! Virtual Machine for running instructions
 
 use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
 
 use binding::RcBinding;
 use bytecode_parser;
 use call_frame::CallFrame;
 use compiled_code::RcCompiledCode;
+use deadlock;
+use deterministic::Scheduler as DeterministicScheduler;
+use distribution;
 use execution_context::ExecutionContext;
 use gc::request::Request as GcRequest;
+use heap_limits::HeapLimits;
+use heap_snapshot::writer;
+use immix::copy_object::{self, CopyObject};
 use object_pointer::ObjectPointer;
 use object_value;
-use process::{RcProcess, Process};
+use mailbox::OverflowPolicy;
+use process::{ExitReason, RcProcess, Process, WaitReason};
 use pool::JoinGuard as PoolJoinGuard;
-use pools::{PRIMARY_POOL, SECONDARY_POOL};
+use errors;
+use pools::PRIMARY_POOL;
 use vm::action::Action;
 use vm::instruction::{Instruction, INSTRUCTION_MAPPING};
 use vm::instructions::result::InstructionResult;
 use vm::state::RcState;
 
+/// A job performed when running in deterministic mode.
+enum Job {
+    Process(RcProcess),
+    Collect(GcRequest),
+}
+
 pub struct Machine {
     pub state: RcState,
 }
@@ -31,17 +48,19 @@ impl Machine {
     ///
     /// This method will block the calling thread until it returns.
     pub fn start(&self, code: RcCompiledCode) -> Result<(), String> {
-        let primary_guard = self.start_primary_threads();
-        let gc_pool_guard = self.start_gc_threads();
+        self.start_node()?;
+        self.open_gc_log()?;
 
-        self.start_secondary_threads();
+        if let Some(seed) = self.state.config.scheduler_seed {
+            return self.start_deterministic(code, seed);
+        }
 
-        let main_process =
-            self.allocate_process(PRIMARY_POOL,
-                                  code,
-                                  self.state.top_level.clone())?;
+        let primary_guard = self.start_primary_threads();
+        let gc_pool_guard = self.start_gc_threads();
 
-        self.state.process_pools.schedule(main_process);
+        self.start_additional_threads();
+        self.start_deadlock_detector();
+        self.schedule_main_process(code)?;
 
         if primary_guard.join().is_err() {
             self.terminate();
@@ -55,9 +74,63 @@ impl Machine {
             return Err("Failed to join the GC pool".to_string());
         }
 
+        // Threads of additional pools may be blocked on IO, so we don't wait
+        // for them. Processes still running on these threads are left out of
+        // the heap snapshot.
+        self.write_heap_snapshot()?;
+
+        self.state.exit_status.lock().clone()
+    }
+
+    /// Starts the VM in deterministic mode.
+    ///
+    /// All processes and garbage collection requests are performed by the
+    /// calling thread, in an order determined by the given seed.
+    fn start_deterministic(&self,
+                           code: RcCompiledCode,
+                           seed: u64)
+                           -> Result<(), String> {
+        self.start_deadlock_detector();
+        self.schedule_main_process(code)?;
+
+        let mut scheduler = DeterministicScheduler::new(seed);
+        let pools = &self.state.process_pools;
+        let primary = pools.get(PRIMARY_POOL).unwrap();
+
+        while primary.inner.should_process() {
+            for pool_id in 0..pools.len() {
+                let jobs = pools.get(pool_id).unwrap().take_jobs();
+
+                scheduler.add(jobs.into_iter().map(Job::Process));
+            }
+
+            let requests = self.state.gc_pool.take_jobs();
+
+            scheduler.add(requests.into_iter().map(Job::Collect));
+
+            match scheduler.next() {
+                Some(Job::Process(process)) => self.run(&process),
+                Some(Job::Collect(request)) => request.perform(),
+                None => thread::yield_now(),
+            }
+        }
+
+        self.write_heap_snapshot()?;
+
         self.state.exit_status.lock().clone()
     }
 
+    fn schedule_main_process(&self, code: RcCompiledCode) -> Result<(), String> {
+        let main_process =
+            self.allocate_process(PRIMARY_POOL,
+                                  code,
+                                  self.state.top_level.clone())?;
+
+        self.state.process_pools.schedule(main_process);
+
+        Ok(())
+    }
+
     fn start_primary_threads(&self) -> PoolJoinGuard<()> {
         let machine = Machine::new(self.state.clone());
         let pool = self.state.process_pools.get(PRIMARY_POOL).unwrap();
@@ -65,11 +138,18 @@ impl Machine {
         pool.run(move |process| machine.run(&process))
     }
 
-    fn start_secondary_threads(&self) {
-        let machine = Machine::new(self.state.clone());
-        let pool = self.state.process_pools.get(SECONDARY_POOL).unwrap();
+    /// Starts the threads of all process pools except for the primary pool.
+    fn start_additional_threads(&self) {
+        for pool_id in 0..self.state.process_pools.len() {
+            if pool_id == PRIMARY_POOL {
+                continue;
+            }
+
+            let machine = Machine::new(self.state.clone());
+            let pool = self.state.process_pools.get(pool_id).unwrap();
 
-        pool.run(move |process| machine.run(&process));
+            pool.run(move |process| machine.run(&process));
+        }
     }
 
     /// Starts the garbage collection threads.
@@ -77,9 +157,102 @@ impl Machine {
         self.state.gc_pool.run(move |request| request.perform())
     }
 
-    fn terminate(&self) {
+    /// Starts accepting messages from other nodes, if distribution is
+    /// enabled.
+    fn start_node(&self) -> Result<(), String> {
+        if let Some(node) = self.state.node.as_ref() {
+            let listener = node.bind()
+                .map_err(|error| {
+                    format!("Failed to start node {}: {}", node.name, error)
+                })?;
+
+            distribution::start_listener(self.state.clone(), listener);
+        }
+
+        Ok(())
+    }
+
+    /// Opens the garbage collection log, if enabled.
+    fn open_gc_log(&self) -> Result<(), String> {
+        if let Some(log) = self.state.gc_log.as_ref() {
+            log.open()
+                .map_err(|error| {
+                    format!("Failed to open the GC log {}: {}",
+                            log.target,
+                            error)
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts a thread that periodically checks if all processes are
+    /// deadlocked, terminating the VM if so.
+    ///
+    /// Deadlocks are not detected when distribution is enabled, as processes
+    /// may receive messages from other nodes at any time.
+    fn start_deadlock_detector(&self) {
+        let interval = self.state.config.deadlock_check_interval;
+
+        if interval == 0 || self.state.node.is_some() {
+            return;
+        }
+
+        let state = self.state.clone();
+
+        thread::spawn(move || {
+            let machine = Machine::new(state);
+            let pool = machine.state.process_pools.get(PRIMARY_POOL).unwrap();
+            let mut detector = deadlock::Detector::new();
+
+            while pool.inner.should_process() {
+                thread::sleep(Duration::from_millis(interval));
+
+                let table = &machine.state.process_table;
+
+                if let Some(blocked) = detector.check(table) {
+                    let report = deadlock::report(&blocked);
+
+                    machine.state.deadlocked.store(true, Ordering::Release);
+                    *machine.state.exit_status.lock() = Err(report);
+
+                    machine.terminate();
+
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Writes a heap snapshot of all processes, if enabled.
+    ///
+    /// Processes that are still running, such as processes blocked on IO, are
+    /// left out as their heaps may change while the snapshot is written.
+    fn write_heap_snapshot(&self) -> Result<(), String> {
+        let path = match self.state.config.heap_snapshot.as_ref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut processes = Vec::new();
+
+        if let Some(process) = self.state.finished_main_process.lock().take() {
+            processes.push(process);
+        }
+
+        writer::write_file_with_suspended(&self.state, processes, path)
+            .map_err(|error| {
+                format!("Failed to write the heap snapshot {}: {}", path, error)
+            })
+    }
+
+    pub fn terminate(&self) {
         self.state.process_pools.terminate();
         self.state.gc_pool.terminate();
+
+        if let Some(node) = self.state.node.as_ref() {
+            node.cleanup();
+        }
     }
 
     /// Allocates a new process and returns the PID and Process structure.
@@ -99,11 +272,38 @@ impl Machine {
                                          self_obj,
                                          self.state.global_allocator.clone());
 
+        process.set_mailbox_capacity(self.state.config.mailbox_capacity);
+        process.set_heap_limits(HeapLimits::from_config(&self.state.config));
         process_table.map(pid, process.clone());
 
         Ok(process)
     }
 
+    /// Returns a version of the given prototype that can be shared by all
+    /// processes.
+    ///
+    /// Prototypes are created by definitions and shared by all objects created
+    /// from them. Process-local prototypes are copied into the permanent space
+    /// the first time they're used, allowing messages to refer to them instead
+    /// of copying them. Methods defined on a prototype from then on are
+    /// allocated in the permanent space as well.
+    pub fn shared_prototype(&self,
+                            proto: ObjectPointer)
+                            -> Result<ObjectPointer, String> {
+        let proto = proto.as_prototype(&self.state);
+
+        if proto.is_permanent() {
+            Ok(proto)
+        } else {
+            self.state.permanent_allocator.lock().copy_object(proto)
+        }
+    }
+
+    /// Allocates a method object for the given code.
+    ///
+    /// Methods defined on permanent objects, such as prototypes, are allocated
+    /// in the permanent space so messages refer to them instead of copying
+    /// them. Other methods are allocated on the process heap.
     pub fn allocate_method(&self,
                            process: &RcProcess,
                            receiver: &ObjectPointer,
@@ -129,6 +329,17 @@ impl Machine {
         process.running();
 
         'exec_loop: loop {
+            // A process may have been killed while it was suspended, in which
+            // case we don't want to execute any further instructions.
+            if process.should_terminate() {
+                return Ok(());
+            }
+
+            // The instruction index of the current context is stored at this
+            // point, so finalizers that have to run in this process can run
+            // on top of it.
+            process.enter_local_finalizers();
+
             let code = process.compiled_code();
             let mut index = process.instruction_index();
             let count = code.instructions.len();
@@ -141,17 +352,50 @@ impl Machine {
 
             while index < count {
                 let ref instruction = code.instructions[index];
+
+                // Blocking instructions are executed on the blocking pool so
+                // they don't block the threads of other pools. Once done the
+                // process is moved back to its own pool.
+                if let Some(pool_id) = self.pool_for_instruction(process,
+                                                                 instruction) {
+                    context.instruction_index = index;
+                    process.move_to_pool(pool_id);
+                    process.suspend();
+
+                    return Ok(());
+                }
+
                 let mapping_index = instruction.instruction_type as usize;
                 let func = INSTRUCTION_MAPPING[mapping_index];
 
                 index += 1;
 
                 match func(self, process, &code, instruction)? {
-                    Action::Goto(new_index) => index = new_index,
+                    Action::Goto(new_index) => {
+                        // Jumping backwards is used for loops, which could
+                        // otherwise run forever without being preempted.
+                        let backwards = new_index < index;
+
+                        index = new_index;
+
+                        if backwards && !consume_reduction(&mut reductions) {
+                            context.instruction_index = index;
+
+                            self.preempt(process);
+
+                            return Ok(());
+                        }
+                    }
                     Action::Return => break,
                     Action::EnterContext => {
                         context.instruction_index = index;
 
+                        if !consume_reduction(&mut reductions) {
+                            self.preempt(process);
+
+                            return Ok(());
+                        }
+
                         continue 'exec_loop;
                     }
                     Action::Suspend => {
@@ -160,6 +404,7 @@ impl Machine {
 
                         return Ok(());
                     }
+                    Action::Terminate => return Ok(()),
                     _ => {}
                 }
             } // while
@@ -195,9 +440,7 @@ impl Machine {
             }
 
             // Reduce once we've exhausted all the instructions in a context.
-            if reductions > 0 {
-                reductions -= 1;
-            } else {
+            if !consume_reduction(&mut reductions) {
                 process.suspend();
 
                 return Ok(());
@@ -327,12 +570,10 @@ impl Machine {
         let receiver_ptr = process.get_register(instruction.arg(1)?)?;
         let rest_arg = instruction.arg(3)? == 1;
 
-        let method_ptr = receiver_ptr.get()
-            .lookup_method(name)
+        let method_ptr = receiver_ptr.lookup_method(&self.state, name)
             .ok_or_else(|| format!("undefined method \"{}\"", name))?;
 
-        let method_obj = method_ptr.get();
-        let method_code = method_obj.value.as_compiled_code()?;
+        let method_code = method_ptr.compiled_code_value()?;
 
         // Argument handling
         let arg_count = instruction.arguments.len() - 4;
@@ -345,9 +586,7 @@ impl Machine {
         // Unpack the last argument if it's a rest argument
         if rest_arg {
             if let Some(last_arg) = arguments.pop() {
-                let array = last_arg.get();
-
-                for value in array.value.as_array()? {
+                for value in last_arg.array_value()? {
                     arguments.push(value.clone());
                 }
             }
@@ -422,28 +661,167 @@ impl Machine {
         Ok(args)
     }
 
+    /// Sends a message to another process.
+    ///
+    /// If the mailbox of the receiver is full the configured mailbox policy is
+    /// applied. The return value is the object to store in the result register
+    /// of the sender, or None if the sender should be suspended until there is
+    /// room in the mailbox.
+    ///
+    /// An error is returned if the message has to be copied but contains
+    /// values that can't be copied, such as files.
+    pub fn send_process_message(&self,
+                                sender: &RcProcess,
+                                receiver: &RcProcess,
+                                message: ObjectPointer)
+                                -> Option<ObjectPointer> {
+        if sender.pid != receiver.pid &&
+           copy_object::ensure_copyable(message).is_err() {
+            let error = object_value::error(errors::process::unsendable());
+
+            return Some(sender.allocate_without_prototype(error));
+        }
+
+        if receiver.send_message(sender, message) {
+            sender.stop_waiting();
+
+            return Some(message);
+        }
+
+        match self.state.config.mailbox_policy {
+            OverflowPolicy::Suspend => {
+                let reason = WaitReason::MailboxSpace(receiver.clone());
+
+                if sender.start_waiting(reason) {
+                    receiver.sender_blocked();
+                }
+
+                None
+            }
+            OverflowPolicy::Error => {
+                let code = errors::process::mailbox_full();
+
+                Some(sender.allocate_without_prototype(object_value::error(code)))
+            }
+            OverflowPolicy::Drop => {
+                receiver.message_dropped();
+
+                Some(message)
+            }
+        }
+    }
+
     /// Spawns a new process.
+    ///
+    /// The mailbox capacity defaults to the capacity set in the configuration.
+    ///
+    /// The self object (if any) and arguments are copied into the heap of the
+    /// new process, just like messages. The arguments are bound as the local
+    /// variables of the new process, in the same order.
+    ///
+    /// If the pool ID is invalid, or the self object or any of the arguments
+    /// can't be copied, an error object is stored in the register instead of a
+    /// PID.
     pub fn spawn_process(&self,
                          process: &RcProcess,
                          pool_id: usize,
                          code: RcCompiledCode,
+                         self_obj: Option<ObjectPointer>,
+                         mailbox_capacity: Option<usize>,
+                         heap_limits: HeapLimits,
+                         arguments: &[ObjectPointer],
                          register: usize)
                          -> Result<(), String> {
+        if !self.state.process_pools.is_valid(pool_id) {
+            let code = errors::process::invalid_pool();
+            let error =
+                process.allocate_without_prototype(object_value::error(code));
+
+            process.set_register(register, error);
+
+            return Ok(());
+        }
+
+        if arguments.len() > code.arguments as usize {
+            return Err(format!("{} accepts up to {} arguments, but {} \
+                                arguments were given",
+                               code.name,
+                               code.arguments,
+                               arguments.len()));
+        }
+
+        if arguments.len() < code.required_arguments as usize {
+            return Err(format!("{} requires {} arguments, but {} arguments \
+                                were given",
+                               code.name,
+                               code.required_arguments,
+                               arguments.len()));
+        }
+
+        let copyable = self_obj.iter()
+            .chain(arguments.iter())
+            .all(|pointer| copy_object::ensure_copyable(*pointer).is_ok());
+
+        if !copyable {
+            let code = errors::process::unsendable();
+            let error =
+                process.allocate_without_prototype(object_value::error(code));
+
+            process.set_register(register, error);
+
+            return Ok(());
+        }
+
         let new_proc =
             self.allocate_process(pool_id, code, self.state.top_level.clone())?;
 
+        if let Some(obj) = self_obj {
+            new_proc.set_self_object(new_proc.copy_object(obj)?);
+        }
+
+        if let Some(capacity) = mailbox_capacity {
+            new_proc.set_mailbox_capacity(capacity);
+        }
+
+        new_proc.set_heap_limits(heap_limits);
+
+        for (index, argument) in arguments.iter().enumerate() {
+            new_proc.set_local(index, new_proc.copy_object(*argument)?);
+        }
+
         let new_pid = new_proc.pid;
 
         self.state.process_pools.schedule(new_proc);
 
-        let pid_obj = process.allocate(object_value::integer(new_pid as i64),
-                                       self.state.integer_prototype.clone());
+        let proto = self.state.integer_prototype.clone();
+        let pid_obj = process.allocate_integer(new_pid as i64, proto);
 
         process.set_register(register, pid_obj);
 
         Ok(())
     }
 
+    /// Returns the ID of the pool to move a process to before running the
+    /// given instruction, if the process has to be moved at all.
+    fn pool_for_instruction(&self,
+                            process: &RcProcess,
+                            instruction: &Instruction)
+                            -> Option<usize> {
+        let current_pool = process.current_pool_id();
+
+        if instruction.instruction_type.is_blocking() {
+            let blocking_pool = self.state.process_pools.blocking_pool();
+
+            if current_pool != blocking_pool {
+                return Some(blocking_pool);
+            }
+        } else if current_pool != process.pool_id {
+            return Some(process.pool_id);
+        }
+
+        None
+    }
+
     /// Checks if a garbage collection run should be scheduled for the given
     /// process.
     fn gc_safepoint(&self, process: &RcProcess) {
@@ -451,9 +829,19 @@ impl Machine {
             return;
         }
 
-        let request_opt = if process.should_collect_young_generation() {
+        let request_opt = if self.state.config.gc_stress {
+            // Every third collection is a mailbox collection, ensuring both
+            // the heap and the mailbox are collected frequently.
+            if process.collections() % 3 == 2 {
+                Some(GcRequest::mailbox(self.state.clone(), process.clone()))
+            } else {
+                Some(GcRequest::heap(self.state.clone(), process.clone()))
+            }
+        } else if process.heap_limit_exceeded() ||
+                  process.should_collect_young_generation() {
             Some(GcRequest::heap(self.state.clone(), process.clone()))
-        } else if process.should_collect_mailbox() {
+        } else if process.mailbox_limit_exceeded() ||
+                  process.should_collect_mailbox() {
             Some(GcRequest::mailbox(self.state.clone(), process.clone()))
         } else {
             None
@@ -466,29 +854,373 @@ impl Machine {
         }
     }
 
+    /// Suspends a process that has run out of reductions.
+    ///
+    /// A garbage collection is scheduled first if needed, as a process may run
+    /// for a long time without ever reaching the end of an execution context.
+    fn preempt(&self, process: &RcProcess) {
+        self.gc_safepoint(process);
+
+        process.suspend();
+    }
+
     /// Executes a process and handles its result.
     fn run(&self, process: &RcProcess) {
         match self.run_process(&process) {
             Ok(_) => {
                 if process.should_suspend_for_gc() {
                     process.suspend_for_gc();
+                } else if process.should_terminate() {
+                    self.finish_process(process);
                 } else if process.should_be_rescheduled() {
                     self.state.process_pools.schedule(process.clone());
                 } else {
-                    let is_main = process.is_main();
-
-                    process.finished();
+                    self.finish_process(process);
+                }
+            }
+            Err(message) => self.error(process, message),
+        };
+    }
 
-                    write_lock!(self.state.process_table).release(&process.pid);
+    /// Marks a process as finished and removes it from the VM.
+    ///
+    /// The memory of the process is returned to the global allocator once the
+    /// last reference to the process is dropped.
+    fn finish_process(&self, process: &RcProcess) {
+        let is_main = process.is_main();
+
+        process.finished();
+
+        // Processes this process is waiting for may refer back to it, so we
+        // need to release these to prevent reference cycles.
+        process.stop_waiting();
+
+        // The PID is released first so no new names can be registered for
+        // the process (or groups joined) once these have been removed.
+        write_lock!(self.state.process_table).release(&process.pid);
+        write_lock!(self.state.process_registry).unregister_pid(&process.pid);
+        write_lock!(self.state.process_groups).leave_all(&process.pid);
+
+        // Terminate once the main process has finished execution.
+        if is_main {
+            if self.state.config.heap_snapshot.is_some() {
+                *self.state.finished_main_process.lock() = Some(process.clone());
+            }
 
-                    // Terminate once the main process has finished
-                    // execution.
-                    if is_main {
-                        self.terminate();
-                    }
+            match process.exit_reason() {
+                ExitReason::Exit(status) if status != 0 => {
+                    *self.state.exit_status.lock() =
+                        Err(format!("The main process exited with status {}",
+                                    status));
                 }
+                ExitReason::OutOfMemory => {
+                    *self.state.exit_status.lock() =
+                        Err("The main process ran out of memory".to_string());
+                }
+                _ => {}
             }
-            Err(message) => self.error(process, message),
+
+            self.terminate();
+        }
+    }
+}
+
+/// Consumes a single reduction.
+///
+/// Returns false if no reductions were left, in which case the process should
+/// be suspended.
+fn consume_reduction(reductions: &mut usize) -> bool {
+    if *reductions > 0 {
+        *reductions -= 1;
+
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binding::Binding;
+    use compiled_code::CompiledCode;
+    use config::Config;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use vm::instruction::InstructionType;
+    use vm::state::State;
+
+    fn machine() -> Machine {
+        let mut config = Config::new();
+
+        config.set_primary_threads(1);
+        config.set_reductions(10);
+
+        Machine::new(State::new(config))
+    }
+
+    /// Returns a CompiledCode that increments register 0 forever.
+    fn busy_loop() -> RcCompiledCode {
+        let instructions = vec![
+            Instruction::new(InstructionType::SetInteger, vec![0, 0], 1, 1),
+            Instruction::new(InstructionType::SetInteger, vec![1, 1], 1, 1),
+            Instruction::new(InstructionType::IntegerAdd, vec![0, 0, 1], 2, 1),
+            Instruction::new(InstructionType::Goto, vec![2], 3, 1),
+        ];
+
+        let mut code = CompiledCode::new("loop".to_string(),
+                                         "test.inko".to_string(),
+                                         1,
+                                         instructions);
+
+        code.integer_literals = vec![0, 1];
+
+        Arc::new(code)
+    }
+
+    fn counter(process: &RcProcess) -> i64 {
+        process.get_register(0).unwrap().integer_value().unwrap()
+    }
+
+    /// Returns true if the counter of a process running on another thread
+    /// exceeds the given value.
+    ///
+    /// The counter can only be read while the process is not running.
+    fn made_progress(process: &RcProcess, value: i64) -> bool {
+        let status = match process.suspend_for_snapshot() {
+            Some(status) => status,
+            None => return false,
         };
+
+        let progress = process.get_register_option(0)
+            .map(|pointer| pointer.integer_value().unwrap() > value)
+            .unwrap_or(false);
+
+        process.resume_after_snapshot(status);
+
+        progress
+    }
+
+    #[test]
+    fn test_consume_reduction() {
+        let mut reductions = 1;
+
+        assert!(consume_reduction(&mut reductions));
+        assert_eq!(reductions, 0);
+        assert_eq!(consume_reduction(&mut reductions), false);
+    }
+
+    #[test]
+    fn test_run_process_preempts_backward_jumps() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let process =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        assert!(machine.run_process(&process).is_ok());
+        assert!(process.should_be_rescheduled());
+
+        // The loop body runs once before the first jump, and once for every
+        // reduction.
+        assert_eq!(counter(&process), 11);
+    }
+
+    #[test]
+    fn test_busy_loops_make_progress_on_a_single_thread() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+
+        let process1 =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let process2 =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let guard = machine.start_primary_threads();
+        let deadline = Instant::now() + Duration::from_secs(30);
+
+        machine.state.process_pools.schedule(process1.clone());
+        machine.state.process_pools.schedule(process2.clone());
+
+        // Both processes loop forever, so the only way for both counters to
+        // advance past a few time slices is for the pool's only thread to
+        // preempt them.
+        while !made_progress(&process1, 33) || !made_progress(&process2, 33) {
+            assert!(Instant::now() < deadline);
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(process1.is_alive());
+        assert!(process2.is_alive());
+
+        machine.terminate();
+
+        assert!(guard.join().is_ok());
+    }
+
+    #[test]
+    fn test_spawn_process_with_arguments() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let parent =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let mut code = CompiledCode::new("worker".to_string(),
+                                         "test.inko".to_string(),
+                                         1,
+                                         Vec::new());
+
+        code.arguments = 1;
+        code.required_arguments = 1;
+
+        let argument =
+            parent.allocate_without_prototype(object_value::integer(5));
+        let new_self = parent.allocate_empty();
+        let mut limits = HeapLimits::unlimited();
+
+        limits.young = 3;
+
+        machine.spawn_process(&parent,
+                              PRIMARY_POOL,
+                              Arc::new(code),
+                              Some(new_self),
+                              Some(2),
+                              limits,
+                              &[argument],
+                              0)
+            .unwrap();
+
+        let pid = counter(&parent) as usize;
+        let child = read_lock!(machine.state.process_table).get(&pid).unwrap();
+        let local = child.get_local(0).unwrap();
+
+        assert!(local != argument);
+        assert_eq!(local.get().value.as_integer().unwrap(), 5);
+        assert!(child.self_object() != new_self);
+        assert!(child.self_object() != self_obj);
+        assert_eq!(child.info(true).mailbox_capacity, Some(2));
+        assert_eq!(child.local_data().heap_limits.young, 3);
+    }
+
+    #[test]
+    fn test_spawn_process_with_too_many_arguments() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let parent =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let argument = parent.allocate_empty();
+
+        assert!(machine.spawn_process(&parent,
+                                      PRIMARY_POOL,
+                                      busy_loop(),
+                                      None,
+                                      None,
+                                      HeapLimits::unlimited(),
+                                      &[argument],
+                                      0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_spawn_process_with_uncopyable_argument() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let parent =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let binding = Binding::new(self_obj);
+        let argument =
+            parent.allocate_without_prototype(object_value::binding(binding));
+
+        let mut code = CompiledCode::new("worker".to_string(),
+                                         "test.inko".to_string(),
+                                         1,
+                                         Vec::new());
+
+        code.arguments = 1;
+
+        machine.spawn_process(&parent,
+                              PRIMARY_POOL,
+                              Arc::new(code),
+                              None,
+                              None,
+                              HeapLimits::unlimited(),
+                              &[argument],
+                              0)
+            .unwrap();
+
+        let result = parent.get_register(0).unwrap();
+
+        assert!(result.get().value.is_error());
+        assert_eq!(read_lock!(machine.state.process_table).pids().len(), 1);
+    }
+
+    #[test]
+    fn test_send_process_message_with_uncopyable_message() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let sender =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let receiver =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let binding = Binding::new(self_obj);
+        let message =
+            sender.allocate_without_prototype(object_value::binding(binding));
+
+        let result =
+            machine.send_process_message(&sender, &receiver, message).unwrap();
+
+        assert!(result.get().value.is_error());
+        assert!(receiver.receive_message().is_none());
+
+        // Messages sent to the current process are not copied.
+        let result =
+            machine.send_process_message(&sender, &sender, message).unwrap();
+
+        assert!(result == message);
+    }
+
+    #[test]
+    fn test_allocate_method_with_permanent_receiver() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let process =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let method = machine.allocate_method(&process, &self_obj, busy_loop());
+
+        assert!(method.is_permanent());
+        assert!(method.get().prototype().unwrap() ==
+                machine.state.method_prototype);
+    }
+
+    #[test]
+    fn test_allocate_method_with_process_receiver() {
+        let machine = machine();
+        let self_obj = machine.state.top_level;
+        let process =
+            machine.allocate_process(PRIMARY_POOL, busy_loop(), self_obj)
+                .unwrap();
+
+        let receiver = process.allocate_empty();
+        let method = machine.allocate_method(&process, &receiver, busy_loop());
+
+        assert!(method.is_young());
+        assert!(method.get().prototype().unwrap() ==
+                machine.state.method_prototype);
     }
 }
@@ -6,17 +6,23 @@
 
 use parking_lot::Mutex;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::AtomicBool;
 use std::collections::HashSet;
 
+use gc::log::Log as GcLog;
 use gc::request::Request;
+use gc::statistics::Statistics as GcStatistics;
 
 use immix::global_allocator::{GlobalAllocator, RcGlobalAllocator};
 use immix::permanent_allocator::PermanentAllocator;
 
 use config::Config;
+use distribution::Node;
 use object_pointer::ObjectPointer;
 use pool::Pool;
 use pools::Pools;
+use process_groups::ProcessGroups;
+use process_registry::ProcessRegistry;
 use process_table::ProcessTable;
 use process::RcProcess;
 
@@ -33,6 +39,12 @@ pub struct State {
     /// Table containing all processes.
     pub process_table: RwLock<ProcessTable<RcProcess>>,
 
+    /// Registry mapping process names to PIDs.
+    pub process_registry: RwLock<ProcessRegistry>,
+
+    /// The groups of processes used for broadcasting messages.
+    pub process_groups: RwLock<ProcessGroups>,
+
     /// The pool to use for garbage collection.
     pub gc_pool: Pool<Request>,
 
@@ -42,6 +54,20 @@ pub struct State {
     /// The exit status of the program.
     pub exit_status: Mutex<Result<(), String>>,
 
+    /// Set to true when the program was terminated because of a deadlock.
+    pub deadlocked: AtomicBool,
+
+    /// The main process after it finished, kept around so it can be included
+    /// in the heap snapshot written when the VM terminates.
+    pub finished_main_process: Mutex<Option<RcProcess>>,
+
+    /// The aggregated profiles of all garbage collections.
+    pub gc_statistics: Mutex<GcStatistics>,
+
+    /// The log to write the details of every garbage collection to, if
+    /// enabled.
+    pub gc_log: Option<GcLog>,
+
     /// The permanent memory allocator, used for global data.
     pub permanent_allocator: Mutex<Box<PermanentAllocator>>,
 
@@ -81,16 +107,31 @@ pub struct State {
     /// The prototype for binding objects.
     pub binding_prototype: ObjectPointer,
 
+    /// The prototype for channel objects.
+    pub channel_prototype: ObjectPointer,
+
+    /// The prototype for weak reference objects.
+    pub weak_reference_prototype: ObjectPointer,
+
     /// The singleton "true" object.
     pub true_object: ObjectPointer,
 
     /// The singleton "false" object.
     pub false_object: ObjectPointer,
+
+    /// Permanent objects that exist in every VM, in a fixed order. Serialized
+    /// messages refer to these objects by their index.
+    pub shared_objects: Vec<ObjectPointer>,
+
+    /// The node used for exchanging messages with other VM instances, if
+    /// distribution is enabled.
+    pub node: Option<Node>,
 }
 
 impl State {
     pub fn new(config: Config) -> RcState {
-        let global_alloc = GlobalAllocator::new();
+        let global_alloc = GlobalAllocator::with_limits(config.free_block_limit,
+                                                        config.block_limit);
 
         // Boxed since moving around the allocator can break pointers from the
         // blocks back to the allocator's bucket.
@@ -108,6 +149,8 @@ impl State {
         let method_proto = perm_alloc.allocate_empty();
         let cc_proto = perm_alloc.allocate_empty();
         let binding_proto = perm_alloc.allocate_empty();
+        let channel_proto = perm_alloc.allocate_empty();
+        let weak_ref_proto = perm_alloc.allocate_empty();
 
         let true_obj = perm_alloc.allocate_empty();
         let false_obj = perm_alloc.allocate_empty();
@@ -119,16 +162,47 @@ impl State {
 
         let gc_pool = Pool::new(config.gc_threads);
 
-        let process_pools = Pools::new(config.primary_threads,
-                                       config.secondary_threads);
+        let process_pools = Pools::with_config(&config);
+
+        let shared_objects = vec![top_level,
+                                  integer_proto,
+                                  float_proto,
+                                  string_proto,
+                                  array_proto,
+                                  true_proto,
+                                  false_proto,
+                                  file_proto,
+                                  method_proto,
+                                  cc_proto,
+                                  binding_proto,
+                                  true_obj,
+                                  false_obj,
+                                  channel_proto,
+                                  weak_ref_proto];
+
+        let node = config.node_name
+            .as_ref()
+            .and_then(|name| {
+                Node::new(name.clone(), config.node_directory.clone()).ok()
+            });
+
+        let gc_log = config.gc_log
+            .as_ref()
+            .map(|target| GcLog::new(target.clone()));
 
         let state = State {
             config: config,
             executed_files: RwLock::new(HashSet::new()),
             process_table: RwLock::new(ProcessTable::new()),
+            process_registry: RwLock::new(ProcessRegistry::new()),
+            process_groups: RwLock::new(ProcessGroups::new()),
             process_pools: process_pools,
             gc_pool: gc_pool,
             exit_status: Mutex::new(Ok(())),
+            deadlocked: AtomicBool::new(false),
+            finished_main_process: Mutex::new(None),
+            gc_statistics: Mutex::new(GcStatistics::new()),
+            gc_log: gc_log,
             permanent_allocator: Mutex::new(perm_alloc),
             global_allocator: global_alloc,
             top_level: top_level,
@@ -142,8 +216,12 @@ impl State {
             method_prototype: method_proto,
             compiled_code_prototype: cc_proto,
             binding_prototype: binding_proto,
+            channel_prototype: channel_proto,
+            weak_reference_prototype: weak_ref_proto,
             true_object: true_obj,
             false_object: false_obj,
+            shared_objects: shared_objects,
+            node: node,
         };
 
         Arc::new(state)